@@ -0,0 +1,780 @@
+// Alert rules from the config, e.g. `cpu.avg(5m) > 80 && load1 > ncpu`.
+//
+// A rule is a condition over the latest sample. `cpu` and `mem` can also be
+// summarised over a window with avg, min or max, using the chart history.
+// Each rule can require its condition to hold `for` a while before firing,
+// and a separate `clear` condition gives hysteresis, otherwise an alert
+// clears as soon as its condition stops holding.
+
+use serde::Deserialize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Metric {
+    // Percentages
+    Cpu,
+    Memory,
+    Swap,
+    Steal,
+    // Share of the last 10 seconds stalled on memory, from PSI
+    Pressure,
+    Conntrack,
+    // Of whichever PID limit is closest to running out
+    Pids,
+    // TCP segments retransmitted per second
+    Retransmits,
+    Load1,
+    Load5,
+    Load15,
+    Cpus,
+}
+
+impl Metric {
+    fn parse(name: &str) -> Option<Self> {
+        let metric = match name {
+            "cpu" => Metric::Cpu,
+            "mem" => Metric::Memory,
+            "swap" => Metric::Swap,
+            "steal" => Metric::Steal,
+            "pressure" => Metric::Pressure,
+            "conntrack" => Metric::Conntrack,
+            "pids" => Metric::Pids,
+            "retrans" => Metric::Retransmits,
+            "load1" => Metric::Load1,
+            "load5" => Metric::Load5,
+            "load15" => Metric::Load15,
+            "ncpu" => Metric::Cpus,
+            _ => return None,
+        };
+        Some(metric)
+    }
+
+    fn has_history(self) -> bool {
+        matches!(self, Metric::Cpu | Metric::Memory)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Aggregate {
+    Average,
+    Minimum,
+    Maximum,
+}
+
+impl Aggregate {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "avg" => Some(Aggregate::Average),
+            "min" => Some(Aggregate::Minimum),
+            "max" => Some(Aggregate::Maximum),
+            _ => None,
+        }
+    }
+
+    pub fn apply(self, values: impl Iterator<Item = f64>) -> Option<f64> {
+        let mut count = 0;
+        let mut result: Option<f64> = None;
+        for value in values {
+            count += 1;
+            result = Some(match (self, result) {
+                (_, None) => value,
+                (Aggregate::Average, Some(sum)) => sum + value,
+                (Aggregate::Minimum, Some(min)) => min.min(value),
+                (Aggregate::Maximum, Some(max)) => max.max(value),
+            });
+        }
+        match self {
+            Aggregate::Average => result.map(|sum| sum / count as f64),
+            _ => result,
+        }
+    }
+}
+
+// Where rules get their numbers from, None when a value isn't available
+pub trait Source {
+    fn current(&self, metric: Metric) -> Option<f64>;
+    fn window(&self, metric: Metric, aggregate: Aggregate, seconds: f64) -> Option<f64>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Arithmetic {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+#[derive(Debug, Clone)]
+enum Value {
+    Number(f64),
+    Metric(Metric),
+    Window(Metric, Aggregate, f64),
+    Negate(Box<Value>),
+    Binary(Box<Value>, Arithmetic, Box<Value>),
+}
+
+impl Value {
+    fn evaluate(&self, source: &impl Source) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Metric(metric) => source.current(*metric),
+            Value::Window(metric, aggregate, seconds) => {
+                source.window(*metric, *aggregate, *seconds)
+            }
+            Value::Negate(value) => value.evaluate(source).map(|v| -v),
+            Value::Binary(left, op, right) => {
+                let (left, right) = (left.evaluate(source)?, right.evaluate(source)?);
+                match op {
+                    Arithmetic::Add => Some(left + right),
+                    Arithmetic::Subtract => Some(left - right),
+                    Arithmetic::Multiply => Some(left * right),
+                    Arithmetic::Divide => Some(left / right).filter(|v| v.is_finite()),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Condition(Node);
+
+#[derive(Debug, Clone)]
+enum Node {
+    Compare(Value, Comparison, Value),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+impl Value {
+    fn metrics(&self, metrics: &mut Vec<Metric>) {
+        match self {
+            Value::Number(_) => {}
+            Value::Metric(metric) | Value::Window(metric, _, _) => metrics.push(*metric),
+            Value::Negate(value) => value.metrics(metrics),
+            Value::Binary(left, _, right) => {
+                left.metrics(metrics);
+                right.metrics(metrics);
+            }
+        }
+    }
+}
+
+impl Node {
+    fn metrics(&self, metrics: &mut Vec<Metric>) {
+        match self {
+            Node::Compare(left, _, right) => {
+                left.metrics(metrics);
+                right.metrics(metrics);
+            }
+            Node::Not(node) => node.metrics(metrics),
+            Node::And(left, right) | Node::Or(left, right) => {
+                left.metrics(metrics);
+                right.metrics(metrics);
+            }
+        }
+    }
+
+    // Comparisons with missing values are false rather than errors, so a rule
+    // on swap simply never fires on a host without any
+    fn evaluate(&self, source: &impl Source) -> bool {
+        match self {
+            Node::Compare(left, op, right) => {
+                let (left, right) = match (left.evaluate(source), right.evaluate(source)) {
+                    (Some(left), Some(right)) => (left, right),
+                    _ => return false,
+                };
+                match op {
+                    Comparison::Greater => left > right,
+                    Comparison::GreaterOrEqual => left >= right,
+                    Comparison::Less => left < right,
+                    Comparison::LessOrEqual => left <= right,
+                    Comparison::Equal => left == right,
+                    Comparison::NotEqual => left != right,
+                }
+            }
+            Node::Not(node) => !node.evaluate(source),
+            Node::And(left, right) => left.evaluate(source) && right.evaluate(source),
+            Node::Or(left, right) => left.evaluate(source) || right.evaluate(source),
+        }
+    }
+}
+
+impl Condition {
+    pub fn evaluate(&self, source: &impl Source) -> bool {
+        self.0.evaluate(source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    // In seconds, written as a number directly followed by s, m or h
+    Duration(f64),
+    Name(String),
+    Symbol(&'static str),
+}
+
+// Longest first so `>=` isn't read as `>` then `=`
+const SYMBOLS: [&str; 16] = [
+    "&&", "||", ">=", "<=", "==", "!=", ">", "<", "!", "+", "-", "*", "/", "(", ")", ".",
+];
+
+fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+    while !rest.is_empty() {
+        if let Some(symbol) = SYMBOLS.iter().find(|s| rest.starts_with(**s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '.')
+                .unwrap_or(rest.len());
+            let word = &rest[..end];
+            tokens.push(match parse_duration(word) {
+                Some(seconds) if word.ends_with(char::is_alphabetic) => Token::Duration(seconds),
+                _ => Token::Number(
+                    word.parse()
+                        .map_err(|_| format!("`{}` isn't a number or duration", word))?,
+                ),
+            });
+            rest = &rest[end..];
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            let unexpected = rest.chars().next().unwrap_or_default();
+            return Err(format!("unexpected `{}`", unexpected));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+// Durations like 30s, 5m or 1h, a bare number is seconds
+pub fn parse_duration(text: &str) -> Option<f64> {
+    let (number, unit) = match text.find(|c: char| c.is_ascii_alphabetic()) {
+        Some(index) => text.split_at(index),
+        None => (text, "s"),
+    };
+    let scale = match unit {
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 60.0 * 60.0,
+        _ => return None,
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|n| *n >= 0.0)
+        .map(|n| n * scale)
+}
+
+// Recursive descent, loosest binding first:
+//   condition := conjunction ("||" conjunction)*
+//   conjunction := negation ("&&" negation)*
+//   negation := "!" negation | comparison | "(" condition ")"
+//   comparison := sum (">" | ">=" | "<" | "<=" | "==" | "!=") sum
+//   sum := product (("+" | "-") product)*
+//   product := factor (("*" | "/") factor)*
+//   factor := number | metric | metric "." aggregate "(" duration ")" | "-" factor | "(" sum ")"
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        if self.peek() == Some(&Token::Symbol(symbol)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("expected `{}` {}", symbol, self.found()))
+        }
+    }
+
+    fn found(&self) -> String {
+        match self.peek() {
+            Some(Token::Number(number)) => format!("before `{}`", number),
+            Some(Token::Duration(seconds)) => format!("before a duration of {}s", seconds),
+            Some(Token::Name(name)) => format!("before `{}`", name),
+            Some(Token::Symbol(symbol)) => format!("before `{}`", symbol),
+            None => "at the end".to_string(),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Node, String> {
+        let mut node = self.conjunction()?;
+        while self.eat("||") {
+            node = Node::Or(Box::new(node), Box::new(self.conjunction()?));
+        }
+        Ok(node)
+    }
+
+    fn conjunction(&mut self) -> Result<Node, String> {
+        let mut node = self.negation()?;
+        while self.eat("&&") {
+            node = Node::And(Box::new(node), Box::new(self.negation()?));
+        }
+        Ok(node)
+    }
+
+    fn negation(&mut self) -> Result<Node, String> {
+        if self.eat("!") {
+            return Ok(Node::Not(Box::new(self.negation()?)));
+        }
+        // A parenthesis can open either a grouped condition or the left side
+        // of a comparison, like `(load1 + load5) / 2 > ncpu`
+        let start = self.position;
+        match self.comparison() {
+            Ok(node) => Ok(node),
+            Err(err) => {
+                self.position = start;
+                if !self.eat("(") {
+                    return Err(err);
+                }
+                let node = self.condition()?;
+                self.expect(")")?;
+                Ok(node)
+            }
+        }
+    }
+
+    fn comparison(&mut self) -> Result<Node, String> {
+        let left = self.sum()?;
+        let op = match self.peek() {
+            Some(Token::Symbol(">")) => Comparison::Greater,
+            Some(Token::Symbol(">=")) => Comparison::GreaterOrEqual,
+            Some(Token::Symbol("<")) => Comparison::Less,
+            Some(Token::Symbol("<=")) => Comparison::LessOrEqual,
+            Some(Token::Symbol("==")) => Comparison::Equal,
+            Some(Token::Symbol("!=")) => Comparison::NotEqual,
+            _ => return Err(format!("expected a comparison {}", self.found())),
+        };
+        self.position += 1;
+        Ok(Node::Compare(left, op, self.sum()?))
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut value = self.product()?;
+        loop {
+            let op = if self.eat("+") {
+                Arithmetic::Add
+            } else if self.eat("-") {
+                Arithmetic::Subtract
+            } else {
+                return Ok(value);
+            };
+            value = Value::Binary(Box::new(value), op, Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Value, String> {
+        let mut value = self.factor()?;
+        loop {
+            let op = if self.eat("*") {
+                Arithmetic::Multiply
+            } else if self.eat("/") {
+                Arithmetic::Divide
+            } else {
+                return Ok(value);
+            };
+            value = Value::Binary(Box::new(value), op, Box::new(self.factor()?));
+        }
+    }
+
+    fn factor(&mut self) -> Result<Value, String> {
+        let found = self.found();
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Value::Number(number)),
+            Some(Token::Symbol("-")) => Ok(Value::Negate(Box::new(self.factor()?))),
+            Some(Token::Symbol("(")) => {
+                let value = self.sum()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Some(Token::Name(name)) => {
+                let metric = Metric::parse(&name).ok_or_else(|| {
+                    format!(
+                        "unknown metric `{}`, expected one of cpu, mem, swap, steal, pressure, conntrack, pids, retrans, load1, load5, load15, ncpu",
+                        name
+                    )
+                })?;
+                if !self.eat(".") {
+                    return Ok(Value::Metric(metric));
+                }
+                let aggregate = match self.next() {
+                    Some(Token::Name(name)) => Aggregate::parse(&name).ok_or_else(|| {
+                        format!("unknown function `{}`, expected avg, min or max", name)
+                    })?,
+                    _ => return Err("expected avg, min or max after `.`".to_string()),
+                };
+                if !metric.has_history() {
+                    return Err(format!(
+                        "`{}` has no history, only cpu and mem can be summarised",
+                        name
+                    ));
+                }
+                self.expect("(")?;
+                let seconds = match self.next() {
+                    Some(Token::Duration(seconds)) => seconds,
+                    Some(Token::Number(seconds)) => seconds,
+                    _ => return Err("expected a duration like 5m".to_string()),
+                };
+                self.expect(")")?;
+                Ok(Value::Window(metric, aggregate, seconds))
+            }
+            _ => Err(format!("expected a number or metric {}", found)),
+        }
+    }
+}
+
+pub fn parse(input: &str) -> Result<Condition, String> {
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        position: 0,
+    };
+    let node = parser.condition()?;
+    if parser.peek().is_some() {
+        return Err(format!("unexpected input {}", parser.found()));
+    }
+    Ok(Condition(node))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    rule: String,
+    clear: Option<String>,
+    #[serde(rename = "for")]
+    hold: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "RuleConfig")]
+pub struct Rule {
+    pub name: String,
+    condition: Condition,
+    clear: Option<Condition>,
+    // Seconds the condition has to hold before the alert fires
+    hold: f64,
+}
+
+impl TryFrom<RuleConfig> for Rule {
+    type Error = String;
+
+    fn try_from(config: RuleConfig) -> Result<Self, String> {
+        let context = |err: String| format!("alert `{}`: {}", config.name, err);
+        let condition = parse(&config.rule).map_err(context)?;
+        let clear = config
+            .clear
+            .as_deref()
+            .map(parse)
+            .transpose()
+            .map_err(|err| context(format!("clear: {}", err)))?;
+        let hold = match config.hold.as_deref() {
+            Some(text) => parse_duration(text)
+                .ok_or_else(|| context(format!("`{}` isn't a duration like 30s or 5m", text)))?,
+            None => 0.0,
+        };
+        Ok(Rule {
+            name: config.name,
+            condition,
+            clear,
+            hold,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    Idle,
+    // Unix seconds the condition started holding
+    Pending(f64),
+    Firing,
+}
+
+// Steps every rule's state on each sample, the interface, the header and the
+// text outputs all read from here
+#[derive(Debug, Default)]
+pub struct Engine {
+    rules: Vec<(Rule, State)>,
+    fired: u64,
+}
+
+impl Engine {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Engine {
+            rules: rules.into_iter().map(|rule| (rule, State::Idle)).collect(),
+            fired: 0,
+        }
+    }
+
+    pub fn evaluate(&mut self, source: &impl Source, now: f64) {
+        for (rule, state) in self.rules.iter_mut() {
+            *state = match *state {
+                State::Firing => {
+                    let cleared = match &rule.clear {
+                        Some(clear) => clear.evaluate(source),
+                        None => !rule.condition.evaluate(source),
+                    };
+                    if cleared {
+                        State::Idle
+                    } else {
+                        State::Firing
+                    }
+                }
+                _ if !rule.condition.evaluate(source) => State::Idle,
+                State::Idle if rule.hold > 0.0 => State::Pending(now),
+                State::Pending(since) if now - since < rule.hold => State::Pending(since),
+                _ => {
+                    self.fired += 1;
+                    State::Firing
+                }
+            };
+        }
+    }
+
+    pub fn firing(&self) -> Vec<&str> {
+        self.rules
+            .iter()
+            .filter(|(_, state)| *state == State::Firing)
+            .map(|(rule, _)| rule.name.as_str())
+            .collect()
+    }
+
+    // What the firing alerts' rules refer to, so the interface can point at it
+    pub fn firing_metrics(&self) -> Vec<Metric> {
+        let mut metrics = Vec::new();
+        for (rule, state) in &self.rules {
+            if *state == State::Firing {
+                rule.condition.0.metrics(&mut metrics);
+            }
+        }
+        metrics
+    }
+
+    // Counts every time an alert starts firing, so readers can tell a new
+    // alert from one they've already shown
+    pub fn fired_count(&self) -> u64 {
+        self.fired
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Windows evaluate to their length in seconds, so tests can see how a
+    // duration was read
+    struct Values(Vec<(Metric, f64)>);
+
+    impl Source for Values {
+        fn current(&self, metric: Metric) -> Option<f64> {
+            self.0
+                .iter()
+                .find(|(name, _)| *name == metric)
+                .map(|(_, value)| *value)
+        }
+
+        fn window(&self, _: Metric, _: Aggregate, seconds: f64) -> Option<f64> {
+            Some(seconds)
+        }
+    }
+
+    fn holds(rule: &str, values: &[(Metric, f64)]) -> bool {
+        parse(rule).unwrap().evaluate(&Values(values.to_vec()))
+    }
+
+    fn rule(condition: &str, clear: Option<&str>, hold: Option<&str>) -> Rule {
+        Rule::try_from(RuleConfig {
+            name: "test".to_string(),
+            rule: condition.to_string(),
+            clear: clear.map(str::to_string),
+            hold: hold.map(str::to_string),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let values = [
+            (Metric::Cpu, 60.0),
+            (Metric::Memory, 0.0),
+            (Metric::Swap, 0.0),
+        ];
+        assert!(holds("cpu > 50 || mem > 50 && swap > 50", &values));
+        assert!(!holds("(cpu > 50 || mem > 50) && swap > 50", &values));
+    }
+
+    #[test]
+    fn not_takes_the_next_comparison_only() {
+        let values = [(Metric::Cpu, 10.0), (Metric::Memory, 60.0)];
+        assert!(holds("!cpu > 50 && mem > 50", &values));
+        assert!(!holds("!(cpu < 50 && mem > 50)", &values));
+        assert!(holds("!!mem > 50", &values));
+    }
+
+    #[test]
+    fn arithmetic_has_the_usual_precedence() {
+        assert!(holds("1 + 2 * 3 == 7", &[]));
+        assert!(holds("(1 + 2) * 3 == 9", &[]));
+        assert!(holds("10 - 4 - 3 == 3", &[]));
+        assert!(holds("12 / 2 / 3 == 2", &[]));
+        assert!(holds("-2 * 3 == -6", &[]));
+        let values = [
+            (Metric::Load1, 6.0),
+            (Metric::Load5, 2.0),
+            (Metric::Cpus, 3.0),
+        ];
+        assert!(holds("(load1 + load5) / 2 > ncpu", &values));
+        assert!(!holds("load1 + load5 / 2 < ncpu", &values));
+    }
+
+    #[test]
+    fn missing_values_and_division_by_zero_are_false() {
+        assert!(!holds("swap > 0", &[]));
+        assert!(!holds("swap <= 0", &[]));
+        assert!(!holds("cpu / 0 > 1", &[(Metric::Cpu, 1.0)]));
+    }
+
+    #[test]
+    fn windows_take_a_duration() {
+        assert!(holds("cpu.avg(5m) == 300", &[]));
+        assert!(holds("mem.max(90) == 90", &[]));
+        assert!(holds("cpu.min(1h) == 3600", &[]));
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30s"), Some(30.0));
+        assert_eq!(parse_duration("5m"), Some(300.0));
+        assert_eq!(parse_duration("1.5h"), Some(5400.0));
+        assert_eq!(parse_duration("10"), Some(10.0));
+        assert_eq!(parse_duration("5d"), None);
+        assert_eq!(parse_duration("-1m"), None);
+    }
+
+    #[test]
+    fn aggregates() {
+        let values = [3.0, 1.0, 2.0];
+        assert_eq!(Aggregate::Average.apply(values.into_iter()), Some(2.0));
+        assert_eq!(Aggregate::Minimum.apply(values.into_iter()), Some(1.0));
+        assert_eq!(Aggregate::Maximum.apply(values.into_iter()), Some(3.0));
+        assert_eq!(Aggregate::Average.apply(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn malformed_rules_are_errors() {
+        for rule in [
+            "",
+            "cpu",
+            "cpu >",
+            "cpu > 80 &&",
+            "cpu > 80)",
+            "(cpu > 80",
+            "cpu >> 80",
+            "cpu # 80",
+            "disk > 80",
+            "cpu.median(5m) > 80",
+            "cpu.avg(5x) > 80",
+            "cpu.avg 5m > 80",
+            "load1.avg(5m) > 1",
+        ] {
+            assert!(parse(rule).is_err(), "`{}` parsed", rule);
+        }
+        let err = parse("disk > 80").unwrap_err();
+        assert!(err.starts_with("unknown metric `disk`"), "{}", err);
+        let err = parse("load1.avg(5m) > 1").unwrap_err();
+        assert!(err.contains("no history"), "{}", err);
+    }
+
+    #[test]
+    fn malformed_rule_config_is_an_error() {
+        let config = RuleConfig {
+            name: "slow".to_string(),
+            rule: "cpu > 80".to_string(),
+            clear: None,
+            hold: Some("soon".to_string()),
+        };
+        let err = Rule::try_from(config).unwrap_err();
+        assert!(err.starts_with("alert `slow`:"), "{}", err);
+    }
+
+    #[test]
+    fn fires_once_the_condition_has_held_for_long_enough() {
+        let mut engine = Engine::new(vec![rule("cpu > 90", None, Some("10s"))]);
+        let busy = Values(vec![(Metric::Cpu, 95.0)]);
+        let idle = Values(vec![(Metric::Cpu, 5.0)]);
+
+        engine.evaluate(&busy, 0.0);
+        engine.evaluate(&busy, 5.0);
+        assert!(engine.firing().is_empty());
+        // Dropping below starts the wait over
+        engine.evaluate(&idle, 6.0);
+        engine.evaluate(&busy, 8.0);
+        engine.evaluate(&busy, 15.0);
+        assert!(engine.firing().is_empty());
+        engine.evaluate(&busy, 18.0);
+        assert_eq!(engine.firing(), ["test"]);
+        assert_eq!(engine.firing_metrics(), [Metric::Cpu]);
+        assert_eq!(engine.fired_count(), 1);
+
+        // Staying over doesn't count as firing again
+        engine.evaluate(&busy, 19.0);
+        assert_eq!(engine.fired_count(), 1);
+    }
+
+    #[test]
+    fn fires_straight_away_without_a_hold() {
+        let mut engine = Engine::new(vec![rule("cpu > 90", None, None)]);
+        engine.evaluate(&Values(vec![(Metric::Cpu, 95.0)]), 0.0);
+        assert_eq!(engine.firing(), ["test"]);
+        engine.evaluate(&Values(vec![(Metric::Cpu, 80.0)]), 1.0);
+        assert!(engine.firing().is_empty());
+    }
+
+    #[test]
+    fn clears_only_once_the_clear_condition_holds() {
+        let mut engine = Engine::new(vec![rule("cpu > 90", Some("cpu < 70"), None)]);
+        engine.evaluate(&Values(vec![(Metric::Cpu, 95.0)]), 0.0);
+        assert_eq!(engine.firing(), ["test"]);
+        engine.evaluate(&Values(vec![(Metric::Cpu, 80.0)]), 1.0);
+        assert_eq!(engine.firing(), ["test"]);
+        engine.evaluate(&Values(vec![(Metric::Cpu, 60.0)]), 2.0);
+        assert!(engine.firing().is_empty());
+        assert_eq!(engine.fired_count(), 1);
+    }
+}
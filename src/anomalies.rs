@@ -0,0 +1,100 @@
+// Cheap signals that a process deserves a closer look. None of them mean
+// anything is wrong on their own: upgrades leave deleted binaries behind
+// and build tools run things from /tmp.
+
+use crate::procfs::{self, BinaryChange};
+
+// World writable places that malware likes to drop binaries in
+const TEMPORARY_DIRS: [&str; 3] = ["/tmp/", "/var/tmp/", "/dev/shm/"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    Executable(BinaryChange),
+    Temporary,
+    RandomName,
+}
+
+impl Reason {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Reason::Executable(BinaryChange::Deleted) => "binary deleted",
+            Reason::Executable(BinaryChange::Replaced) => "binary replaced",
+            Reason::Temporary => "runs from temp dir",
+            Reason::RandomName => "random name",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    pub pid: u32,
+    pub name: String,
+    pub user: String,
+    pub executable: Option<String>,
+    pub reasons: Vec<Reason>,
+}
+
+// Shannon entropy in bits per character
+fn entropy(chars: &[char]) -> f64 {
+    let mut counts = std::collections::HashMap::new();
+    for c in chars {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    let length = chars.len() as f64;
+    counts
+        .values()
+        .map(|count| {
+            let p = *count as f64 / length;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+// Entropy alone flags plenty of ordinary names like systemd-journald, since
+// most characters in a short name are distinct anyway. Generated names also
+// jump between lower case, upper case and digits far more often than words do.
+fn looks_random(name: &str) -> bool {
+    let chars = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect::<Vec<char>>();
+    if chars.len() < 6 {
+        return false;
+    }
+    let class = |c: &char| match c {
+        'a'..='z' => 0,
+        'A'..='Z' => 1,
+        _ => 2,
+    };
+    let switches = chars
+        .windows(2)
+        .filter(|pair| class(&pair[0]) != class(&pair[1]))
+        .count();
+    entropy(&chars) >= 2.5 && switches * 2 >= chars.len() - 1
+}
+
+// Kernel threads have no executable and are left alone
+pub fn check(pid: u32, name: &str, user: &str) -> Option<Anomaly> {
+    let executable = procfs::read_executable(pid);
+    let mut reasons = Vec::new();
+    if let Some(change) = executable.as_ref().and_then(|executable| executable.change) {
+        reasons.push(Reason::Executable(change));
+    }
+    if executable.as_ref().is_some_and(|executable| {
+        TEMPORARY_DIRS
+            .iter()
+            .any(|dir| executable.path.starts_with(dir))
+    }) {
+        reasons.push(Reason::Temporary);
+    }
+    if executable.is_some() && looks_random(name) {
+        reasons.push(Reason::RandomName);
+    }
+    (!reasons.is_empty()).then(|| Anomaly {
+        pid,
+        name: name.to_string(),
+        user: user.to_string(),
+        executable: executable.map(|executable| executable.path),
+        reasons,
+    })
+}
@@ -0,0 +1,118 @@
+// Processes recording from a microphone or camera. Programs that open the
+// devices directly show up through their file descriptors, sound servers
+// hold the devices for their clients, so PulseAudio and PipeWire clients are
+// asked for with pactl as well.
+
+use std::{fs, process::Command};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Device {
+    Microphone,
+    Camera,
+}
+
+impl Device {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Device::Microphone => "microphone",
+            Device::Camera => "camera",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Capture {
+    pub pid: u32,
+    pub name: String,
+    pub device: Device,
+    // The device file, or the sound server's description of the stream
+    pub source: String,
+}
+
+// ALSA capture devices are /dev/snd/pcmC<card>D<device>c, playback ends in p
+fn device(path: &str) -> Option<Device> {
+    if path
+        .strip_prefix("/dev/video")
+        .is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+    {
+        return Some(Device::Camera);
+    }
+    let pcm = path.strip_prefix("/dev/snd/pcmC")?;
+    pcm.ends_with('c').then_some(Device::Microphone)
+}
+
+fn open_devices(pid: u32) -> Vec<(Device, String)> {
+    let mut devices = fs::read_dir(format!("/proc/{}/fd", pid))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|fd| {
+            let target = fs::read_link(fd.path()).ok()?;
+            let target = target.to_string_lossy().into_owned();
+            Some((device(&target)?, target))
+        })
+        .collect::<Vec<(Device, String)>>();
+    devices.sort();
+    devices.dedup();
+    devices
+}
+
+// Recording streams as (pid, application name, stream name). The properties
+// of each "Source Output #n" block are lines like `application.process.id = "123"`.
+fn audio_clients() -> Vec<(u32, String, String)> {
+    let output = match Command::new("pactl")
+        .args(["list", "source-outputs"])
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        _ => return Vec::new(),
+    };
+    let mut clients = Vec::new();
+    for block in String::from_utf8_lossy(&output.stdout).split("Source Output #") {
+        let property = |key: &str| {
+            block.lines().find_map(|line| {
+                let value = line.trim().strip_prefix(key)?.trim().strip_prefix('=')?;
+                Some(value.trim().trim_matches('"').to_string())
+            })
+        };
+        if let Some(pid) = property("application.process.id").and_then(|pid| pid.parse().ok()) {
+            clients.push((
+                pid,
+                property("application.name").unwrap_or_default(),
+                property("media.name").unwrap_or_default(),
+            ));
+        }
+    }
+    clients
+}
+
+// Other users' file descriptors are only readable as root
+pub fn read<'a>(processes: impl Iterator<Item = (u32, &'a str)> + Clone) -> Vec<Capture> {
+    let mut captures = Vec::new();
+    for (pid, name) in processes.clone() {
+        for (device, source) in open_devices(pid) {
+            captures.push(Capture {
+                pid,
+                name: name.to_string(),
+                device,
+                source,
+            });
+        }
+    }
+    // Sandboxed clients can report a pid from their own namespace, so the
+    // name they gave the sound server is kept when it's not in the list
+    for (pid, application, stream) in audio_clients() {
+        let name = processes
+            .clone()
+            .find(|(process, _)| *process == pid)
+            .map_or(application, |(_, name)| name.to_string());
+        captures.push(Capture {
+            pid,
+            name,
+            device: Device::Microphone,
+            source: stream,
+        });
+    }
+    captures.sort_by_key(|capture| (capture.pid, capture.device));
+    captures
+}
@@ -0,0 +1,314 @@
+use crate::ProcessData;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[value(rename_all = "lowercase")]
+pub enum Column {
+    Pid,
+    Name,
+    User,
+    State,
+    Nice,
+    Threads,
+    Cpu,
+    #[serde(rename = "cpuavg")]
+    #[value(name = "cpuavg")]
+    CpuAverage,
+    Time,
+    Mem,
+    Swap,
+    Oom,
+    Io,
+    Unit,
+    Container,
+    Command,
+}
+
+// Order used when listing the columns that aren't shown
+pub const ALL_COLUMNS: [Column; 16] = [
+    Column::Pid,
+    Column::Name,
+    Column::User,
+    Column::State,
+    Column::Nice,
+    Column::Threads,
+    Column::Cpu,
+    Column::CpuAverage,
+    Column::Time,
+    Column::Mem,
+    Column::Swap,
+    Column::Oom,
+    Column::Io,
+    Column::Unit,
+    Column::Container,
+    Column::Command,
+];
+
+impl Column {
+    pub fn title(self) -> &'static str {
+        match self {
+            Column::Pid => "PID",
+            Column::Name => "Process Name",
+            Column::User => "User",
+            Column::State => "State",
+            Column::Nice => "Nice",
+            Column::Threads => "Threads",
+            Column::Cpu => "CPU",
+            Column::CpuAverage => "CPU avg",
+            Column::Time => "TIME+",
+            Column::Mem => "Memory",
+            Column::Swap => "Swap",
+            Column::Oom => "OOM (adj)",
+            Column::Io => "Read/Write/s",
+            Column::Unit => "Unit",
+            Column::Container => "Container",
+            Column::Command => "Command",
+        }
+    }
+
+    // Free text columns that give up space when the table doesn't fit
+    pub fn is_flexible(self) -> bool {
+        matches!(self, Column::Name | Column::Unit | Column::Command)
+    }
+
+    // Numbers first sort biggest first, text from A to Z
+    pub fn sorts_descending(self) -> bool {
+        !matches!(
+            self,
+            Column::Pid
+                | Column::Name
+                | Column::User
+                | Column::State
+                | Column::Unit
+                | Column::Container
+                | Column::Command
+        )
+    }
+
+    // Ascending, numbers as numbers and text regardless of case
+    pub fn compare(self, a: &ProcessData, b: &ProcessData) -> Ordering {
+        match self {
+            Column::Pid => a.pid.cmp(&b.pid),
+            Column::Nice => a.nice.cmp(&b.nice),
+            Column::Threads => a.threads.cmp(&b.threads),
+            Column::Cpu => a.cpu_usage.total_cmp(&b.cpu_usage),
+            Column::CpuAverage => a.cpu_average.total_cmp(&b.cpu_average),
+            Column::Time => a
+                .cpu_time
+                .unwrap_or(0.0)
+                .total_cmp(&b.cpu_time.unwrap_or(0.0)),
+            Column::Mem => a.memory.cmp(&b.memory),
+            Column::Swap => a.swap.cmp(&b.swap),
+            Column::Oom => a.oom.map(|oom| oom.score).cmp(&b.oom.map(|oom| oom.score)),
+            Column::Io => (a.read_rate + a.write_rate).total_cmp(&(b.read_rate + b.write_rate)),
+            // Only text is left, which doesn't depend on the units
+            _ => self
+                .value(a, Units::Binary)
+                .to_lowercase()
+                .cmp(&self.value(b, Units::Binary).to_lowercase()),
+        }
+    }
+
+    pub fn value(self, process: &ProcessData, units: Units) -> String {
+        match self {
+            Column::Pid => process.pid.to_string(),
+            Column::Name => process.name.clone(),
+            Column::User => process.user.clone(),
+            Column::State => process.state.clone(),
+            Column::Nice => process
+                .nice
+                .map(|nice| nice.to_string())
+                .unwrap_or_default(),
+            Column::Threads => process
+                .threads
+                .map(|threads| threads.to_string())
+                .unwrap_or_default(),
+            Column::Cpu => format!("{:.2}%", process.cpu_usage),
+            Column::CpuAverage => format!("{:.2}%", process.cpu_average),
+            Column::Time => process.cpu_time.map(format_cpu_time).unwrap_or_default(),
+            Column::Mem => format_bytes(process.memory as f64, units),
+            // Kernel threads have no VmSwap line at all
+            Column::Swap => process
+                .swap
+                .map(|swap| format_bytes(swap as f64, units))
+                .unwrap_or_default(),
+            Column::Oom => process
+                .oom
+                .map(|oom| format!("{} ({})", oom.score, oom.adjustment))
+                .unwrap_or_default(),
+            Column::Io => format!(
+                "{}/{}",
+                format_bytes(process.read_rate, units),
+                format_bytes(process.write_rate, units)
+            ),
+            Column::Unit => process.unit(),
+            Column::Container => process.container.clone().unwrap_or_default(),
+            Column::Command => process.command.clone(),
+        }
+    }
+}
+
+// Flexible columns are never squeezed below this unless their content is shorter
+const MIN_FLEXIBLE_WIDTH: u16 = 8;
+
+// Sizes every column to its widest cell. When that's too wide for the table,
+// the fixed columns keep their size and the flexible ones share what's left,
+// with the narrowest taking its full width first.
+pub fn column_widths(columns: &[Column], rows: &[Vec<String>], available: u16) -> Vec<u16> {
+    let mut widths = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .map(|value| value.chars().count())
+                .chain(std::iter::once(column.title().len()))
+                .max()
+                .unwrap_or(0) as u16
+        })
+        .collect::<Vec<u16>>();
+
+    let spacing = columns.len().saturating_sub(1) as u16;
+    let fixed = columns
+        .iter()
+        .zip(widths.iter())
+        .filter(|(column, _)| !column.is_flexible())
+        .map(|(_, width)| *width)
+        .sum::<u16>();
+    let mut remaining = available.saturating_sub(fixed + spacing);
+
+    let mut flexible = (0..columns.len())
+        .filter(|i| columns[*i].is_flexible())
+        .collect::<Vec<usize>>();
+    flexible.sort_by_key(|i| widths[*i]);
+    for (n, i) in flexible.iter().enumerate() {
+        let share = remaining / (flexible.len() - n) as u16;
+        widths[*i] = widths[*i].min(share.max(MIN_FLEXIBLE_WIDTH));
+        remaining = remaining.saturating_sub(widths[*i]);
+    }
+
+    // Leftover space goes to the last column, otherwise the layout hands it
+    // out to whichever column it likes
+    let used = widths.iter().sum::<u16>() + spacing;
+    if let Some(last) = widths.last_mut() {
+        *last += available.saturating_sub(used);
+    }
+
+    widths
+}
+
+// Like top's TIME+, minutes:seconds.hundredths, with hours split off once
+// there are any
+pub fn format_cpu_time(seconds: f64) -> String {
+    let hundredths = (seconds * 100.0) as u64;
+    let (minutes, hundredths) = (hundredths / 6000, hundredths % 6000);
+    if minutes < 60 {
+        format!(
+            "{}:{:02}.{:02}",
+            minutes,
+            hundredths / 100,
+            hundredths % 100
+        )
+    } else {
+        format!(
+            "{}h{:02}:{:02}",
+            minutes / 60,
+            minutes % 60,
+            hundredths / 100
+        )
+    }
+}
+
+// Sizes in powers of 1024 as top and free show them, or of 1000 as drives
+// and network speeds are sold
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Binary,
+    Decimal,
+}
+
+impl Units {
+    pub fn name(self) -> &'static str {
+        match self {
+            Units::Binary => "binary",
+            Units::Decimal => "decimal",
+        }
+    }
+
+    pub fn base(self) -> f64 {
+        match self {
+            Units::Binary => 1024.0,
+            Units::Decimal => 1000.0,
+        }
+    }
+
+    // Bytes in a gigabyte and what it's called
+    pub fn gigabyte(self) -> (f64, &'static str) {
+        match self {
+            Units::Binary => (1024.0 * 1024.0 * 1024.0, "GiB"),
+            Units::Decimal => (1e9, "GB"),
+        }
+    }
+}
+
+pub fn format_bytes(bytes: f64, units: Units) -> String {
+    let suffixes = match units {
+        Units::Binary => ["B", "K", "M", "G", "T"],
+        Units::Decimal => ["B", "kB", "MB", "GB", "TB"],
+    };
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= units.base() && unit < suffixes.len() - 1 {
+        value /= units.base();
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0}{}", value, suffixes[unit])
+    } else {
+        format!("{:.1}{}", value, suffixes[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PID is fixed at its title's 3 wide, the name and command are flexible
+    const COLUMNS: [Column; 3] = [Column::Pid, Column::Name, Column::Command];
+
+    fn widths(name: &str, command: &str, available: u16) -> Vec<u16> {
+        let rows = [vec!["1".to_string(), name.to_string(), command.to_string()]];
+        column_widths(&COLUMNS, &rows, available)
+    }
+
+    #[test]
+    fn leftover_space_goes_to_the_last_column() {
+        // 3 + 12 + 12 and two spaces between
+        assert_eq!(widths("bash", "/bin/bash -l", 29), [3, 12, 12]);
+        assert_eq!(widths("bash", "/bin/bash -l", 40), [3, 12, 23]);
+    }
+
+    #[test]
+    fn flexible_columns_share_what_is_left() {
+        let name = "a".repeat(30);
+        let command = "b".repeat(50);
+        assert_eq!(widths(&name, &command, 40), [3, 17, 18]);
+        // The narrower one takes its full width before the other gets the rest
+        assert_eq!(widths("bash", &command, 40), [3, 12, 23]);
+    }
+
+    #[test]
+    fn flexible_columns_keep_a_minimum_width() {
+        let name = "a".repeat(30);
+        let command = "b".repeat(50);
+        assert_eq!(widths(&name, &command, 10), [3, 8, 8]);
+        // Unless what's in them is shorter, the command title is 7 wide
+        assert_eq!(widths(&name, "ls", 10), [3, 8, 7]);
+        assert_eq!(widths("bash", "ls", 0), [3, 8, 7]);
+    }
+}
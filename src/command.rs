@@ -0,0 +1,163 @@
+use crate::{control::IoPriority, filter::Filter, maintenance::Task};
+
+// Commands typed after `:`
+#[derive(Debug)]
+pub enum Command {
+    // Sends SIGTERM to every process whose name matches, after confirmation
+    KillAll(Filter),
+    // Applied to the marked processes, or the selected one
+    Renice(i32),
+    Ionice(IoPriority),
+    Pin(String),
+    // System wide, only offered to root
+    Maintenance(Task),
+    // Power profile to switch to
+    Power(String),
+    // Interface profiles, see profile::Profile
+    SaveProfile(String),
+    LoadProfile(String),
+}
+
+pub fn parse(input: &str) -> Result<Command, String> {
+    let input = input.trim();
+    let (name, argument) = input.split_once(' ').unwrap_or((input, ""));
+    match name {
+        "killall" => match Filter::parse(argument.trim()) {
+            Ok(Some(filter)) => Ok(Command::KillAll(filter)),
+            Ok(None) => Err("usage: killall <pattern>".to_string()),
+            Err(err) => Err(err
+                .to_string()
+                .lines()
+                .last()
+                .unwrap_or_default()
+                .to_string()),
+        },
+        "renice" => match argument.trim().parse::<i32>() {
+            Ok(nice) if (-20..=19).contains(&nice) => Ok(Command::Renice(nice)),
+            _ => Err("usage: renice <-20..19>".to_string()),
+        },
+        "ionice" => {
+            let mut words = argument.split_whitespace();
+            let class = words.next().unwrap_or_default();
+            let level = words.next().map(|level| level.parse::<u8>());
+            let priority = match level {
+                Some(Ok(level)) => IoPriority::parse(class, Some(level)),
+                Some(Err(_)) => None,
+                None => IoPriority::parse(class, None),
+            };
+            match priority {
+                Some(priority) if words.next().is_none() => Ok(Command::Ionice(priority)),
+                _ => Err("usage: ionice <rt|be|idle> [0..7]".to_string()),
+            }
+        }
+        "pin" => {
+            let cpus = argument.trim();
+            let valid = !cpus.is_empty()
+                && cpus
+                    .chars()
+                    .all(|c| c.is_ascii_digit() || c == ',' || c == '-');
+            if valid {
+                Ok(Command::Pin(cpus.to_string()))
+            } else {
+                Err("usage: pin <cpu list, e.g. 0-3,6>".to_string())
+            }
+        }
+        "drop-caches" => match argument.trim() {
+            "" | "1" => Ok(Command::Maintenance(Task::DropCaches(1))),
+            "2" => Ok(Command::Maintenance(Task::DropCaches(2))),
+            "3" => Ok(Command::Maintenance(Task::DropCaches(3))),
+            _ => Err("usage: drop-caches [1|2|3]".to_string()),
+        },
+        "compact" if argument.trim().is_empty() => Ok(Command::Maintenance(Task::CompactMemory)),
+        "power" => match argument.trim() {
+            "" => Err("usage: power <performance|balanced|power-saver>".to_string()),
+            profile => Ok(Command::Power(profile.to_string())),
+        },
+        "profile" => match argument.split_whitespace().collect::<Vec<&str>>()[..] {
+            ["save", name] => Ok(Command::SaveProfile(name.to_string())),
+            [name] if name != "save" => Ok(Command::LoadProfile(name.to_string())),
+            _ => Err("usage: profile [save] <name>".to_string()),
+        },
+        "" => Err("no command given".to_string()),
+        _ => Err(format!("unknown command `{}`", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renice_takes_a_nice_value_in_range() {
+        assert!(matches!(parse("renice -20"), Ok(Command::Renice(-20))));
+        assert!(matches!(parse(" renice 19 "), Ok(Command::Renice(19))));
+        assert!(parse("renice 20").is_err());
+        assert!(parse("renice -21").is_err());
+        assert!(parse("renice").is_err());
+        assert!(parse("renice high").is_err());
+    }
+
+    #[test]
+    fn ionice_takes_a_class_and_an_optional_level() {
+        assert!(matches!(
+            parse("ionice rt 0"),
+            Ok(Command::Ionice(IoPriority::Realtime(0)))
+        ));
+        assert!(matches!(
+            parse("ionice be"),
+            Ok(Command::Ionice(IoPriority::BestEffort(4)))
+        ));
+        assert!(matches!(
+            parse("ionice idle"),
+            Ok(Command::Ionice(IoPriority::Idle))
+        ));
+        assert!(parse("ionice be 8").is_err());
+        assert!(parse("ionice be low").is_err());
+        assert!(parse("ionice fast").is_err());
+        assert!(parse("ionice be 2 now").is_err());
+    }
+
+    #[test]
+    fn pin_takes_a_cpu_list() {
+        assert!(matches!(parse("pin 0-3,6"), Ok(Command::Pin(cpus)) if cpus == "0-3,6"));
+        assert!(parse("pin").is_err());
+        assert!(parse("pin 0 1").is_err());
+        assert!(parse("pin all").is_err());
+    }
+
+    #[test]
+    fn drop_caches_defaults_to_the_page_cache() {
+        for (input, level) in [
+            ("drop-caches", 1),
+            ("drop-caches 2", 2),
+            ("drop-caches 3", 3),
+        ] {
+            assert!(matches!(
+                parse(input),
+                Ok(Command::Maintenance(Task::DropCaches(found))) if found == level
+            ));
+        }
+        assert!(parse("drop-caches 4").is_err());
+        assert!(parse("drop-caches 0").is_err());
+    }
+
+    #[test]
+    fn profile_saves_or_loads_by_name() {
+        assert!(
+            matches!(parse("profile save work"), Ok(Command::SaveProfile(name)) if name == "work")
+        );
+        assert!(matches!(parse("profile work"), Ok(Command::LoadProfile(name)) if name == "work"));
+        assert!(parse("profile save").is_err());
+        assert!(parse("profile").is_err());
+        assert!(parse("profile save a b").is_err());
+    }
+
+    #[test]
+    fn unknown_and_empty_commands_are_errors() {
+        assert_eq!(parse("").unwrap_err(), "no command given");
+        assert_eq!(
+            parse("frobnicate").unwrap_err(),
+            "unknown command `frobnicate`"
+        );
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{
+    alerts::Rule,
+    columns::{Column, Units},
+    palette::Theme,
+};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use serde::{de::Error, Deserialize, Deserializer};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tui::style::{Color, Modifier, Style};
+
+// Everything is optional, a missing file or section falls back to the defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    // Where it was read from, or would have been, for writing settings back
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    pub interface: InterfaceConfig,
+    pub processes: ProcessConfig,
+    pub alerts: Vec<Rule>,
+    pub notify: NotifyConfig,
+    pub terminal: TerminalConfig,
+    pub sensors: Vec<SensorConfig>,
+    pub dns: DnsConfig,
+    pub storage: StorageConfig,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InterfaceConfig {
+    pub theme: Theme,
+    pub units: Units,
+    // Milliseconds between samples at start, < and > change it while running
+    pub refresh_ms: u64,
+    // What the general layout shows, the others fill in for hidden ones
+    pub panels: Vec<Panel>,
+    // Minutes of chart history kept, None for as much as the charts offer
+    pub history_minutes: Option<u64>,
+}
+
+impl Default for InterfaceConfig {
+    fn default() -> Self {
+        InterfaceConfig {
+            theme: Theme::Dark,
+            units: Units::Binary,
+            refresh_ms: 100,
+            panels: PANELS.to_vec(),
+            history_minutes: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Panel {
+    Cpu,
+    Memory,
+    Processes,
+    Network,
+    Disks,
+    Io,
+}
+
+pub const PANELS: [Panel; 6] = [
+    Panel::Cpu,
+    Panel::Memory,
+    Panel::Io,
+    Panel::Processes,
+    Panel::Network,
+    Panel::Disks,
+];
+
+impl Panel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Panel::Cpu => "cpu",
+            Panel::Memory => "memory",
+            Panel::Processes => "processes",
+            Panel::Network => "network",
+            Panel::Disks => "disks",
+            Panel::Io => "io",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    // Warn when a filesystem is projected to fill up within this many days
+    // at its current growth rate, 0 turns the warning off
+    pub full_within_days: f64,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        StorageConfig {
+            full_within_days: 7.0,
+        }
+    }
+}
+
+// Off unless a name is given
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DnsConfig {
+    // Resolved periodically to chart lookup latency and failures
+    pub name: Option<String>,
+    pub interval_seconds: u64,
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        DnsConfig {
+            name: None,
+            interval_seconds: 10,
+        }
+    }
+}
+
+// hwmon inputs are named like temp3, so they can be renamed, hidden or given
+// a warning threshold of their own
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SensorConfig {
+    // Chip and input as shown in the sensors panel ("nct6775/temp3"), or the
+    // kernel's label for it
+    pub sensor: String,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub hide: bool,
+    // Celsius, defaults to the chip's own max when it reports one
+    pub warn: Option<f64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerminalConfig {
+    // Keep the window title at "CPU 34% MEM 62%", visible in the tab bar
+    pub title: bool,
+}
+
+// What happens in the interface when an alert starts firing, on top of the
+// status line
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotifyConfig {
+    // Ring the terminal bell, which tmux and most terminals flag on the
+    // window or tab when it isn't focused
+    pub bell: bool,
+    // Blink the border of the chart the alert's rule refers to
+    pub flash: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProcessConfig {
+    pub columns: Vec<Column>,
+    // Sorted by at start, in the column's usual direction
+    pub sort: Option<Column>,
+    // The first rule whose pattern matches a process name styles its row
+    pub highlight: Vec<Highlight>,
+    // Span of the CPU avg column
+    pub cpu_average_seconds: u64,
+    // Hold the rows still while moving the selection
+    pub freeze_while_navigating: bool,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        ProcessConfig {
+            columns: vec![Column::Pid, Column::Name, Column::Cpu, Column::Mem],
+            sort: None,
+            highlight: Vec::new(),
+            cpu_average_seconds: 10,
+            freeze_while_navigating: false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Highlight {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub color: Option<Color>,
+    #[serde(default, deserialize_with = "deserialize_color")]
+    pub background: Option<Color>,
+    #[serde(default)]
+    pub bold: bool,
+}
+
+impl Highlight {
+    pub fn style(&self) -> Style {
+        let mut style = Style::default();
+        if let Some(color) = self.color {
+            style = style.fg(color);
+        }
+        if let Some(background) = self.background {
+            style = style.bg(background);
+        }
+        if self.bold {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        style
+    }
+}
+
+fn deserialize_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(D::Error::custom)
+}
+
+fn deserialize_color<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Color>, D::Error> {
+    let name = String::deserialize(deserializer)?;
+    parse_color(&name)
+        .map(Some)
+        .ok_or_else(|| D::Error::custom(format!("unknown color `{}`", name)))
+}
+
+// Terminal color names as tui spells them, or #rrggbb
+fn parse_color(name: &str) -> Option<Color> {
+    if let Some(hex) = name.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16)
+            .ok()
+            .filter(|_| hex.len() == 6)?;
+        return Some(Color::Rgb(
+            (value >> 16) as u8,
+            (value >> 8) as u8,
+            value as u8,
+        ));
+    }
+    let color = match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "gray" | "grey" => Color::Gray,
+        "darkgray" | "darkgrey" => Color::DarkGray,
+        "lightred" => Color::LightRed,
+        "lightgreen" => Color::LightGreen,
+        "lightyellow" => Color::LightYellow,
+        "lightblue" => Color::LightBlue,
+        "lightmagenta" => Color::LightMagenta,
+        "lightcyan" => Color::LightCyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color)
+}
+
+// Follows the XDG base directory spec, falling back to ~/.config
+pub fn default_path() -> Option<PathBuf> {
+    let config_dir = match std::env::var_os("XDG_CONFIG_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("rust_resource_viewer").join("config.toml"))
+}
+
+// A path given on the command line has to exist, the default one doesn't
+pub fn load(path: Option<PathBuf>) -> Result<Config> {
+    let (path, explicit) = match path {
+        Some(path) => (path, true),
+        None => match default_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound && !explicit => return Ok(Config::default()),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+    let mut config: Config =
+        toml::from_str(&content).with_context(|| format!("parsing {}", path.display()))?;
+    if config.processes.columns.is_empty() {
+        bail!("{}: processes.columns can't be empty", path.display());
+    }
+    if config.interface.panels.is_empty() {
+        bail!("{}: interface.panels can't be empty", path.display());
+    }
+    if config.interface.refresh_ms == 0 {
+        bail!("{}: interface.refresh_ms must be above 0", path.display());
+    }
+    if config.interface.history_minutes == Some(0) {
+        bail!(
+            "{}: interface.history_minutes must be above 0",
+            path.display()
+        );
+    }
+    config.path = Some(path);
+    Ok(config)
+}
+
+// Updates the [interface] settings the settings panel changes, leaving the
+// rest of the file and its comments as they are
+pub fn save_interface(path: &Path, interface: &InterfaceConfig) -> Result<()> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", path.display())),
+    };
+    let mut document = content
+        .parse::<toml_edit::DocumentMut>()
+        .with_context(|| format!("parsing {}", path.display()))?;
+    let section = document
+        .entry("interface")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .with_context(|| format!("{}: interface isn't a table", path.display()))?;
+    section["theme"] = toml_edit::value(interface.theme.name());
+    section["units"] = toml_edit::value(interface.units.name());
+    section["refresh_ms"] = toml_edit::value(interface.refresh_ms as i64);
+    section["panels"] = toml_edit::value(
+        interface
+            .panels
+            .iter()
+            .map(|panel| panel.name())
+            .collect::<toml_edit::Array>(),
+    );
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, document.to_string()).with_context(|| format!("writing {}", path.display()))?;
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use serde::Deserialize;
+use std::{collections::HashMap, fs, path::PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Runtime {
+    Docker,
+    Podman,
+}
+
+// Container ids show up in cgroup paths as /system.slice/docker-<id>.scope
+// under systemd, /docker/<id> with the cgroupfs driver, and libpod-<id>.scope
+// (optionally followed by /container) for podman
+fn container_id(cgroup: &str) -> Option<(Runtime, &str)> {
+    let is_id = |id: &str| id.len() == 64 && id.chars().all(|c| c.is_ascii_hexdigit());
+    let parts = cgroup.split('/').collect::<Vec<&str>>();
+    parts.iter().enumerate().rev().find_map(|(i, part)| {
+        let scope = part.strip_suffix(".scope").unwrap_or(part);
+        if let Some(id) = scope.strip_prefix("docker-").filter(|id| is_id(id)) {
+            return Some((Runtime::Docker, id));
+        }
+        if let Some(id) = scope.strip_prefix("libpod-").filter(|id| is_id(id)) {
+            return Some((Runtime::Podman, id));
+        }
+        let parent = i.checked_sub(1).map(|p| parts[p]);
+        (parent == Some("docker") && is_id(part)).then_some((Runtime::Docker, *part))
+    })
+}
+
+#[derive(Deserialize)]
+struct DockerConfig {
+    #[serde(rename = "Name")]
+    name: String,
+}
+
+fn docker_name(id: &str) -> Option<String> {
+    let path = format!("/var/lib/docker/containers/{}/config.v2.json", id);
+    let config: DockerConfig = serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+    Some(config.name.trim_start_matches('/').to_string())
+}
+
+#[derive(Deserialize)]
+struct PodmanContainer {
+    id: String,
+    #[serde(default)]
+    names: Vec<String>,
+}
+
+// Rootful containers live under /var/lib, rootless ones in the user's data dir
+fn podman_name(id: &str) -> Option<String> {
+    let mut stores = vec![PathBuf::from("/var/lib/containers/storage")];
+    if let Some(home) = std::env::var_os("HOME") {
+        stores.push(PathBuf::from(home).join(".local/share/containers/storage"));
+    }
+    stores.into_iter().find_map(|store| {
+        let path = store.join("overlay-containers/containers.json");
+        let containers: Vec<PodmanContainer> =
+            serde_json::from_str(&fs::read_to_string(path).ok()?).ok()?;
+        containers
+            .into_iter()
+            .find(|container| container.id == id)?
+            .names
+            .into_iter()
+            .next()
+    })
+}
+
+// Names only change when a container is renamed, so each id is looked up once
+#[derive(Debug, Default)]
+pub struct Resolver {
+    names: HashMap<String, String>,
+}
+
+impl Resolver {
+    // Falls back to the short id that `docker ps` shows when the runtime's
+    // state isn't readable, which it usually isn't without root
+    pub fn name(&mut self, cgroup: &str) -> Option<String> {
+        let (runtime, id) = container_id(cgroup)?;
+        let name = self.names.entry(id.to_string()).or_insert_with(|| {
+            let name = match runtime {
+                Runtime::Docker => docker_name(id),
+                Runtime::Podman => podman_name(id),
+            };
+            name.unwrap_or_else(|| id[..12].to_string())
+        });
+        Some(name.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "4f1c0e9a2b7d3c5e8f6a1b2c3d4e5f60718293a4b5c6d7e8f90a1b2c3d4e5f6a";
+
+    #[test]
+    fn ids_are_found_in_every_layout() {
+        let cases = [
+            (
+                format!("/system.slice/docker-{}.scope", ID),
+                Runtime::Docker,
+            ),
+            (format!("/docker/{}", ID), Runtime::Docker),
+            (
+                format!("/machine.slice/libpod-{}.scope/container", ID),
+                Runtime::Podman,
+            ),
+            (
+                format!("/user.slice/user-1000.slice/libpod-{}.scope", ID),
+                Runtime::Podman,
+            ),
+        ];
+        for (cgroup, runtime) in &cases {
+            assert_eq!(container_id(cgroup), Some((*runtime, ID)), "{}", cgroup);
+        }
+    }
+
+    #[test]
+    fn other_cgroups_are_not_containers() {
+        let cases = [
+            "/".to_string(),
+            "/user.slice/user-1000.slice/session-2.scope".to_string(),
+            "/system.slice/docker.service".to_string(),
+            "/docker".to_string(),
+            // Too short to be an id
+            "/system.slice/docker-4f1c0e9a.scope".to_string(),
+            format!("/system.slice/other-{}.scope", ID),
+            format!("/other/{}", ID),
+        ];
+        for cgroup in &cases {
+            assert_eq!(container_id(cgroup), None, "{}", cgroup);
+        }
+    }
+}
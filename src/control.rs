@@ -0,0 +1,128 @@
+use std::{
+    env, fmt, io,
+    process::{Command, Stdio},
+};
+
+// Priority and affinity changes go through util-linux rather than raw syscalls,
+// which also gets us their permission checks and error reporting for free.
+// They only say why they failed on stderr.
+fn run(program: &str, args: &[&str]) -> io::Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .stdout(Stdio::null())
+        .output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = String::from_utf8_lossy(&output.stderr);
+    if message.contains("Permission denied") || message.contains("Operation not permitted") {
+        Err(io::ErrorKind::PermissionDenied.into())
+    } else {
+        Err(io::Error::other("failed"))
+    }
+}
+
+pub fn renice(pid: u32, nice: i32) -> io::Result<()> {
+    run("renice", &["-n", &nice.to_string(), "-p", &pid.to_string()])
+}
+
+// I/O scheduling class and level, lower levels get served first
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoPriority {
+    Realtime(u8),
+    BestEffort(u8),
+    // Only gets disk time nobody else wants
+    Idle,
+}
+
+impl IoPriority {
+    pub fn parse(class: &str, level: Option<u8>) -> Option<Self> {
+        let level = level.unwrap_or(4);
+        if level > 7 {
+            return None;
+        }
+        match class {
+            "rt" | "realtime" => Some(IoPriority::Realtime(level)),
+            "be" | "best-effort" => Some(IoPriority::BestEffort(level)),
+            "idle" => Some(IoPriority::Idle),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IoPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IoPriority::Realtime(level) => write!(f, "realtime {}", level),
+            IoPriority::BestEffort(level) => write!(f, "best-effort {}", level),
+            IoPriority::Idle => write!(f, "idle"),
+        }
+    }
+}
+
+pub fn ionice(pid: u32, priority: IoPriority) -> io::Result<()> {
+    let pid = pid.to_string();
+    match priority {
+        IoPriority::Realtime(level) => {
+            run("ionice", &["-c", "1", "-n", &level.to_string(), "-p", &pid])
+        }
+        IoPriority::BestEffort(level) => {
+            run("ionice", &["-c", "2", "-n", &level.to_string(), "-p", &pid])
+        }
+        IoPriority::Idle => run("ionice", &["-c", "3", "-p", &pid]),
+    }
+}
+
+// As ionice reports it, e.g. "best-effort: prio 4". Processes that never set
+// one report "none", the kernel then derives a best-effort level from the nice value
+pub fn io_priority(pid: u32) -> Option<String> {
+    let output = Command::new("ionice")
+        .args(["-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(match text.strip_prefix("none") {
+        Some(_) => "none, follows the nice value".to_string(),
+        None => text,
+    })
+}
+
+// `cpus` is a taskset list such as "0-3,6", applied to every thread
+pub fn pin(pid: u32, cpus: &str) -> io::Result<()> {
+    run("taskset", &["-a", "-p", "-c", cpus, &pid.to_string()])
+}
+
+// Signals go straight to kill(2), whose error tells a process that has
+// exited apart from one owned by someone else
+pub fn signal(pid: u32, signal: i32) -> io::Result<()> {
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+// sudo first, it's what a terminal user most likely has set up and it
+// remembers the password for a while. pkexec asks through polkit instead.
+pub fn escalator() -> Option<&'static str> {
+    let paths = env::var_os("PATH")?;
+    ["sudo", "pkexec"]
+        .into_iter()
+        .find(|program| env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+// With the terminal's stdin and stdout, for the password prompt. Waited on
+// without blocking, the runtime's other tasks keep sampling.
+pub async fn escalate(escalator: &str, command: &[String]) -> io::Result<()> {
+    let status = tokio::process::Command::new(escalator)
+        .args(command)
+        .status()
+        .await?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(status.to_string()))
+    }
+}
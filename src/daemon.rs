@@ -0,0 +1,126 @@
+// Keeps sampling in the background with --daemon, so the charts already have
+// history when the interface starts. The interface attaches by asking the
+// daemon for its history over a unix socket, samples on its own while it runs
+// and detaches by quitting, which leaves the daemon going. Under systemd the
+// socket can come from a .socket unit and readiness is reported with sd_notify.
+
+use crate::{config::Config, persist, systemd, SystemData};
+use anyhow::{bail, Context, Result};
+use std::{
+    fs,
+    io::{self, Read},
+    os::unix::{fs::PermissionsExt, net::UnixStream},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::UnixListener,
+    signal::unix::{signal, SignalKind},
+};
+
+// How long the interface waits for the history before starting without it
+const ATTACH_TIMEOUT: Duration = Duration::from_secs(2);
+// And how long the daemon keeps trying to hand it over
+const SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+// The runtime directory is per user and cleared on logout, the state
+// directory stands in without one
+pub fn socket_path() -> Option<PathBuf> {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|v| !v.is_empty()) {
+        Some(dir) => Some(PathBuf::from(dir).join("rust_resource_viewer.sock")),
+        None => Some(persist::state_dir()?.join("daemon.sock")),
+    }
+}
+
+// None when no daemon is listening
+pub fn attach(path: &Path) -> Option<persist::SavedHistory> {
+    let mut stream = UnixStream::connect(path).ok()?;
+    stream.set_read_timeout(Some(ATTACH_TIMEOUT)).ok()?;
+    let mut content = String::new();
+    stream.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Made when started by hand, a .socket unit has systemd make it instead
+fn bind(path: &Path) -> Result<UnixListener> {
+    if UnixStream::connect(path).is_ok() {
+        bail!("a daemon is already listening on {}", path.display());
+    }
+    // Left behind by one that didn't get to clean up
+    match fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            return Err(err).with_context(|| format!("removing {}", path.display()))
+        }
+        _ => {}
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let listener =
+        UnixListener::bind(path).with_context(|| format!("listening on {}", path.display()))?;
+    // The history shows what everyone runs, it's only for this user
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+// The listener is the one from systemd::listener, taken before the runtime
+// started
+pub async fn run(
+    config: &Config,
+    interval: Duration,
+    history_file: Option<PathBuf>,
+    activated: Option<std::os::unix::net::UnixListener>,
+) -> Result<()> {
+    // Only made and cleaned up when systemd didn't hand one over
+    let (listener, path) = match activated {
+        Some(listener) => {
+            listener.set_nonblocking(true)?;
+            (UnixListener::from_std(listener)?, None)
+        }
+        None => {
+            let path = socket_path().context("no runtime or state directory, HOME isn't set")?;
+            (bind(&path)?, Some(path))
+        }
+    };
+
+    // Only the charts that are handed over get sampled
+    let mut state = SystemData::new(config);
+    state.lightweight = true;
+    if let Some(saved) = history_file.as_deref().and_then(persist::load) {
+        state.restore_history(saved);
+    }
+    state.update_system_info();
+    systemd::notify("READY=1");
+
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut interrupt = signal(SignalKind::interrupt())?;
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => state.update_system_info(),
+            accepted = listener.accept() => {
+                // Written on a task of its own, so a client that doesn't read
+                // holds up neither sampling nor the next one. One that goes
+                // away mid-write doesn't stop the daemon.
+                if let Ok((mut stream, _)) = accepted {
+                    let history = serde_json::to_vec(&state.saved_history())?;
+                    tokio::spawn(async move {
+                        let _ = tokio::time::timeout(SEND_TIMEOUT, stream.write_all(&history)).await;
+                    });
+                }
+            }
+            _ = terminate.recv() => break,
+            _ = interrupt.recv() => break,
+        }
+    }
+
+    systemd::notify("STOPPING=1");
+    if let Some(path) = &path {
+        let _ = fs::remove_file(path);
+    }
+    if let Some(path) = &history_file {
+        persist::save(path, &state.saved_history())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use crate::history::History;
+use std::time::{Duration, Instant};
+
+// Longer than any resolver's own retries, a lookup that takes this long is
+// counted as failed
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+
+// Results of resolving one configured name over and over
+#[derive(Debug, Default)]
+pub struct DnsHealth {
+    pub name: String,
+    // Milliseconds per successful lookup
+    pub latency: History,
+    // A point per failed lookup
+    pub failed: History,
+    pub last: Option<Result<f64, String>>,
+    pub lookups: u64,
+    pub failures: u64,
+}
+
+impl DnsHealth {
+    pub fn new(name: String) -> Self {
+        DnsHealth {
+            name,
+            ..DnsHealth::default()
+        }
+    }
+
+    pub fn record(&mut self, time: f64, result: Result<f64, String>) {
+        self.lookups += 1;
+        match &result {
+            Ok(milliseconds) => self.latency.push(time, *milliseconds),
+            Err(_) => {
+                self.failures += 1;
+                self.failed.push(time, 1.0);
+            }
+        }
+        self.last = Some(result);
+    }
+}
+
+// Goes through the system resolver like everything else on the machine, so
+// nsswitch, /etc/hosts and any local cache are part of what's measured
+pub async fn lookup(name: &str) -> Result<f64, String> {
+    let started = Instant::now();
+    let lookup = tokio::net::lookup_host((name, 0));
+    match tokio::time::timeout(TIMEOUT, lookup).await {
+        Ok(Ok(mut addresses)) => match addresses.next() {
+            Some(_) => Ok(started.elapsed().as_secs_f64() * 1000.0),
+            None => Err("no addresses".to_string()),
+        },
+        Ok(Err(err)) => Err(err.to_string()),
+        Err(_) => Err("timed out".to_string()),
+    }
+}
@@ -0,0 +1,67 @@
+// CSV export of a single panel's full data, written to the working directory
+// with a timestamped name so repeated exports don't overwrite each other
+
+use crate::{
+    columns::{Column, Units},
+    ProcessData,
+};
+use chrono::{Local, TimeZone};
+use std::{fs, io, path::PathBuf};
+
+// Quoted only when needed, per RFC 4180
+fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn line(fields: impl Iterator<Item = String>) -> String {
+    fields.map(|f| field(&f)).collect::<Vec<String>>().join(",") + "\n"
+}
+
+// Every column, whichever are shown, and every row the filter lets through
+pub fn processes(processes: &[&ProcessData], columns: &[Column], units: Units) -> String {
+    let mut csv = line(columns.iter().map(|c| c.title().to_string()));
+    for process in processes {
+        csv.push_str(&line(columns.iter().map(|c| c.value(process, units))));
+    }
+    csv
+}
+
+// A row per point at the resolution it was kept at, see History::all_points
+pub fn history(points: &[(f64, f64, f64)], value_name: &str) -> String {
+    let header = ["time", value_name, "bucket_seconds"];
+    let mut csv = line(header.iter().map(|name| name.to_string()));
+    for (time, value, width) in points {
+        let time = Local
+            .timestamp_millis_opt((time * 1000.0) as i64)
+            .single()
+            .map(|t| t.to_rfc3339())
+            .unwrap_or_default();
+        csv.push_str(&line(
+            [time, value.to_string(), width.to_string()].into_iter(),
+        ));
+    }
+    csv
+}
+
+// How many TCP sockets are in each state right now
+pub fn connections(states: &[u64], names: &[&str]) -> String {
+    let mut csv = line(["state".to_string(), "count".to_string()].into_iter());
+    for (name, count) in names.iter().zip(states) {
+        csv.push_str(&line([name.to_string(), count.to_string()].into_iter()));
+    }
+    csv
+}
+
+pub fn write(panel: &str, csv: &str) -> io::Result<PathBuf> {
+    let path = PathBuf::from(format!(
+        "{}-{}.csv",
+        panel,
+        Local::now().format("%Y%m%d-%H%M%S")
+    ));
+    fs::write(&path, csv)?;
+    Ok(path)
+}
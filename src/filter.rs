@@ -0,0 +1,38 @@
+use crate::ProcessData;
+use regex::{Regex, RegexBuilder};
+
+// Plain queries match a case insensitive substring, queries starting with `~`
+// are regular expressions, e.g. `~postgres: (walwriter|checkpointer)`
+#[derive(Debug)]
+pub enum Filter {
+    Substring(String),
+    Regex(Regex),
+}
+
+impl Filter {
+    pub fn parse(query: &str) -> Result<Option<Filter>, regex::Error> {
+        if let Some(pattern) = query.strip_prefix('~') {
+            if pattern.is_empty() {
+                return Ok(None);
+            }
+            let regex = RegexBuilder::new(pattern).case_insensitive(true).build()?;
+            return Ok(Some(Filter::Regex(regex)));
+        }
+        if query.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Filter::Substring(query.to_lowercase())))
+    }
+
+    pub fn matches(&self, process: &ProcessData) -> bool {
+        // The command line carries the arguments, e.g. postgres worker titles
+        self.is_match(&process.name) || self.is_match(&process.command)
+    }
+
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            Filter::Substring(query) => text.to_lowercase().contains(query),
+            Filter::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
@@ -0,0 +1,461 @@
+// GPU engine usage from the DRM fdinfo of every open GPU file descriptor
+// (Linux 5.19+ for i915, 5.14+ for amdgpu), the source intel_gpu_top and
+// nvtop use too. Engine time only counts while a client has work queued, so
+// summing it over clients gives how busy each engine is. The proprietary
+// NVIDIA driver has no fdinfo stats, its processes come from nvidia-smi.
+
+use crate::history::History;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+    process::Stdio,
+    time::Duration,
+};
+use tokio::process::Command;
+
+// nvidia-smi takes a while to start, like systemctl
+pub const NVIDIA_INTERVAL: Duration = Duration::from_secs(5);
+// Every process's fdinfo is read, too much for every sample
+pub const CLIENT_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    pub pid: u32,
+    pub driver: String,
+    // PCI address, e.g. 0000:00:02.0
+    pub device: String,
+    id: u64,
+    // Nanoseconds busy per engine class
+    engines: HashMap<String, u64>,
+    // Engines per class, for classes with more than one
+    capacity: HashMap<String, u64>,
+    // Resident bytes per memory region, e.g. vram, gtt or system
+    memory: HashMap<String, u64>,
+}
+
+// Memory on the card itself, as opposed to system memory mapped for it
+fn is_vram(region: &str) -> bool {
+    region == "vram" || region.starts_with("local")
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Process {
+    pub pid: u32,
+    // Percent busy per engine class
+    pub engines: BTreeMap<String, f64>,
+    pub vram: u64,
+    // System memory the GPU uses for it, how integrated GPUs get memory
+    pub shared: u64,
+}
+
+impl Process {
+    // e.g. "3D 12%, video decode/encode 40%", leaving out idle engines
+    pub fn describe_engines(&self) -> String {
+        let busy = self
+            .engines
+            .iter()
+            .filter(|(_, usage)| **usage >= 0.5)
+            .map(|(engine, usage)| format!("{} {:.0}%", engine_label(engine), usage))
+            .collect::<Vec<String>>();
+        if busy.is_empty() {
+            "idle".to_string()
+        } else {
+            busy.join(", ")
+        }
+    }
+}
+
+// Whether there's a GPU to ask about at all
+pub fn present() -> bool {
+    fs::read_dir("/dev/dri").is_ok_and(|mut entries| entries.next().is_some())
+}
+
+// Lines look like `drm-engine-render:	1234 ns` and `drm-resident-vram:	12 KiB`.
+// Older kernels only have drm-memory-<region>, which is the resident size.
+fn parse_client(pid: u32, fdinfo: &str) -> Option<Client> {
+    let mut client = Client {
+        pid,
+        ..Client::default()
+    };
+    let mut legacy_memory = HashMap::new();
+    for line in fdinfo.lines() {
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key, value.trim()),
+            None => continue,
+        };
+        let number = || value.split_whitespace().next()?.parse::<u64>().ok();
+        let bytes = || {
+            let unit = match value.split_whitespace().nth(1) {
+                Some("KiB") => 1 << 10,
+                Some("MiB") => 1 << 20,
+                Some("GiB") => 1 << 30,
+                _ => 1,
+            };
+            Some(number()? * unit)
+        };
+        match key {
+            "drm-driver" => client.driver = value.to_string(),
+            "drm-pdev" => client.device = value.to_string(),
+            "drm-client-id" => client.id = number()?,
+            _ => {
+                if let Some(engine) = key.strip_prefix("drm-engine-capacity-") {
+                    client.capacity.insert(engine.to_string(), number()?);
+                } else if let Some(engine) = key.strip_prefix("drm-engine-") {
+                    client.engines.insert(engine.to_string(), number()?);
+                } else if let Some(region) = key.strip_prefix("drm-resident-") {
+                    client.memory.insert(region.to_string(), bytes()?);
+                } else if let Some(region) = key.strip_prefix("drm-memory-") {
+                    legacy_memory.insert(region.to_string(), bytes()?);
+                }
+            }
+        }
+    }
+    if client.memory.is_empty() {
+        client.memory = legacy_memory;
+    }
+    (!client.driver.is_empty()).then_some(client)
+}
+
+// A client's file descriptor can be duplicated or inherited, so clients are
+// told apart by their device and id, and go to the first process seen with
+// them. Other users' descriptors are only readable as root.
+pub fn read_clients(pids: impl Iterator<Item = u32>) -> Vec<Client> {
+    let mut clients = HashMap::new();
+    for pid in pids {
+        let fds = match fs::read_dir(format!("/proc/{}/fd", pid)) {
+            Ok(fds) => fds,
+            Err(_) => continue,
+        };
+        for fd in fds.flatten() {
+            let is_gpu =
+                fs::read_link(fd.path()).is_ok_and(|target| target.starts_with("/dev/dri"));
+            if !is_gpu {
+                continue;
+            }
+            let path = format!("/proc/{}/fdinfo/{}", pid, fd.file_name().to_string_lossy());
+            let client = fs::read_to_string(path)
+                .ok()
+                .and_then(|fdinfo| parse_client(pid, &fdinfo));
+            if let Some(client) = client {
+                clients
+                    .entry((client.device.clone(), client.id))
+                    .or_insert(client);
+            }
+        }
+    }
+    clients.into_values().collect()
+}
+
+// What the engine classes are called by the drivers that report them
+pub fn engine_label(engine: &str) -> &str {
+    match engine {
+        "render" | "gfx" => "3D",
+        "video" => "video decode/encode",
+        "video-enhance" => "video enhance",
+        "dec" => "video decode",
+        "enc" | "enc_1" => "video encode",
+        "copy" | "dma" => "copy",
+        _ => engine,
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Gpu {
+    previous: HashMap<(String, u64), Client>,
+    time: Option<f64>,
+    // Percent busy per device and engine class
+    pub engines: BTreeMap<(String, String), History>,
+    // Driver per device
+    pub drivers: BTreeMap<String, String>,
+    // Busiest first
+    pub processes: Vec<Process>,
+    // Pid and bytes of VRAM, from nvidia-smi
+    pub nvidia: Vec<(u32, u64)>,
+    // Summed over every NVIDIA card, from nvidia-smi
+    pub nvidia_cards: Option<NvidiaCards>,
+    // GiB of VRAM in use
+    pub vram_history: History,
+    pub vram_used: u64,
+    // None when only the per process usage is known
+    pub vram_total: Option<u64>,
+    // Watts drawn by every card together, and the hottest card in Celsius
+    pub power_history: History,
+    pub temperature_history: History,
+    // Percent busy of the busiest card as its driver counts it, which needs
+    // no per process scan
+    pub busy_history: History,
+}
+
+impl Gpu {
+    pub fn record(&mut self, time: f64, clients: Vec<Client>) {
+        let elapsed = self.time.map_or(0.0, |previous| time - previous) * 1e9;
+        let mut busy = BTreeMap::new();
+        let mut processes = BTreeMap::new();
+        for client in &clients {
+            let process = processes.entry(client.pid).or_insert_with(|| Process {
+                pid: client.pid,
+                ..Process::default()
+            });
+            for (region, bytes) in &client.memory {
+                if is_vram(region) {
+                    process.vram += bytes;
+                } else {
+                    process.shared += bytes;
+                }
+            }
+            self.drivers
+                .insert(client.device.clone(), client.driver.clone());
+            let previous = self.previous.get(&(client.device.clone(), client.id));
+            for (engine, ns) in &client.engines {
+                let key = (client.device.clone(), engine.clone());
+                let total = busy.entry(key).or_insert(0.0);
+                let usage = process.engines.entry(engine.clone()).or_insert(0.0);
+                let earlier = previous.and_then(|previous| previous.engines.get(engine));
+                if let (Some(earlier), true) = (earlier, elapsed > 0.0) {
+                    let capacity = client.capacity.get(engine).copied().unwrap_or(1).max(1);
+                    let fraction = ns.saturating_sub(*earlier) as f64 / elapsed / capacity as f64;
+                    *total += fraction;
+                    *usage = (*usage + fraction * 100.0).min(100.0);
+                }
+            }
+        }
+        // Engines without clients left are idle rather than gone
+        for key in self.engines.keys() {
+            busy.entry(key.clone()).or_insert(0.0);
+        }
+        for (key, fraction) in busy {
+            self.engines
+                .entry(key)
+                .or_default()
+                .push(time, (fraction * 100.0).clamp(0.0, 100.0));
+        }
+        for (pid, vram) in &self.nvidia {
+            let process = processes.entry(*pid).or_insert_with(|| Process {
+                pid: *pid,
+                ..Process::default()
+            });
+            process.vram += vram;
+        }
+        let mut processes = processes.into_values().collect::<Vec<Process>>();
+        let busiest = |process: &Process| process.engines.values().copied().fold(0.0, f64::max);
+        processes.sort_by(|a, b| {
+            busiest(b)
+                .total_cmp(&busiest(a))
+                .then(b.vram.cmp(&a.vram))
+                .then(b.shared.cmp(&a.shared))
+        });
+        self.processes = processes;
+        self.previous = clients
+            .into_iter()
+            .map(|client| ((client.device.clone(), client.id), client))
+            .collect();
+        self.time = Some(time);
+    }
+
+    // What the cards report themselves, on every sample. VRAM falls back on
+    // the processes from the last record.
+    pub fn record_cards(&mut self, time: f64) {
+        self.record_vram(time);
+        self.record_sensors(time);
+        let busy = combine(
+            read_busy(),
+            self.nvidia_cards.and_then(|cards| cards.busy),
+            f64::max,
+        );
+        if let Some(busy) = busy {
+            self.busy_history.push(time, busy);
+        }
+    }
+
+    // The cards' own counters when there are any, which include what the
+    // kernel and other users' processes hold, the sum over processes otherwise
+    fn record_vram(&mut self, time: f64) {
+        let nvidia = self.nvidia_cards.map(|cards| cards.vram);
+        let cards = [read_vram(), nvidia].into_iter().flatten().reduce(
+            |(used, total), (more_used, more_total)| (used + more_used, total + more_total),
+        );
+        let (used, total) = match cards {
+            Some((used, total)) => (used, Some(total)),
+            None => (
+                self.processes.iter().map(|process| process.vram).sum(),
+                None,
+            ),
+        };
+        if total.is_none() && used == 0 {
+            return;
+        }
+        self.vram_used = used;
+        self.vram_total = total;
+        self.vram_history.push(time, used as f64 / crate::GIB);
+    }
+
+    fn record_sensors(&mut self, time: f64) {
+        let (mut watts, mut celsius) = read_sensors();
+        if let Some(cards) = self.nvidia_cards {
+            watts = combine(watts, cards.watts, |a, b| a + b);
+            celsius = combine(celsius, cards.celsius, f64::max);
+        }
+        if let Some(watts) = watts {
+            self.power_history.push(time, watts);
+        }
+        if let Some(celsius) = celsius {
+            self.temperature_history.push(time, celsius);
+        }
+    }
+}
+
+// The cards' sysfs device directories. Connectors like card0-DP-1 link back
+// to the same device, so they're left out.
+fn card_devices() -> Vec<PathBuf> {
+    let entries = match fs::read_dir("/sys/class/drm") {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with("card") && !name.contains('-')
+        })
+        .map(|entry| entry.path().join("device"))
+        .collect()
+}
+
+// Readings of several cards, any card missing one is left out
+fn combine(a: Option<f64>, b: Option<f64>, with: impl Fn(f64, f64) -> f64) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(with(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+fn read_number(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+// Used and total bytes over every amdgpu card, None without one. i915 has no
+// such counters, integrated GPUs borrow system memory.
+pub fn read_vram() -> Option<(u64, u64)> {
+    card_devices()
+        .into_iter()
+        .filter_map(|device| {
+            let used = read_number(&device.join("mem_info_vram_used"))?;
+            let total = read_number(&device.join("mem_info_vram_total"))?;
+            Some((used, total))
+        })
+        .reduce(|(used, total), (more_used, more_total)| (used + more_used, total + more_total))
+}
+
+// amdgpu's own busy percentage, of the busiest card
+pub fn read_busy() -> Option<f64> {
+    card_devices()
+        .into_iter()
+        .filter_map(|device| read_number(&device.join("gpu_busy_percent")))
+        .max()
+        .map(|percent| percent as f64)
+}
+
+// Watts over every card and the hottest card's Celsius, from the hwmon
+// amdgpu and the discrete Intel cards register. Integrated GPUs have none,
+// their power is part of the CPU package's.
+pub fn read_sensors() -> (Option<f64>, Option<f64>) {
+    let (mut watts, mut celsius) = (None, None);
+    for device in card_devices() {
+        let hwmons = match fs::read_dir(device.join("hwmon")) {
+            Ok(hwmons) => hwmons,
+            Err(_) => continue,
+        };
+        for hwmon in hwmons.flatten() {
+            let path = hwmon.path();
+            // Microwatts, averaged by the firmware on older cards
+            let power = read_number(&path.join("power1_average"))
+                .or_else(|| read_number(&path.join("power1_input")));
+            watts = combine(watts, power.map(|power| power as f64 / 1e6), |a, b| a + b);
+            // Millidegrees, temp1 is the edge or whole GPU temperature
+            let temperature = read_number(&path.join("temp1_input"));
+            celsius = combine(
+                celsius,
+                temperature.map(|temperature| temperature as f64 / 1000.0),
+                f64::max,
+            );
+        }
+    }
+    (watts, celsius)
+}
+
+pub fn has_nvidia() -> bool {
+    Path::new("/dev/nvidiactl").exists()
+}
+
+// Only compute processes are listed, the driver doesn't report per process
+// memory for graphics ones
+pub async fn nvidia_processes() -> Option<Vec<(u32, u64)>> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-compute-apps=pid,used_memory",
+            "--format=csv,noheader,nounits",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    // Megabytes, e.g. "1234, 512"
+    let processes = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let (pid, memory) = line.split_once(',')?;
+            let memory = memory.trim().parse::<u64>().ok()?;
+            Some((pid.trim().parse().ok()?, memory << 20))
+        })
+        .collect();
+    Some(processes)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NvidiaCards {
+    // Used and total bytes
+    pub vram: (u64, u64),
+    // Consumer cards can't always report their draw
+    pub watts: Option<f64>,
+    pub celsius: Option<f64>,
+    // Percent, of the busiest card
+    pub busy: Option<f64>,
+}
+
+pub async fn nvidia_cards() -> Option<NvidiaCards> {
+    let output = Command::new("nvidia-smi")
+        .args([
+            "--query-gpu=memory.used,memory.total,power.draw,temperature.gpu,utilization.gpu",
+            "--format=csv,noheader,nounits",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    // One line per card, e.g. "2048, 24576, 61.25, 54, 97" with megabytes, and
+    // "[N/A]" for what the card can't report
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split(',').map(str::trim).collect::<Vec<&str>>();
+            let used = fields.first()?.parse::<u64>().ok()?;
+            let total = fields.get(1)?.parse::<u64>().ok()?;
+            Some(NvidiaCards {
+                vram: (used << 20, total << 20),
+                watts: fields.get(2).and_then(|watts| watts.parse().ok()),
+                celsius: fields.get(3).and_then(|celsius| celsius.parse().ok()),
+                busy: fields.get(4).and_then(|busy| busy.parse().ok()),
+            })
+        })
+        .reduce(|cards, more| NvidiaCards {
+            vram: (cards.vram.0 + more.vram.0, cards.vram.1 + more.vram.1),
+            watts: combine(cards.watts, more.watts, |a, b| a + b),
+            celsius: combine(cards.celsius, more.celsius, f64::max),
+            busy: combine(cards.busy, more.busy, f64::max),
+        })
+}
@@ -0,0 +1,449 @@
+// Time series storage for the charts. Points are (unix seconds, value) pairs so
+// they can be handed to tui datasets directly and windows can be cut by time.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// Each tier averages samples into buckets of `width` seconds and keeps them for
+// `retention` seconds. A width of zero keeps every sample as is.
+const TIERS: [(f64, f64); 4] = [
+    (0.0, 5.0 * 60.0),
+    (1.0, 30.0 * 60.0),
+    (10.0, 6.0 * 60.0 * 60.0),
+    (60.0, 24.0 * 60.0 * 60.0),
+];
+
+// Seconds of history kept by every series, set once from --history-length
+// before any history is recorded. Tiers retaining more are cut down to it.
+static RETENTION_LIMIT: AtomicU64 = AtomicU64::new(u64::MAX);
+
+pub fn limit_retention(seconds: u64) {
+    RETENTION_LIMIT.store(seconds, Ordering::Relaxed);
+}
+
+fn retention_limit() -> f64 {
+    RETENTION_LIMIT.load(Ordering::Relaxed) as f64
+}
+
+// The since-start series halves its resolution whenever it grows past this
+const SINCE_START_POINTS: usize = 600;
+const SINCE_START_BUCKET_SECONDS: f64 = 10.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub enum TimeWindow {
+    #[default]
+    OneMinute,
+    FiveMinutes,
+    ThirtyMinutes,
+    OneHour,
+    SixHours,
+    OneDay,
+    SinceStart,
+}
+
+impl TimeWindow {
+    pub fn seconds(self) -> Option<f64> {
+        match self {
+            TimeWindow::OneMinute => Some(60.0),
+            TimeWindow::FiveMinutes => Some(5.0 * 60.0),
+            TimeWindow::ThirtyMinutes => Some(30.0 * 60.0),
+            TimeWindow::OneHour => Some(60.0 * 60.0),
+            TimeWindow::SixHours => Some(6.0 * 60.0 * 60.0),
+            TimeWindow::OneDay => Some(24.0 * 60.0 * 60.0),
+            TimeWindow::SinceStart => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeWindow::OneMinute => "1m",
+            TimeWindow::FiveMinutes => "5m",
+            TimeWindow::ThirtyMinutes => "30m",
+            TimeWindow::OneHour => "1h",
+            TimeWindow::SixHours => "6h",
+            TimeWindow::OneDay => "24h",
+            TimeWindow::SinceStart => "since start",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            TimeWindow::OneMinute => TimeWindow::FiveMinutes,
+            TimeWindow::FiveMinutes => TimeWindow::ThirtyMinutes,
+            TimeWindow::ThirtyMinutes => TimeWindow::OneHour,
+            TimeWindow::OneHour => TimeWindow::SixHours,
+            TimeWindow::SixHours => TimeWindow::OneDay,
+            TimeWindow::OneDay => TimeWindow::SinceStart,
+            TimeWindow::SinceStart => TimeWindow::OneMinute,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct Bucket {
+    start: f64,
+    time_sum: f64,
+    value_sum: f64,
+    count: u32,
+}
+
+impl Bucket {
+    fn new(time: f64, value: f64) -> Self {
+        Bucket {
+            start: time,
+            time_sum: time,
+            value_sum: value,
+            count: 1,
+        }
+    }
+
+    fn point(&self) -> (f64, f64) {
+        let count = self.count as f64;
+        (self.time_sum / count, self.value_sum / count)
+    }
+}
+
+// Adds a sample to the open bucket, returning the averaged point of the
+// previous bucket once a sample falls outside it
+fn accumulate(
+    bucket: &mut Option<Bucket>,
+    time: f64,
+    value: f64,
+    width: f64,
+) -> Option<(f64, f64)> {
+    match bucket {
+        Some(open) if time < open.start + width => {
+            open.time_sum += time;
+            open.value_sum += value;
+            open.count += 1;
+            None
+        }
+        _ => bucket.replace(Bucket::new(time, value)).map(|b| b.point()),
+    }
+}
+
+fn trim(points: &mut Vec<(f64, f64)>, cutoff: f64) {
+    let expired = points.partition_point(|(time, _)| *time < cutoff);
+    if expired > 0 {
+        points.drain(..expired);
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Tier {
+    width: f64,
+    retention: f64,
+    points: Vec<(f64, f64)>,
+    bucket: Option<Bucket>,
+}
+
+impl Tier {
+    fn new((width, retention): (f64, f64)) -> Self {
+        Tier {
+            width,
+            retention,
+            points: Vec::new(),
+            bucket: None,
+        }
+    }
+
+    fn push(&mut self, time: f64, value: f64) {
+        let point = if self.width > 0.0 {
+            accumulate(&mut self.bucket, time, value, self.width)
+        } else {
+            Some((time, value))
+        };
+        if let Some(point) = point {
+            let retention = self.retention.min(retention_limit());
+            self.points.push(point);
+            trim(&mut self.points, time - retention);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History {
+    started: Option<f64>,
+    newest: Option<(f64, f64)>,
+    tiers: Vec<Tier>,
+    since_start: Vec<(f64, f64)>,
+    since_start_bucket: Option<Bucket>,
+    since_start_width: f64,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        History {
+            started: None,
+            newest: None,
+            tiers: TIERS.iter().copied().map(Tier::new).collect(),
+            since_start: Vec::new(),
+            since_start_bucket: None,
+            since_start_width: SINCE_START_BUCKET_SECONDS,
+        }
+    }
+}
+
+impl History {
+    pub fn push(&mut self, time: f64, value: f64) {
+        self.started.get_or_insert(time);
+        self.newest = Some((time, value));
+
+        for tier in self.tiers.iter_mut() {
+            tier.push(time, value);
+        }
+
+        let width = self.since_start_width;
+        if let Some(point) = accumulate(&mut self.since_start_bucket, time, value, width) {
+            self.since_start.push(point);
+            trim(&mut self.since_start, time - retention_limit());
+            if self.since_start.len() > SINCE_START_POINTS {
+                self.since_start = self
+                    .since_start
+                    .chunks(2)
+                    .map(|pair| {
+                        let count = pair.len() as f64;
+                        let time = pair.iter().map(|(t, _)| t).sum::<f64>() / count;
+                        let value = pair.iter().map(|(_, v)| v).sum::<f64>() / count;
+                        (time, value)
+                    })
+                    .collect();
+                self.since_start_width *= 2.0;
+            }
+        }
+    }
+
+    pub fn latest(&self) -> Option<f64> {
+        self.newest.map(|(_, value)| value)
+    }
+
+    pub fn newest_time(&self) -> Option<f64> {
+        self.newest.map(|(time, _)| time)
+    }
+
+    pub fn started(&self) -> Option<f64> {
+        self.started
+    }
+
+    // The finest tier that still covers the whole window
+    pub fn points(&self, window: TimeWindow) -> &[(f64, f64)] {
+        let seconds = match (window.seconds(), self.started, self.newest_time()) {
+            (Some(seconds), _, _) => seconds,
+            (None, Some(started), Some(newest)) => newest - started,
+            _ => 0.0,
+        };
+        self.tiers
+            .iter()
+            .find(|tier| tier.retention >= seconds)
+            .map(|tier| tier.points.as_slice())
+            .unwrap_or(&self.since_start)
+    }
+
+    // Everything kept, oldest first, each stretch from the finest tier that
+    // still has it. The third value is the width in seconds of the bucket a
+    // point averages, 0 for single samples.
+    pub fn all_points(&self) -> Vec<(f64, f64, f64)> {
+        let tiers = self
+            .tiers
+            .iter()
+            .map(|tier| (tier.width, tier.points.as_slice()))
+            .chain(std::iter::once((
+                self.since_start_width,
+                self.since_start.as_slice(),
+            )));
+        let mut stretches = Vec::new();
+        let mut covered_from = f64::INFINITY;
+        for (width, points) in tiers {
+            let older = &points[..points.partition_point(|(time, _)| *time < covered_from)];
+            if let Some((first, _)) = older.first() {
+                covered_from = *first;
+                stretches.push((width, older));
+            }
+        }
+        stretches
+            .into_iter()
+            .rev()
+            .flat_map(|(width, points)| points.iter().map(move |(t, v)| (*t, *v, width)))
+            .collect()
+    }
+
+    // Points from the last `seconds` before the newest one, for alert rules
+    pub fn recent(&self, seconds: f64) -> &[(f64, f64)] {
+        let newest = match self.newest_time() {
+            Some(newest) => newest,
+            None => return &[],
+        };
+        let points = self
+            .tiers
+            .iter()
+            .find(|tier| tier.retention >= seconds)
+            .map(|tier| tier.points.as_slice())
+            .unwrap_or(&self.since_start);
+        slice(points, newest - seconds, newest)
+    }
+}
+
+pub fn slice(points: &[(f64, f64)], start: f64, end: f64) -> &[(f64, f64)] {
+    let from = points.partition_point(|(time, _)| *time < start);
+    let to = points.partition_point(|(time, _)| *time <= end);
+    &points[from..to.max(from)]
+}
+
+pub fn nearest(points: &[(f64, f64)], time: f64) -> Option<(f64, f64)> {
+    let index = points.partition_point(|(t, _)| *t < time);
+    let before = index.checked_sub(1).and_then(|i| points.get(i));
+    let after = points.get(index);
+    match (before, after) {
+        (Some(b), Some(a)) => Some(if time - b.0 <= a.0 - time { *b } else { *a }),
+        (Some(point), None) | (None, Some(point)) => Some(*point),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Which of the tiers a window is drawn from, None for the since-start series
+    fn tier(history: &History, window: TimeWindow) -> Option<usize> {
+        let points = history.points(window);
+        history
+            .tiers
+            .iter()
+            .position(|tier| std::ptr::eq(tier.points.as_slice(), points))
+    }
+
+    // Two samples, enough to close a bucket in every tier that spans them
+    fn spanning(seconds: f64) -> History {
+        let mut history = History::default();
+        history.push(1000.0, 1.0);
+        history.push(1000.0 + seconds, 2.0);
+        history
+    }
+
+    #[test]
+    fn windows_use_the_finest_tier_that_covers_them() {
+        // Long enough for every tier to have closed a bucket, empty ones
+        // can't be told apart
+        let mut history = History::default();
+        for time in 0..=120 {
+            history.push(time as f64, 1.0);
+        }
+        assert_eq!(tier(&history, TimeWindow::OneMinute), Some(0));
+        // Tiers that keep exactly the window's length still cover it
+        assert_eq!(tier(&history, TimeWindow::FiveMinutes), Some(0));
+        assert_eq!(tier(&history, TimeWindow::ThirtyMinutes), Some(1));
+        assert_eq!(tier(&history, TimeWindow::OneHour), Some(2));
+        assert_eq!(tier(&history, TimeWindow::SixHours), Some(2));
+        assert_eq!(tier(&history, TimeWindow::OneDay), Some(3));
+    }
+
+    #[test]
+    fn since_start_goes_by_how_long_has_been_recorded() {
+        assert_eq!(tier(&spanning(300.0), TimeWindow::SinceStart), Some(0));
+        assert_eq!(tier(&spanning(300.5), TimeWindow::SinceStart), Some(1));
+        assert_eq!(tier(&spanning(86400.0), TimeWindow::SinceStart), Some(3));
+        assert_eq!(tier(&spanning(86400.5), TimeWindow::SinceStart), None);
+    }
+
+    #[test]
+    fn tiers_average_into_buckets() {
+        let mut history = History::default();
+        for (time, value) in [(0.0, 1.0), (0.5, 3.0), (1.0, 5.0)] {
+            history.push(time, value);
+        }
+        assert_eq!(
+            history.tiers[0].points,
+            [(0.0, 1.0), (0.5, 3.0), (1.0, 5.0)]
+        );
+        // The bucket from 1s on is still open
+        assert_eq!(history.tiers[1].points, [(0.25, 2.0)]);
+        assert_eq!(history.latest(), Some(5.0));
+        assert_eq!(history.started(), Some(0.0));
+    }
+
+    #[test]
+    fn since_start_halves_once_it_has_too_many_points() {
+        let mut history = History::default();
+        // Each sample closes the previous 10 second bucket
+        for step in 0..=SINCE_START_POINTS {
+            history.push(step as f64 * 10.0, step as f64);
+        }
+        assert_eq!(history.since_start.len(), SINCE_START_POINTS);
+        assert_eq!(history.since_start_width, SINCE_START_BUCKET_SECONDS);
+
+        let step = SINCE_START_POINTS as f64 + 1.0;
+        history.push(step * 10.0, step);
+        assert_eq!(history.since_start.len(), (SINCE_START_POINTS + 2) / 2);
+        assert_eq!(history.since_start_width, SINCE_START_BUCKET_SECONDS * 2.0);
+        assert_eq!(history.since_start[0], (5.0, 0.5));
+
+        // Later buckets are twice as wide
+        history.push((step + 1.0) * 10.0, step + 1.0);
+        history.push((step + 2.0) * 10.0, step + 2.0);
+        let newest = *history.since_start.last().unwrap();
+        assert_eq!(newest, (step * 10.0 + 5.0, step + 0.5));
+    }
+
+    #[test]
+    fn slice_includes_both_ends() {
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)];
+        assert_eq!(slice(&points, 1.0, 2.0), &points[1..3]);
+        assert_eq!(slice(&points, 0.5, 2.5), &points[1..3]);
+        assert_eq!(slice(&points, 3.0, 3.0), &points[3..]);
+        assert_eq!(slice(&points, -10.0, 10.0), &points);
+    }
+
+    #[test]
+    fn slice_outside_the_points_is_empty() {
+        let points = [(0.0, 0.0), (1.0, 1.0), (2.0, 2.0)];
+        assert!(slice(&points, 5.0, 6.0).is_empty());
+        assert!(slice(&points, -2.0, -1.0).is_empty());
+        assert!(slice(&points, 0.25, 0.75).is_empty());
+        // A start after the end doesn't underflow
+        assert!(slice(&points, 2.0, 1.0).is_empty());
+        assert!(slice(&[], 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn nearest_prefers_the_earlier_point_on_a_tie() {
+        let points = [(0.0, 0.0), (2.0, 2.0)];
+        assert_eq!(nearest(&points, 1.0), Some((0.0, 0.0)));
+        assert_eq!(nearest(&points, 1.5), Some((2.0, 2.0)));
+        assert_eq!(nearest(&points, 5.0), Some((2.0, 2.0)));
+        assert_eq!(nearest(&[], 1.0), None);
+    }
+
+    #[test]
+    fn all_points_take_the_finest_tier_for_each_stretch() {
+        let mut history = History::default();
+        // One sample a second for ten minutes, past the raw tier's five
+        for time in 0..600 {
+            history.push(time as f64, 1.0);
+        }
+        let points = history.all_points();
+        assert!(points.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(points.last(), Some(&(599.0, 1.0, 0.0)));
+        let raw = points.iter().filter(|(_, _, width)| *width == 0.0).count();
+        assert_eq!(raw, 301);
+        // The second wide tier only fills in before the raw samples
+        let (oldest, _, width) = points[0];
+        assert_eq!((oldest, width), (0.0, 1.0));
+        assert!(points.iter().all(|(_, value, _)| *value == 1.0));
+    }
+
+    #[test]
+    fn recent_is_measured_from_the_newest_point() {
+        let mut history = History::default();
+        for time in 0..10 {
+            history.push(time as f64, time as f64);
+        }
+        let values = history
+            .recent(3.0)
+            .iter()
+            .map(|(_, v)| *v)
+            .collect::<Vec<f64>>();
+        assert_eq!(values, [6.0, 7.0, 8.0, 9.0]);
+        assert!(History::default().recent(3.0).is_empty());
+    }
+}
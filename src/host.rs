@@ -0,0 +1,368 @@
+use std::{fs, path::Path};
+
+fn read_trimmed(path: &str) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+// DMI strings are the most reliable signal, cloud providers set them to
+// something recognisable even when the underlying hypervisor is KVM or Xen
+const DMI_SIGNATURES: [(&str, &str); 12] = [
+    ("Amazon EC2", "EC2"),
+    ("Google Compute Engine", "GCE"),
+    ("DigitalOcean", "DigitalOcean"),
+    ("Hetzner", "Hetzner"),
+    ("OpenStack", "OpenStack"),
+    ("VMware", "VMware"),
+    ("VirtualBox", "VirtualBox"),
+    ("innotek", "VirtualBox"),
+    ("Parallels", "Parallels"),
+    ("Xen", "Xen"),
+    ("KVM", "KVM"),
+    ("QEMU", "KVM"),
+];
+
+// Azure sets this asset tag on every VM, Hyper-V itself does not
+const AZURE_ASSET_TAG: &str = "7783-7084-3265-9085-8269-3286-77";
+
+pub fn detect_virtualization() -> Option<String> {
+    let dmi = [
+        "/sys/class/dmi/id/sys_vendor",
+        "/sys/class/dmi/id/product_name",
+        "/sys/class/dmi/id/bios_vendor",
+        "/sys/class/dmi/id/board_vendor",
+    ]
+    .iter()
+    .filter_map(|path| read_trimmed(path))
+    .collect::<Vec<String>>();
+
+    for (signature, name) in DMI_SIGNATURES.iter() {
+        if dmi.iter().any(|value| value.contains(signature)) {
+            return Some(name.to_string());
+        }
+    }
+
+    // Surface and other Microsoft hardware share the vendor, only guests are
+    // named Virtual Machine
+    let product_name = read_trimmed("/sys/class/dmi/id/product_name");
+    if dmi.iter().any(|value| value.contains("Microsoft"))
+        && product_name.as_deref() == Some("Virtual Machine")
+    {
+        let asset_tag = read_trimmed("/sys/class/dmi/id/chassis_asset_tag");
+        if asset_tag.as_deref() == Some(AZURE_ASSET_TAG) {
+            return Some("Azure".to_string());
+        }
+        return Some("Hyper-V".to_string());
+    }
+
+    if let Some(hypervisor) = read_trimmed("/sys/hypervisor/type") {
+        return Some(hypervisor);
+    }
+    if Path::new("/proc/xen").exists() {
+        return Some("Xen".to_string());
+    }
+
+    // Catches hypervisors without DMI hints, e.g. some ARM guests
+    let cpuinfo = fs::read_to_string("/proc/cpuinfo").unwrap_or_default();
+    let has_flag = cpuinfo
+        .lines()
+        .filter(|line| line.starts_with("flags"))
+        .any(|line| line.split_whitespace().any(|flag| flag == "hypervisor"));
+    if has_flag {
+        return Some("VM".to_string());
+    }
+
+    None
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CpuTimes {
+    pub total: u64,
+    pub steal: u64,
+}
+
+// Aggregate line of /proc/stat: user nice system idle iowait irq softirq steal ...
+pub fn read_cpu_times() -> Option<CpuTimes> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    let line = stat.lines().find(|line| line.starts_with("cpu "))?;
+    let values = line
+        .split_whitespace()
+        .skip(1)
+        .filter_map(|v| v.parse::<u64>().ok())
+        .collect::<Vec<u64>>();
+
+    // guest and guest_nice are already included in user and nice
+    Some(CpuTimes {
+        total: values.iter().take(8).sum(),
+        steal: values.get(7).copied().unwrap_or(0),
+    })
+}
+
+pub fn detect_container() -> Option<String> {
+    if Path::new("/.dockerenv").exists() {
+        return Some("Docker".to_string());
+    }
+    if Path::new("/run/.containerenv").exists() {
+        return Some("Podman".to_string());
+    }
+
+    let cgroup = fs::read_to_string("/proc/1/cgroup").unwrap_or_default();
+    let signatures = [
+        ("kubepods", "Kubernetes"),
+        ("docker", "Docker"),
+        ("libpod", "Podman"),
+        ("lxc", "LXC"),
+    ];
+    for (signature, name) in signatures.iter() {
+        if cgroup.contains(signature) {
+            return Some(name.to_string());
+        }
+    }
+
+    // systemd-nspawn and friends export this to the init process
+    std::env::var("container").ok().filter(|s| !s.is_empty())
+}
+
+fn is_cgroup_v2() -> bool {
+    Path::new("/sys/fs/cgroup/cgroup.controllers").exists()
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct CgroupLimits {
+    pub memory_limit: Option<u64>,
+    // In cores, e.g. 1.5 for a quota of 150000 per 100000 period
+    pub cpu_limit: Option<f64>,
+}
+
+pub fn read_cgroup_limits() -> CgroupLimits {
+    if is_cgroup_v2() {
+        let memory_limit =
+            read_trimmed("/sys/fs/cgroup/memory.max").and_then(|v| v.parse::<u64>().ok());
+        let cpu_limit = read_trimmed("/sys/fs/cgroup/cpu.max").and_then(|v| {
+            let mut parts = v.split_whitespace();
+            let quota = parts.next()?.parse::<f64>().ok()?;
+            let period = parts.next()?.parse::<f64>().ok()?;
+            Some(quota / period)
+        });
+        return CgroupLimits {
+            memory_limit,
+            cpu_limit,
+        };
+    }
+
+    // v1 reports "unlimited" as a huge page aligned number rather than a keyword
+    let memory_limit = read_trimmed("/sys/fs/cgroup/memory/memory.limit_in_bytes")
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|v| *v < i64::MAX as u64 / 2);
+    let quota = read_trimmed("/sys/fs/cgroup/cpu/cpu.cfs_quota_us")
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0);
+    let period = read_trimmed("/sys/fs/cgroup/cpu/cpu.cfs_period_us")
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|v| *v > 0);
+    let cpu_limit = match (quota, period) {
+        (Some(quota), Some(period)) => Some(quota as f64 / period as f64),
+        _ => None,
+    };
+    CgroupLimits {
+        memory_limit,
+        cpu_limit,
+    }
+}
+
+fn read_stat_value(path: &str, key: &str) -> Option<u64> {
+    fs::read_to_string(path).ok()?.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        if parts.next()? == key {
+            parts.next()?.parse::<u64>().ok()
+        } else {
+            None
+        }
+    })
+}
+
+// Excludes reclaimable page cache, matching what `docker stats` reports
+pub fn read_cgroup_memory_usage() -> Option<u64> {
+    if is_cgroup_v2() {
+        let current = read_trimmed("/sys/fs/cgroup/memory.current")?
+            .parse::<u64>()
+            .ok()?;
+        let inactive = read_stat_value("/sys/fs/cgroup/memory.stat", "inactive_file").unwrap_or(0);
+        return Some(current.saturating_sub(inactive));
+    }
+    let usage = read_trimmed("/sys/fs/cgroup/memory/memory.usage_in_bytes")?
+        .parse::<u64>()
+        .ok()?;
+    let inactive =
+        read_stat_value("/sys/fs/cgroup/memory/memory.stat", "total_inactive_file").unwrap_or(0);
+    Some(usage.saturating_sub(inactive))
+}
+
+// Cumulative CPU time used by the cgroup in microseconds
+pub fn read_cgroup_cpu_usage() -> Option<u64> {
+    if is_cgroup_v2() {
+        return read_stat_value("/sys/fs/cgroup/cpu.stat", "usage_usec");
+    }
+    read_trimmed("/sys/fs/cgroup/cpuacct/cpuacct.usage")?
+        .parse::<u64>()
+        .ok()
+        .map(|ns| ns / 1000)
+}
+
+// Share of the last 10 seconds in which some task stalled on "cpu", "memory"
+// or "io", from PSI
+pub fn read_pressure(resource: &str) -> Option<f64> {
+    let content = fs::read_to_string(format!("/proc/pressure/{}", resource)).ok()?;
+    let line = content.lines().find(|line| line.starts_with("some"))?;
+    line.split_whitespace()
+        .find_map(|field| field.strip_prefix("avg10="))?
+        .parse::<f64>()
+        .ok()
+}
+
+// Bits the kernel estimates are in its pool. Since Linux 5.18 this always
+// reads 256 once the pool has been seeded.
+pub fn read_entropy() -> Option<u64> {
+    read_trimmed("/proc/sys/kernel/random/entropy_avail")?
+        .parse::<u64>()
+        .ok()
+}
+
+// getrandom() blocks until the pool is seeded after boot, which on boards
+// without a hardware RNG can take minutes and stalls whatever asked
+pub fn random_ready() -> bool {
+    let mut byte = 0u8;
+    let read = unsafe {
+        libc::getrandom(
+            &mut byte as *mut u8 as *mut libc::c_void,
+            1,
+            libc::GRND_NONBLOCK,
+        )
+    };
+    read == 1 || std::io::Error::last_os_error().raw_os_error() != Some(libc::EAGAIN)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClockStatus {
+    pub synchronized: bool,
+    // Seconds the kernel is still slewing the clock by, as set by the NTP daemon
+    pub offset: f64,
+}
+
+// The kernel's NTP state, whichever daemon drives it. This is what
+// timedatectl's "System clock synchronized" goes by: the daemon clears
+// STA_UNSYNC once synced, and the kernel sets it again if the daemon stops
+// updating the maximum error for long enough.
+pub fn read_clock() -> Option<ClockStatus> {
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    if state < 0 {
+        return None;
+    }
+    // 16 seconds is NTP's "unsynchronized" maximum error
+    let synchronized = state != libc::TIME_ERROR
+        && timex.status & libc::STA_UNSYNC == 0
+        && timex.maxerror < 16_000_000;
+    let unit = if timex.status & libc::STA_NANO != 0 {
+        1e9
+    } else {
+        1e6
+    };
+    Some(ClockStatus {
+        synchronized,
+        offset: timex.offset as f64 / unit,
+    })
+}
+
+// Processes created since boot, from the "processes" line of /proc/stat.
+// Threads count too, they're created the same way.
+pub fn read_forks() -> Option<u64> {
+    let stat = fs::read_to_string("/proc/stat").ok()?;
+    stat.lines()
+        .find_map(|line| line.strip_prefix("processes "))?
+        .trim()
+        .parse::<u64>()
+        .ok()
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TaskCounts {
+    pub processes: u64,
+    // Every thread of every process, each takes a PID
+    pub threads: u64,
+    pub pid_max: u64,
+    // kernel.threads-max, sized from RAM at boot
+    pub threads_max: Option<u64>,
+    // pids.current and pids.max of our cgroup, None when unlimited
+    pub cgroup_pids: Option<(u64, u64)>,
+}
+
+impl TaskCounts {
+    // Used and maximum for each limit that applies
+    pub fn limits(&self) -> Vec<(&'static str, u64, u64)> {
+        let mut limits = vec![("PIDs", self.threads, self.pid_max)];
+        if let Some(threads_max) = self.threads_max {
+            limits.push(("threads", self.threads, threads_max));
+        }
+        if let Some((current, max)) = self.cgroup_pids {
+            limits.push(("cgroup pids", current, max));
+        }
+        limits
+    }
+
+    // Whichever limit is closest to running out
+    pub fn tightest(&self) -> Option<(&'static str, u64, u64)> {
+        let share = |(_, used, max): &(&str, u64, u64)| *used as f64 / (*max).max(1) as f64;
+        self.limits()
+            .into_iter()
+            .max_by(|a, b| share(a).total_cmp(&share(b)))
+    }
+}
+
+// Containers and systemd services get a pids.max of their own, often far
+// below pid_max
+fn read_cgroup_pids() -> Option<(u64, u64)> {
+    let dir = if is_cgroup_v2() {
+        "/sys/fs/cgroup"
+    } else {
+        "/sys/fs/cgroup/pids"
+    };
+    // "max" when unlimited
+    let max = read_trimmed(&format!("{}/pids.max", dir))?
+        .parse::<u64>()
+        .ok()?;
+    let current = read_trimmed(&format!("{}/pids.current", dir))?
+        .parse::<u64>()
+        .ok()?;
+    Some((current, max))
+}
+
+pub fn read_task_counts() -> Option<TaskCounts> {
+    // The fourth field of loadavg is running/total scheduling entities
+    let loadavg = read_trimmed("/proc/loadavg")?;
+    let threads = loadavg
+        .split_whitespace()
+        .nth(3)?
+        .split_once('/')?
+        .1
+        .parse::<u64>()
+        .ok()?;
+    let processes = fs::read_dir("/proc")
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit())
+        })
+        .count() as u64;
+    Some(TaskCounts {
+        processes,
+        threads,
+        pid_max: read_trimmed("/proc/sys/kernel/pid_max")?.parse().ok()?,
+        threads_max: read_trimmed("/proc/sys/kernel/threads-max").and_then(|v| v.parse().ok()),
+        cgroup_pids: read_cgroup_pids(),
+    })
+}
@@ -1,446 +1,6662 @@
+mod alerts;
+mod anomalies;
+mod capture;
+mod columns;
+mod command;
+mod config;
+mod containers;
+mod control;
+mod daemon;
+mod dns;
+mod export;
+mod filter;
+mod gpu;
+mod history;
+mod host;
+mod maintenance;
+mod netstat;
+mod palette;
+mod persist;
+mod power;
+mod preset;
+mod privilege;
+mod procfs;
+mod profile;
+mod report;
+mod sensors;
+mod session;
+mod setup;
+mod storage;
+mod sysctl;
+mod systemd;
+mod tree;
+
 use anyhow::Result;
+use chrono::{Local, TimeZone};
+use clap::{ArgGroup, Parser};
+use columns::{Column, Units};
+use config::{Config, Panel, PANELS};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{
+        self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers,
+        MouseEvent, MouseEventKind,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen, SetTitle,
+    },
+};
+use filter::Filter;
+use history::{History, TimeWindow};
+use palette::THEMES;
+use preset::Preset;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io::{self, IsTerminal, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use sysinfo::{
+    CpuExt, CpuRefreshKind, Pid, PidExt, ProcessExt, Signal, System, SystemExt, UserExt,
+};
+use sysinfo::{DiskExt, NetworkExt};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::{Notify, RwLock},
 };
-use std::{io, sync::Arc, time::Duration};
-use sysinfo::NetworkExt;
-use sysinfo::{CpuExt, ProcessExt, System, SystemExt};
-use tokio::sync::RwLock;
 use tui::{
     backend::{Backend, CrosstermBackend},
     layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     symbols,
-    text::Span,
+    text::{Span, Spans},
     widgets::{
-        Axis, BarChart, Block, BorderType, Borders, Cell, Chart, Dataset, GraphType, Paragraph,
-        Row, Table,
+        Axis, Block, BorderType, Borders, Cell, Chart, Clear, Dataset, Gauge, GraphType, Paragraph,
+        Row, Sparkline, Table, TableState, Tabs, Wrap,
     },
     Frame, Terminal,
 };
 
+#[derive(Debug, Parser)]
+#[command(about = "Terminal resource viewer")]
+#[command(group(ArgGroup::new("output").args(["once", "stream", "plain", "daemon"])))]
+struct Args {
+    /// Read settings from this file instead of the default config.toml
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Save chart history on exit and reload it on the next start
+    #[arg(long)]
+    persist_history: bool,
+
+    /// Where to keep the persisted history, defaults to the XDG state directory
+    #[arg(long, value_name = "PATH", requires = "persist_history")]
+    history_file: Option<PathBuf>,
+
+    /// Reopen the interface as the last run left it, with the same layout, sort, filters and
+    /// marked processes, saved every few seconds so it survives a crash or reboot
+    #[arg(long)]
+    resume: bool,
+
+    /// Print a plain text snapshot to stdout and exit, without the interface
+    #[arg(long)]
+    once: bool,
+
+    /// Print one JSON object per sample to stdout until interrupted, without the interface
+    #[arg(long)]
+    stream: bool,
+
+    /// Print a short sentence style summary every few seconds, for screen readers
+    #[arg(long)]
+    plain: bool,
+
+    /// Keep sampling in the background without the interface, which then starts with the history
+    /// collected meanwhile. Samples CPU, memory, clock speed and temperatures every second unless
+    /// --interval is given, the other charts come from --persist-history
+    #[arg(long)]
+    daemon: bool,
+
+    /// Milliseconds between samples, 1000 for --stream and 5000 for --plain by default, otherwise
+    /// the preset's or refresh_ms from the config
+    #[arg(long, value_name = "MS", value_parser = clap::value_parser!(u64).range(1..))]
+    interval: Option<u64>,
+
+    /// Minutes of history the charts keep, history_minutes from the config or at most a day by default
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..))]
+    history_length: Option<u64>,
+
+    /// Column the process table is sorted by at start, instead of the config's
+    #[arg(long, value_enum, value_name = "COLUMN")]
+    sort: Option<Column>,
+
+    /// Colors to draw with, instead of the config's
+    #[arg(long, value_enum)]
+    theme: Option<palette::Theme>,
+
+    /// Leave the mouse to the terminal, e.g. to select text, instead of hovering over charts
+    #[arg(long)]
+    no_mouse: bool,
+
+    /// When started through sudo or pkexec, switch back to the user who ran it before anything
+    /// else, keeping only the capabilities for reading other users' processes. Panels that only
+    /// see that user's processes are marked degraded
+    #[arg(long)]
+    drop_privileges: bool,
+
+    /// Number of processes listed, busiest first, 10 by default and 3 for --plain
+    #[arg(long, value_name = "N", requires = "output")]
+    top: Option<usize>,
+
+    /// Lay the interface out for one kind of use: ml charts the GPU's engines, memory, power and
+    /// processes, gaming shows sparklines sampled every 2 seconds without scanning processes, server
+    /// shows load, pressure, disk latency, connections and failed units and skips the GPU
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+}
+
 #[derive(Debug)]
 struct SystemData<'a> {
     system: System,
     total_memory: f64,
-    memory_usage: Vec<f64>,
-    memory_usage_as_tuple: Vec<(f64, f64)>,
+    // Used memory in GiB
+    memory_history: History,
+    // GiB of that stored in tmpfs mounts
+    tmpfs_history: History,
+    tmpfs_used: f64,
     cpus: Vec<CPUData>,
     cpu_usage: f64,
     packets: [(&'a str, u64); 2],
-    processes: Vec<Vec<String>>,
+    // Bytes received and transmitted per second across the interfaces
+    network_history: [History; 2],
+    // By name
+    interfaces: Vec<netstat::Interface>,
+    processes: Vec<ProcessData>,
+    boot_time: u64,
+    uptime: u64,
+    virtualization: Option<String>,
+    cpu_times: host::CpuTimes,
+    steal_usage: f64,
+    used_memory: f64,
+    container: Option<String>,
+    cgroup_limits: host::CgroupLimits,
+    cgroup_cpu_sample: Option<(u64, Instant)>,
+    last_refresh: Option<Instant>,
+    // Recent CPU samples per pid, averaged for the CPU avg column
+    cpu_samples: HashMap<u32, VecDeque<(Instant, f32)>>,
+    cpu_average_window: Duration,
+    memory_pressure: Option<f64>,
+    entropy: Option<u64>,
+    random_ready: bool,
+    clock: Option<host::ClockStatus>,
+    cpu_sensors: Vec<sensors::Sensor>,
+    // Average over the cores in MHz, and the package in Celsius
+    frequency_history: History,
+    package_temperature_history: History,
+    container_names: containers::Resolver,
+    // Read from /proc ahead of update_system_info, which only picks them up
+    process_extras: HashMap<u32, procfs::Extras>,
+    // Set by the UI while the table is being navigated, the process list is
+    // then left as is while everything else keeps sampling
+    freeze_processes: Arc<AtomicBool>,
+    alerts: alerts::Engine,
+    tcp_states: Option<[u64; 11]>,
+    // Entries and limit, None without the nf_conntrack module
+    conntrack: Option<(u64, u64)>,
+    tasks: Option<host::TaskCounts>,
+    // Processes and threads, and forks per second
+    task_history: [History; 2],
+    forks: Option<u64>,
+    fork_history: History,
+    tcp_segments: Option<netstat::TcpSegments>,
+    // Per second since the previous refresh
+    retransmit_rate: Option<f64>,
+    // Share of the segments sent since the previous refresh that were resent
+    retransmit_share: Option<f64>,
+    retransmit_history: History,
+    // One per netstat::CHARTED_STATES
+    socket_history: [History; 3],
+    // Present when a name to resolve is configured
+    dns: Option<dns::DnsHealth>,
+    // None until systemctl has answered, and on systems without systemd
+    failed_units: Option<Vec<systemd::FailedUnit>>,
+    // Read while the storage panel is shown, see App::disks
+    disks: Vec<storage::Disk>,
+    // Read while the microphone and camera popup is open, see App::captures
+    captures: Vec<capture::Capture>,
+    // Read while the anomalies popup is open, see App::anomalies
+    anomalies: Vec<anomalies::Anomaly>,
+    power_profile: Option<power::PowerProfile>,
+    backlight: Option<power::Backlight>,
+    inhibitors: Option<Vec<systemd::Inhibitor>>,
+    filesystems: Vec<storage::Filesystem>,
+    full_within_days: f64,
+    disk_latency: Vec<storage::DiskLatency>,
+    // Bytes read and written per second across the disks
+    disk_io_history: [History; 2],
+    gpu: gpu::Gpu,
+    // Only CPU, memory, GPU and temperatures are sampled, see Preset::lightweight
+    lightweight: bool,
+    // No GPU or backlight, see Preset::headless
+    headless: bool,
+    // 1, 5 and 15 minute load averages
+    load_history: [History; 3],
+    // Stalled share of the last 10 seconds on cpu, memory and io, from PSI
+    pressure_history: [History; 3],
 }
 
-#[derive(Debug)]
-struct CPUData {
-    name: String,
-    raw_data: Vec<f64>,
-    data: Vec<(f64, f64)>,
-    color: Color,
+#[derive(Debug, PartialEq)]
+enum Popup {
+    Sysctl,
+    ProcessDetail(u32),
+    Columns,
+    Users,
+    Confirm,
+    Maintenance,
+    Sensors,
+    Network,
+    Storage,
+    Anomalies,
+    Throttling,
+    Tasks,
+    FailedUnits,
+    Sleep,
+    Capture,
+    Gpu,
+    Settings,
+    Help,
 }
 
-impl SystemData<'_> {
-    fn update_system_info(&mut self) {
-        self.system.refresh_all();
+// The tabs along the top, each a full screen view picked with its number key
+#[derive(Debug, Clone, Copy, PartialEq, Default, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum AppView {
+    #[default]
+    Overview,
+    Processes,
+    Network,
+    Disks,
+    Sensors,
+}
 
-        // Memory
-        self.memory_usage
-            .push((self.system.used_memory() as f64) / 1024.0 / 1024.0 / 1024.0);
+const VIEWS: [AppView; 5] = [
+    AppView::Overview,
+    AppView::Processes,
+    AppView::Network,
+    AppView::Disks,
+    AppView::Sensors,
+];
 
-        if self.memory_usage.len() > 500 {
-            self.memory_usage.remove(0);
+impl AppView {
+    fn name(self) -> &'static str {
+        match self {
+            AppView::Overview => "Overview",
+            AppView::Processes => "Processes",
+            AppView::Network => "Network",
+            AppView::Disks => "Disks",
+            AppView::Sensors => "Sensors",
         }
+    }
+}
 
-        self.total_memory = self.system.total_memory() as f64;
-        self.memory_usage_as_tuple = create_tuple_vec_for_graph(&self.memory_usage);
+// Rows of the settings popup
+#[derive(Debug, Clone, Copy)]
+enum Setting {
+    Refresh,
+    Units,
+    Theme,
+    Panel(Panel),
+}
 
-        // CPU
-        let all_cpus = vec![self.system.global_cpu_info()];
-        // For future improvement to add individual cpu usage
-        for (_, cpu) in all_cpus.iter().enumerate() {
-            let cpu_name: String = format!("CPU {}", cpu.name());
+const SETTINGS: [Setting; 9] = [
+    Setting::Refresh,
+    Setting::Units,
+    Setting::Theme,
+    Setting::Panel(Panel::Cpu),
+    Setting::Panel(Panel::Memory),
+    Setting::Panel(Panel::Io),
+    Setting::Panel(Panel::Processes),
+    Setting::Panel(Panel::Network),
+    Setting::Panel(Panel::Disks),
+];
 
-            let cpu_vec = self.cpus.iter().position(|x| x.name == cpu_name);
-            let cpu_index: usize = match cpu_vec {
-                Some(index) => index,
-                None => {
-                    self.cpus.push(CPUData {
-                        name: cpu_name.clone(),
-                        raw_data: Vec::new(),
-                        data: Vec::new(),
-                        color: Color::Green,
-                    });
-                    self.cpus.len() - 1
-                }
-            };
+#[derive(Debug, Clone, PartialEq)]
+enum Action {
+    Terminate,
+    Kill,
+    Renice(i32),
+    Ionice(control::IoPriority),
+    Pin(String),
+}
 
-            let cpu_vec = self.cpus.get_mut(cpu_index).unwrap();
-            cpu_vec.raw_data.push(cpu.cpu_usage() as f64);
-            if cpu_vec.raw_data.len() > 500 {
-                cpu_vec.raw_data.remove(0);
-            }
-            cpu_vec.data = create_tuple_vec_for_graph(&cpu_vec.raw_data);
+impl Action {
+    fn describe(&self) -> String {
+        match self {
+            Action::Terminate => "Send SIGTERM to".to_string(),
+            Action::Kill => "Send SIGKILL to".to_string(),
+            Action::Renice(nice) => format!("Renice to {}:", nice),
+            Action::Ionice(priority) => format!("Set I/O priority to {}:", priority),
+            Action::Pin(cpus) => format!("Pin to CPUs {}:", cpus),
         }
-        self.cpu_usage = self.system.global_cpu_info().cpu_usage() as f64;
+    }
 
-        // Network
-        let all_networks = self.system.networks();
+    fn describe_done(&self) -> String {
+        match self {
+            Action::Terminate => "Sent SIGTERM to".to_string(),
+            Action::Kill => "Sent SIGKILL to".to_string(),
+            Action::Renice(nice) => format!("Reniced to {}:", nice),
+            Action::Ionice(priority) => format!("Set I/O priority to {}:", priority),
+            Action::Pin(cpus) => format!("Pinned to CPUs {}:", cpus),
+        }
+    }
 
-        let (recieved_packets, transmitted_packets) = all_networks
-            .into_iter()
-            .map(|(_, net)| {
-                return (net.packets_received(), net.packets_transmitted());
-            })
-            .reduce(|(a, b), (c, d)| {
-                return (a + c, b + d);
-            })
-            .unwrap_or((0, 0));
+    fn apply(&self, pid: u32) -> io::Result<()> {
+        match self {
+            Action::Terminate => control::signal(pid, libc::SIGTERM),
+            Action::Kill => control::signal(pid, libc::SIGKILL),
+            Action::Renice(nice) => control::renice(pid, *nice),
+            Action::Ionice(priority) => control::ionice(pid, *priority),
+            Action::Pin(cpus) => control::pin(pid, cpus),
+        }
+    }
 
-        self.packets = [
-            ("Packets In", recieved_packets),
-            ("Packets Out", transmitted_packets),
-        ];
+    // The same action as one command line for sudo or pkexec to run, only
+    // for those whose tool takes several PIDs at once
+    fn command(&self, pids: &[u32]) -> Option<Vec<String>> {
+        let command = match self {
+            Action::Terminate => "kill -s TERM".to_string(),
+            Action::Kill => "kill -s KILL".to_string(),
+            Action::Renice(nice) => format!("renice -n {}", nice),
+            Action::Ionice(_) | Action::Pin(_) => return None,
+        };
+        let mut command = command
+            .split(' ')
+            .map(String::from)
+            .collect::<Vec<String>>();
+        command.extend(pids.iter().map(|pid| pid.to_string()));
+        Some(command)
+    }
+}
 
-        // Processes
-        let num_cpus = self.system.cpus().len() as f32;
-        let all_processes = self.system.processes();
-        let mut sorted_processes = all_processes
-            .iter()
-            .map(|(_, p)| {
-                return (
-                    p.pid().to_string(),
-                    p.name().to_owned(),
-                    p.cpu_usage() / num_cpus,
-                );
-            })
-            .collect::<Vec<(String, String, f32)>>();
+// An action on several processes, waiting for the user to confirm it
+#[derive(Debug)]
+struct PendingAction {
+    action: Action,
+    // Pid and name, so the confirmation still reads well if a process exits
+    processes: Vec<(u32, String)>,
+    // Set when offering to retry what wasn't permitted through sudo or pkexec
+    escalator: Option<&'static str>,
+}
 
-        sorted_processes.sort_by(|a, b| {
-            return a.2.partial_cmp(&b.2).unwrap();
-        });
+// A read too slow to do while drawing, done by a task of its own for as long
+// as a panel shows the result. Drawing asks for it on every frame, the task
+// reads again each interval while it was asked and idles once it wasn't.
+#[derive(Debug, Default)]
+struct OnDemand {
+    asked: AtomicBool,
+    idle: AtomicBool,
+    // Wakes an idle task as soon as the panel opens
+    woken: Notify,
+}
 
-        // print first
-        let top_processes = sorted_processes
-            .iter()
-            .rev()
-            .take(100)
-            .map(|(pid, name, cpu)| {
-                return vec![pid.to_owned(), name.to_owned(), format!("{:.2}%", cpu)];
-            })
-            .collect::<Vec<Vec<String>>>();
-        self.processes = top_processes;
+impl OnDemand {
+    fn ask(&self) {
+        self.asked.store(true, Ordering::Relaxed);
+        if self.idle.swap(false, Ordering::Relaxed) {
+            self.woken.notify_one();
+        }
+    }
+
+    // Returns once drawing has asked since the last read
+    async fn asked(&self) {
+        while !self.asked.swap(false, Ordering::Relaxed) {
+            self.idle.store(true, Ordering::Relaxed);
+            self.woken.notified().await;
+        }
     }
 }
 
-fn memory_to_gb(memory: &f64) -> String {
-    format!("{:.2} GB", memory / 1024.0 / 1024. / 1024.0)
+// UI only state, owned by the render loop
+#[derive(Debug, Default)]
+struct App {
+    view: AppView,
+    popup: Option<Popup>,
+    sysctls: Vec<sysctl::Sysctl>,
+    sysctl_query: String,
+    sysctl_state: TableState,
+    process_state: TableState,
+    // Followed across refreshes, process_state only holds its current row
+    selected_pid: Option<u32>,
+    // Rows the process table has room for, moved by PageUp and PageDown
+    process_page: usize,
+    process_details: procfs::ProcessDetails,
+    mouse_position: Option<(u16, u16)>,
+    // False with --no-mouse, when the terminal keeps the mouse
+    mouse: bool,
+    units: Units,
+    inspect: Option<Inspect>,
+    // Shared with the sampling task, which skips updates while set
+    paused: Arc<AtomicBool>,
+    // Milliseconds between samples, read by the sampling task before each sleep
+    refresh_interval: Arc<AtomicU64>,
+    // Wakes the sampling task for an immediate sample, even while paused
+    refresh_now: Arc<Notify>,
+    // Wakes the power profile poller after a switch
+    power_changed: Arc<Notify>,
+    // Chosen with :power, switched to once the state lock has been let go
+    power_choice: Option<String>,
+    // Shared with the sampling task, see SystemData::freeze_processes
+    freeze_processes: Arc<AtomicBool>,
+    freeze_while_navigating: bool,
+    last_navigation: Option<Instant>,
+    // Seconds scrolled back from the newest sample, only while paused
+    scroll: f64,
+    window: TimeWindow,
+    cpu_plot_area: Rect,
+    memory_plot_area: Rect,
+    // Process table columns in display order
+    columns: Vec<Column>,
+    // Column picked to sort by and whether it's descending, the table is
+    // ordered by CPU otherwise
+    sort: Option<(Column, bool)>,
+    highlights: Vec<config::Highlight>,
+    // Characters scrolled into truncated name and command cells
+    process_scroll: u16,
+    tree: bool,
+    // Chart every core instead of the total
+    per_core: bool,
+    // Sum descendants' CPU and memory into each row
+    aggregate: bool,
+    // Tree nodes whose descendants are hidden
+    collapsed: HashSet<u32>,
+    // List processes of the same systemd unit together, busiest unit first
+    group_units: bool,
+    // Only processes owned by this user are listed
+    user_filter: Option<String>,
+    users: Vec<String>,
+    user_state: TableState,
+    // Typed after `/`, see filter::Filter for the syntax
+    process_query: String,
+    process_filter: Option<Filter>,
+    filter_error: Option<String>,
+    editing_filter: bool,
+    // Typed after `:`, None when the command line is closed
+    command_input: Option<String>,
+    pending: Option<PendingAction>,
+    pending_task: Option<maintenance::Task>,
+    sensor_config: Vec<config::SensorConfig>,
+    // Read when the panel opens and again every refresh interval after that
+    sensors: Vec<sensors::Sensor>,
+    sensors_read: Option<Instant>,
+    disks: Arc<OnDemand>,
+    anomalies: Arc<OnDemand>,
+    captures: Arc<OnDemand>,
+    // Shown in place of the usage title until it expires
+    status: Option<(String, Instant)>,
+    // Processes marked with space for batch actions
+    marked: HashSet<u32>,
+    column_state: TableState,
+    settings_state: TableState,
+    // Lines scrolled in the help popup
+    help_scroll: u16,
+    // Charted by the network panel and picked in the network popup, None for
+    // all of them together
+    interface: Option<String>,
+    // Written to by the settings popup, None without a home directory
+    config_path: Option<PathBuf>,
+    // What the terminal can show, colors are mapped down to it after drawing
+    color_depth: palette::ColorDepth,
+    theme: palette::Theme,
+    // Shown by the general layout
+    panels: Vec<Panel>,
+    // Alerts fired so far, to notice new ones
+    alerts_seen: u64,
+    bell: bool,
+    flash_alerts: bool,
+    // Charts whose border blinks, and since when
+    flash: Option<(Vec<ChartKind>, Instant)>,
+    set_title: bool,
+    // Last title sent, it's only sent again when the numbers change
+    title: String,
+    preset: Option<Preset>,
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+impl App {
+    fn toggle_inspect(&mut self) {
+        self.inspect = match self.inspect {
+            Some(_) => None,
+            None => Some(Inspect {
+                chart: ChartKind::Cpu,
+                offset: 0,
+            }),
+        };
+    }
 
-    // run app
-    let _ = run_app(&mut terminal).await;
+    fn move_inspect(&mut self, offset: i64) {
+        if let Some(inspect) = self.inspect.as_mut() {
+            let plot = match inspect.chart {
+                ChartKind::Cpu => self.cpu_plot_area,
+                ChartKind::Memory => self.memory_plot_area,
+            };
+            let max_offset = plot.width.saturating_sub(1) as i64;
+            inspect.offset = (inspect.offset as i64 + offset).clamp(0, max_offset) as u16;
+        }
+    }
 
-    // restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
 
-    Ok(())
-}
+    fn toggle_pause(&mut self) {
+        let paused = !self.is_paused();
+        self.paused.store(paused, Ordering::Relaxed);
+        if !paused {
+            self.scroll = 0.0;
+        }
+    }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>) -> Result<()> {
-    let poll_rate = 100;
+    // Steps are a tenth of the window, the since start window always shows everything
+    fn scroll_history(&mut self, steps: f64, history: &History) {
+        let seconds = match self.window.seconds() {
+            Some(seconds) => seconds,
+            None => return,
+        };
+        let oldest = history.points(self.window).first().map(|(time, _)| *time);
+        let max_scroll = match (oldest, history.newest_time()) {
+            (Some(oldest), Some(newest)) => (newest - oldest - seconds).max(0.0),
+            _ => 0.0,
+        };
+        self.scroll = (self.scroll + steps * seconds / 10.0).clamp(0.0, max_scroll);
+    }
 
-    let mut state = SystemData {
-        system: System::new_all(),
-        total_memory: 0.0,
-        memory_usage: Vec::new(),
-        memory_usage_as_tuple: Vec::new(),
-        cpus: Vec::new(),
-        packets: [("Packets In", 0), ("Packets Out", 0)],
-        processes: Vec::new(),
-        cpu_usage: 0.0,
-    };
-    state.update_system_info();
+    fn cycle_window(&mut self) {
+        self.window = self.window.next();
+        self.scroll = 0.0;
+    }
 
-    let system_data = Arc::new(RwLock::new(state));
+    fn switch_inspect_chart(&mut self) {
+        if let Some(inspect) = self.inspect.as_mut() {
+            inspect.chart = match inspect.chart {
+                ChartKind::Cpu => ChartKind::Memory,
+                ChartKind::Memory => ChartKind::Cpu,
+            };
+        }
+    }
 
-    let loop_system_data = system_data.clone();
-    tokio::spawn(async move {
-        loop {
-            loop_system_data.write().await.update_system_info();
-            tokio::time::sleep(Duration::from_millis(poll_rate)).await;
+    // Takes the pids of the rows as currently listed, the selection then
+    // sticks to the process rather than the row
+    fn scroll_processes(&mut self, offset: i64, pids: &[u32]) {
+        if pids.is_empty() {
+            self.process_state.select(None);
+            self.selected_pid = None;
+            return;
         }
-    });
+        let current = self.process_state.selected().unwrap_or(0) as i64;
+        let next = (current + offset).clamp(0, pids.len() as i64 - 1) as usize;
+        self.process_state.select(Some(next));
+        self.selected_pid = Some(pids[next]);
+        self.note_navigation();
+    }
 
-    loop {
-        let system_data = system_data.read().await;
-        terminal.draw(|f| {
-            ui(f, &system_data);
-        })?;
+    fn change_refresh_interval(&mut self, step: i64) {
+        let current = self.refresh_interval.load(Ordering::Relaxed);
+        // --interval and the config take any value, so this goes to the next
+        // listed one in that direction rather than by position
+        let next = if step > 0 {
+            REFRESH_INTERVALS.iter().find(|ms| **ms > current)
+        } else {
+            REFRESH_INTERVALS.iter().rev().find(|ms| **ms < current)
+        }
+        .copied()
+        .unwrap_or(current);
+        self.refresh_interval.store(next, Ordering::Relaxed);
+        self.set_status(format!("Refreshing every {}", format_interval(next)));
+    }
 
-        if event::poll(Duration::from_millis(poll_rate))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Char('q') => {
-                        return Ok(());
-                    }
-                    _ => {}
-                }
-            }
+    fn refresh(&mut self) {
+        self.refresh_now.notify_one();
+    }
+
+    fn note_navigation(&mut self) {
+        if self.freeze_while_navigating {
+            self.freeze_processes.store(true, Ordering::Relaxed);
+            self.last_navigation = Some(Instant::now());
         }
     }
-}
 
-fn ui<B: Backend>(f: &mut Frame<B>, system_data: &SystemData) {
-    // Wrapping block for a group
-    // Just draw the block and the group on the same area and build the group
-    // with at least a margin of 1
-    let size = f.size();
+    // Lets the table catch up once the selection has been still for a moment
+    fn thaw_processes(&mut self) {
+        if let Some(last) = self.last_navigation {
+            if last.elapsed() >= NAVIGATION_IDLE {
+                self.freeze_processes.store(false, Ordering::Relaxed);
+                self.last_navigation = None;
+            }
+        }
+    }
 
-    // Surrounding block
-    let block = Block::default()
-        .borders(Borders::ALL)
-        .title(" System Info ")
-        .title_alignment(Alignment::Left)
-        .border_type(BorderType::Rounded);
-    f.render_widget(block, size);
+    fn is_frozen(&self) -> bool {
+        self.freeze_processes.load(Ordering::Relaxed)
+    }
 
-    let chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .margin(1)
-        .constraints(
-            [
-                Constraint::Percentage(48),
-                Constraint::Percentage(48),
-                Constraint::Max(3),
-            ]
-            .as_ref(),
-        )
-        .split(f.size());
+    // Picking the sorted column again flips the direction
+    fn sort_by(&mut self, column: Column) {
+        let descending = match self.sort {
+            Some((current, descending)) if current == column => !descending,
+            _ => column.sorts_descending(),
+        };
+        self.sort = Some((column, descending));
+        self.set_status(format!(
+            "Sorted by {}, {}",
+            column.title(),
+            if descending {
+                "descending"
+            } else {
+                "ascending"
+            }
+        ));
+    }
 
-    // Top two inner blocks
-    let top_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
-        .split(chunks[0]);
+    // Steps through the shown columns, starting after the sorted one
+    fn cycle_sort(&mut self) {
+        let columns = shown_columns(self);
+        let next = match self.sort {
+            Some((current, _)) => columns
+                .iter()
+                .position(|column| *column == current)
+                .map_or(0, |index| (index + 1) % columns.len()),
+            None => 0,
+        };
+        if let Some(column) = columns.get(next) {
+            self.sort = None;
+            self.sort_by(*column);
+        }
+    }
 
-    let cpu_block = cpu_block(f, system_data, top_chunks[0]);
-    f.render_widget(cpu_block, top_chunks[0]);
+    fn invert_sort(&mut self) {
+        let (column, descending) = self.sort.unwrap_or((Column::Cpu, true));
+        self.sort = Some((column, descending));
+        self.sort_by(column);
+    }
 
-    let ram_block = ram_block(f, system_data, top_chunks[1]);
-    f.render_widget(ram_block, top_chunks[1]);
+    fn select_first_process(&mut self) {
+        self.process_state.select(Some(0));
+        self.selected_pid = None;
+    }
 
-    let bottom_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
-        .split(chunks[1]);
+    // Moves the highlight to wherever the selected process ended up after a
+    // refresh. When it has exited the row index stays and the process now
+    // listed there becomes the selection.
+    fn follow_selected_process(&mut self, pids: &[u32]) {
+        let index = self
+            .selected_pid
+            .and_then(|pid| pids.iter().position(|p| *p == pid))
+            .or_else(|| {
+                self.process_state
+                    .selected()
+                    .map(|index| index.min(pids.len().saturating_sub(1)))
+            })
+            .filter(|_| !pids.is_empty());
+        self.process_state.select(index);
+        self.selected_pid = index.map(|index| pids[index]);
+    }
 
-    let table = processes_block(system_data);
-    f.render_widget(table, bottom_chunks[0]);
+    fn scroll_process_columns(&mut self, offset: i64) {
+        // Clamped to the longest truncated cell when the table is drawn
+        self.process_scroll =
+            (self.process_scroll as i64 + offset).clamp(0, u16::MAX as i64) as u16;
+    }
 
-    let bar = network_block(system_data, bottom_chunks[1]);
-    f.render_widget(bar, bottom_chunks[1]);
+    fn open_process_popup(&mut self, process: Option<&ProcessData>) {
+        if let Some(process) = process {
+            self.process_details = procfs::ProcessDetails::load(process.pid);
+            self.popup = Some(Popup::ProcessDetail(process.pid));
+        }
+    }
 
-    let info_block = info_block();
-    f.render_widget(info_block, chunks[2]);
-}
+    fn toggle_per_core(&mut self) {
+        self.per_core = !self.per_core;
+    }
 
-fn ram_block<'a, B: Backend>(
-    f: &mut Frame<B>,
-    system_data: &'a SystemData,
-    area: Rect,
-) -> Chart<'a> {
-    let block = Block::default()
-        .title(" Memory Usage ")
-        .borders(Borders::ALL);
+    fn toggle_tree(&mut self) {
+        self.tree = !self.tree;
+        self.group_units = false;
+        self.select_first_process();
+    }
 
-    // create labels
-    let x_labels = vec![Span::styled(
-        "X AXIS",
-        Style::default().add_modifier(Modifier::BOLD),
-    )];
+    // Grouping replaces the tree, and sums per unit rather than per subtree
+    fn toggle_group_units(&mut self) {
+        self.group_units = !self.group_units;
+        if self.group_units {
+            self.tree = false;
+            self.aggregate = false;
+        }
+        self.select_first_process();
+    }
 
-    let datasets = vec![Dataset::default()
-        .marker(symbols::Marker::Dot)
-        .style(Style::default().fg(Color::Cyan))
-        .data(&system_data.memory_usage_as_tuple)];
+    // Starts from the collapsed tree so each service is one row, expandable for a breakdown
+    fn toggle_aggregate(&mut self, processes: &[&ProcessData]) {
+        self.aggregate = !self.aggregate;
+        if self.aggregate {
+            self.tree = true;
+            self.group_units = false;
+            self.collapse_all(processes);
+        }
+    }
 
-    let c: Chart<'a> = Chart::new(datasets)
-        .x_axis(
-            Axis::default()
-                .style(Style::default().fg(Color::Gray))
-                .labels(x_labels)
-                .bounds([1.0, 501.0]),
-        )
-        .y_axis(
-            Axis::default()
-                .style(Style::default().fg(Color::Gray))
-                .labels(vec![
-                    Span::styled("0GB", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::styled(
-                        memory_to_gb(&system_data.total_memory),
-                        Style::default().add_modifier(Modifier::BOLD),
-                    ),
-                ])
-                .bounds([0.0, 100.0]),
-        )
-        .block(block);
+    fn set_collapsed(&mut self, process: Option<&ProcessData>, collapsed: bool) {
+        if let Some(process) = process {
+            if collapsed {
+                self.collapsed.insert(process.pid);
+            } else {
+                self.collapsed.remove(&process.pid);
+            }
+        }
+    }
 
-    // add text inside area
+    // Leaves only the top of the tree, e.g. init and kthreadd
+    fn collapse_all(&mut self, processes: &[&ProcessData]) {
+        self.collapsed = tree::top_level_parents(processes).into_iter().collect();
+        self.select_first_process();
+    }
 
-    let percentage_used = format!(
-        "{:.2}% Used",
-        (system_data.system.used_memory() as f64) / system_data.total_memory * 100.0
-    );
-    let temp_rect = Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2);
-    let widget = Paragraph::new(percentage_used).alignment(Alignment::Center);
-    f.render_widget(widget, temp_rect);
+    fn open_sysctl_popup(&mut self) {
+        self.sysctls = sysctl::read_interesting_sysctls();
+        self.sysctl_query.clear();
+        self.sysctl_state.select(Some(0));
+        self.popup = Some(Popup::Sysctl);
+    }
 
-    c
-}
+    fn open_sensor_popup(&mut self) {
+        self.sensors_read = None;
+        self.popup = Some(Popup::Sensors);
+    }
 
-fn cpu_block<'a, B: Backend>(
-    f: &mut Frame<B>,
-    system_data: &'a SystemData,
-    area: Rect,
-) -> Chart<'a> {
-    let block = Block::default().title(" CPU Usage ").borders(Borders::ALL);
+    fn refresh_sensors(&mut self) {
+        let interval = Duration::from_millis(self.refresh_interval.load(Ordering::Relaxed));
+        if self
+            .sensors_read
+            .is_some_and(|read| read.elapsed() < interval)
+        {
+            return;
+        }
+        self.sensors = sensors::apply_config(sensors::read_sensors(), &self.sensor_config);
+        self.sensors_read = Some(Instant::now());
+    }
 
-    // create labels
-    let x_labels = vec![Span::styled(
-        "X AXIS",
-        Style::default().add_modifier(Modifier::BOLD),
-    )];
+    fn filtered_sysctls(&self) -> Vec<&sysctl::Sysctl> {
+        self.sysctls
+            .iter()
+            .filter(|s| sysctl::matches_query(s, &self.sysctl_query))
+            .collect()
+    }
 
-    let datasets = system_data
-        .cpus
-        .iter()
-        .map(|item| {
-            Dataset::default()
-                .marker(symbols::Marker::Dot)
-                .graph_type(GraphType::Line)
-                .style(Style::default().fg(item.color))
-                .data(&item.data)
-        })
-        .collect();
+    fn open_user_popup(&mut self, processes: &[ProcessData]) {
+        let mut users = processes
+            .iter()
+            .map(|p| p.user.clone())
+            .filter(|user| !user.is_empty())
+            .collect::<Vec<String>>();
+        users.sort();
+        users.dedup();
+        self.users = users;
 
-    let c: Chart<'a> = Chart::new(datasets)
-        .x_axis(
-            Axis::default()
-                .style(Style::default().fg(Color::Gray))
-                .labels(x_labels)
-                .bounds([1.0, 501.0]),
-        )
-        .y_axis(
-            Axis::default()
-                .style(Style::default().fg(Color::Gray))
-                .labels(vec![
-                    Span::styled("0%", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::styled("100%", Style::default().add_modifier(Modifier::BOLD)),
-                ])
-                .bounds([0.0, 100.0]),
-        )
-        .block(block);
+        // Row 0 is "all users"
+        let selected = self
+            .user_filter
+            .as_ref()
+            .and_then(|filter| self.users.iter().position(|user| user == filter))
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        self.user_state.select(Some(selected));
+        self.popup = Some(Popup::Users);
+    }
 
-    // add text inside area
+    fn scroll_users(&mut self, offset: i64) {
+        let len = self.users.len() as i64 + 1;
+        let current = self.user_state.selected().unwrap_or(0) as i64;
+        let next = (current + offset).clamp(0, len - 1);
+        self.user_state.select(Some(next as usize));
+    }
 
-    let percentage_used = format!("{:.2}%", system_data.cpu_usage,);
-    let temp_rect = Rect::new(area.x + 1, area.y + 1, area.width - 2, area.height - 2);
-    let widget = Paragraph::new(percentage_used).alignment(Alignment::Center);
-    f.render_widget(widget, temp_rect);
+    fn choose_user(&mut self) {
+        let selected = self.user_state.selected().unwrap_or(0);
+        self.set_user_filter(
+            selected
+                .checked_sub(1)
+                .and_then(|i| self.users.get(i))
+                .cloned(),
+        );
+        self.popup = None;
+    }
 
-    c
-}
+    fn set_user_filter(&mut self, user: Option<String>) {
+        self.user_filter = user;
+        self.select_first_process();
+    }
 
-fn network_block<'a>(system_data: &'a SystemData, area: Rect) -> BarChart<'a> {
-    let block = Block::default()
-        .title(" Network Usage ")
-        .borders(Borders::ALL);
+    fn toggle_own_processes(&mut self, own_user: Option<String>) {
+        if self.user_filter.is_some() {
+            self.set_user_filter(None);
+        } else {
+            self.set_user_filter(own_user);
+        }
+    }
 
-    // max of 2 bars
-    let calc_bar_width = area.width / 2 - 3;
-    let max = {
-        let mut max = 0;
-        for (_, v) in system_data.packets.iter() {
-            if *v > max {
-                max = *v;
+    fn edit_filter(&mut self, edit: impl FnOnce(&mut String)) {
+        edit(&mut self.process_query);
+        match Filter::parse(&self.process_query) {
+            Ok(filter) => {
+                self.process_filter = filter;
+                self.filter_error = None;
             }
+            // Keep filtering with the last valid pattern while one is being typed
+            Err(err) => self.filter_error = Some(err.to_string()),
         }
-        max
-    };
+        self.select_first_process();
+    }
+
+    fn set_status(&mut self, message: String) {
+        self.status = Some((message, Instant::now()));
+    }
+
+    // True when an alert started firing since the last call
+    fn notice_alerts(&mut self, alerts: &alerts::Engine) -> bool {
+        if alerts.fired_count() <= self.alerts_seen {
+            return false;
+        }
+        self.alerts_seen = alerts.fired_count();
+        self.set_status(format!("Alert: {}", alerts.firing().join(", ")));
+        if self.flash_alerts {
+            let charts = alerts
+                .firing_metrics()
+                .into_iter()
+                .map(alert_chart)
+                .collect();
+            self.flash = Some((charts, Instant::now()));
+        }
+        true
+    }
+
+    // Alternates between red and the normal border until the flash runs out
+    fn border_style(&mut self, chart: ChartKind) -> Style {
+        let started = match &self.flash {
+            Some((charts, started)) if charts.contains(&chart) => *started,
+            _ => return Style::default(),
+        };
+        let elapsed = started.elapsed();
+        if elapsed >= FLASH_DURATION {
+            self.flash = None;
+            return Style::default();
+        }
+        if (elapsed.as_millis() / FLASH_BLINK.as_millis()).is_multiple_of(2) {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        }
+    }
+
+    fn run_command(&mut self, system_data: &SystemData) {
+        let input = self.command_input.take().unwrap_or_default();
+        match command::parse(&input) {
+            Ok(command::Command::KillAll(filter)) => {
+                let own_pid = sysinfo::get_current_pid().ok().map(|pid| pid.as_u32());
+                let processes = system_data
+                    .processes
+                    .iter()
+                    .filter(|p| Some(p.pid) != own_pid && filter.is_match(&p.name))
+                    .map(|p| (p.pid, p.name.clone()))
+                    .collect::<Vec<(u32, String)>>();
+                if processes.is_empty() {
+                    self.set_status(format!("No processes match `{}`", input.trim()));
+                    return;
+                }
+                self.pending = Some(PendingAction {
+                    action: Action::Terminate,
+                    processes,
+                    escalator: None,
+                });
+                self.popup = Some(Popup::Confirm);
+            }
+            Ok(command::Command::Renice(nice)) => {
+                self.confirm_for_targets(system_data, Action::Renice(nice))
+            }
+            Ok(command::Command::Ionice(priority)) => {
+                self.confirm_for_targets(system_data, Action::Ionice(priority))
+            }
+            Ok(command::Command::Pin(cpus)) => {
+                self.confirm_for_targets(system_data, Action::Pin(cpus))
+            }
+            Ok(command::Command::Power(name)) => {
+                let profile = match &system_data.power_profile {
+                    Some(profile) => profile,
+                    None => {
+                        self.set_status("No power profiles on this system".to_string());
+                        return;
+                    }
+                };
+                match profile.choice(&name) {
+                    Some(choice) => self.power_choice = Some(choice.to_string()),
+                    None => self.set_status(format!(
+                        "Unknown power profile `{}`, expected one of {}",
+                        name,
+                        profile.choices.join(", ")
+                    )),
+                }
+            }
+            Ok(command::Command::Maintenance(task)) => {
+                if maintenance::is_root() {
+                    self.pending_task = Some(task);
+                    self.popup = Some(Popup::Maintenance);
+                } else {
+                    self.set_status(format!("{} needs root", task.describe()));
+                }
+            }
+            Ok(command::Command::SaveProfile(name)) => {
+                match profile::save(&name, &self.profile()) {
+                    Ok(()) => self.set_status(format!("Saved profile `{}`", name)),
+                    Err(err) => self.set_status(format!("Profile: {}", err)),
+                }
+            }
+            Ok(command::Command::LoadProfile(name)) => match profile::load(&name) {
+                Ok(profile) => {
+                    self.apply_profile(profile);
+                    self.set_status(format!("Switched to profile `{}`", name));
+                }
+                Err(err) => self.set_status(format!("Profile: {}", err)),
+            },
+            Err(err) => self.set_status(err),
+        }
+    }
+
+    fn profile(&self) -> profile::Profile {
+        profile::Profile {
+            preset: self.preset,
+            per_core: self.per_core,
+            window: self.window,
+            columns: self.columns.clone(),
+            tree: self.tree,
+            aggregate: self.aggregate,
+            group_units: self.group_units,
+            sort: self.sort,
+            process_query: self.process_query.clone(),
+            user_filter: self.user_filter.clone(),
+            refresh_interval: self.refresh_interval.load(Ordering::Relaxed),
+            freeze_while_navigating: self.freeze_while_navigating,
+        }
+    }
+
+    // Sampling stays as it started, a preset's lighter sampling only comes
+    // with --preset
+    fn apply_profile(&mut self, profile: profile::Profile) {
+        self.preset = profile.preset;
+        self.per_core = profile.per_core;
+        self.window = profile.window;
+        self.scroll = 0.0;
+        if !profile.columns.is_empty() {
+            self.columns = profile.columns;
+        }
+        self.tree = profile.tree;
+        self.aggregate = profile.aggregate;
+        self.group_units = profile.group_units;
+        self.collapsed.clear();
+        self.sort = profile.sort;
+        self.user_filter = profile.user_filter;
+        self.edit_filter(|query| *query = profile.process_query);
+        self.refresh_interval
+            .store(profile.refresh_interval.max(1), Ordering::Relaxed);
+        self.freeze_while_navigating = profile.freeze_while_navigating;
+    }
+
+    fn session(&self, system: &System) -> session::Session {
+        let started = |pid: u32| start_time(system, pid).map(|time| (pid, time));
+        session::Session {
+            profile: self.profile(),
+            marked: self.marked.iter().filter_map(|pid| started(*pid)).collect(),
+            selected: self.selected_pid.and_then(started),
+            view: self.view,
+        }
+    }
+
+    // Processes that have since exited, or whose PID now belongs to another
+    // one, are left out
+    fn restore_session(&mut self, session: session::Session, system: &System) {
+        self.apply_profile(session.profile);
+        self.view = session.view;
+        let running = |(pid, time): &(u32, u64)| start_time(system, *pid) == Some(*time);
+        self.marked = session
+            .marked
+            .iter()
+            .filter(|process| running(process))
+            .map(|(pid, _)| *pid)
+            .collect();
+        if let Some((pid, _)) = session.selected.filter(running) {
+            self.selected_pid = Some(pid);
+        }
+    }
+
+    fn save_session(&mut self, system: &System) {
+        if let Err(err) = session::save(&self.session(system)) {
+            self.set_status(format!("Saving the session failed: {:#}", err));
+        }
+    }
+
+    fn toggle_mark(&mut self, process: Option<&ProcessData>, pids: &[u32]) {
+        if let Some(process) = process {
+            if !self.marked.remove(&process.pid) {
+                self.marked.insert(process.pid);
+            }
+            self.scroll_processes(1, pids);
+        }
+    }
+
+    // Batch actions apply to the marked processes, or the selected one when nothing is marked
+    fn confirm_for_targets(&mut self, system_data: &SystemData, action: Action) {
+        let processes = if self.marked.is_empty() {
+            selected_process(system_data, self)
+                .map(|p| vec![(p.pid, p.name.clone())])
+                .unwrap_or_default()
+        } else {
+            system_data
+                .processes
+                .iter()
+                .filter(|p| self.marked.contains(&p.pid))
+                .map(|p| (p.pid, p.name.clone()))
+                .collect()
+        };
+        if processes.is_empty() {
+            self.set_status("No process selected".to_string());
+            return;
+        }
+        self.pending = Some(PendingAction {
+            action,
+            processes,
+            escalator: None,
+        });
+        self.popup = Some(Popup::Confirm);
+    }
+
+    fn confirm_action(&mut self) {
+        self.popup = None;
+        let pending = match self.pending.take() {
+            Some(pending) => pending,
+            None => return,
+        };
+        let mut errors = Vec::new();
+        let mut denied = Vec::new();
+        for (pid, name) in &pending.processes {
+            if let Err(err) = pending.action.apply(*pid) {
+                if err.kind() == io::ErrorKind::PermissionDenied {
+                    denied.push((*pid, name.clone()));
+                }
+                errors.push(err);
+            }
+        }
+        self.marked.clear();
+        let mut status = format!(
+            "{} {} of {} processes",
+            pending.action.describe_done(),
+            pending.processes.len() - errors.len(),
+            pending.processes.len()
+        );
+        // Other users' processes are the usual reason, say so rather than EPERM
+        if errors
+            .iter()
+            .any(|err| err.kind() == io::ErrorKind::PermissionDenied)
+        {
+            status.push_str(", not permitted for other users' processes without root");
+        } else if let Some(err) = errors.first() {
+            status.push_str(&format!(", {}", err));
+        }
+        self.set_status(status);
+
+        // Rather than running the whole viewer as root, just this once
+        let escalator = control::escalator().filter(|_| pending.action.command(&[]).is_some());
+        if let (false, Some(escalator)) = (denied.is_empty(), escalator) {
+            self.pending = Some(PendingAction {
+                action: pending.action,
+                processes: denied,
+                escalator: Some(escalator),
+            });
+            self.popup = Some(Popup::Confirm);
+        }
+    }
+
+    fn run_task(&mut self) {
+        self.popup = None;
+        let task = match self.pending_task.take() {
+            Some(task) => task,
+            None => return,
+        };
+        match task.run() {
+            Ok(()) => self.set_status(format!("{}: done", task.describe())),
+            Err(err) => self.set_status(format!("{}: {}", task.describe(), err)),
+        }
+    }
+
+    // The inspected chart when inspecting, the process table otherwise
+    fn export_panel(&mut self, system_data: &SystemData) {
+        let (panel, csv) = match self.inspect.map(|inspect| inspect.chart) {
+            Some(ChartKind::Cpu) => {
+                let points = system_data
+                    .cpus
+                    .first()
+                    .map(|cpu| cpu.history.all_points())
+                    .unwrap_or_default();
+                ("cpu", export::history(&points, "cpu_percent"))
+            }
+            Some(ChartKind::Memory) => {
+                let points = system_data.memory_history.all_points();
+                ("memory", export::history(&points, "used_gib"))
+            }
+            None if self.view == AppView::Network => match system_data.tcp_states {
+                Some(states) => (
+                    "connections",
+                    export::connections(&states, &netstat::TCP_STATES),
+                ),
+                None => {
+                    self.set_status("Nothing to export, /proc/net/tcp is unavailable".to_string());
+                    return;
+                }
+            },
+            None => {
+                let processes = filtered_processes(system_data, self);
+                (
+                    "processes",
+                    export::processes(&processes, &columns::ALL_COLUMNS, self.units),
+                )
+            }
+        };
+        match export::write(panel, &csv) {
+            Ok(path) => self.set_status(format!("Exported {} to {}", panel, path.display())),
+            Err(err) => self.set_status(format!("Export failed: {}", err)),
+        }
+    }
+
+    fn open_settings_popup(&mut self) {
+        self.settings_state.select(Some(0));
+        self.popup = Some(Popup::Settings);
+    }
+
+    // Steps through All and then each interface by name
+    fn scroll_interfaces(&mut self, offset: i64, interfaces: &[netstat::Interface]) {
+        let current = self
+            .interface
+            .as_ref()
+            .and_then(|name| interfaces.iter().position(|i| &i.name == name))
+            .map_or(0, |index| index as i64 + 1);
+        let next = (current + offset).clamp(0, interfaces.len() as i64);
+        self.interface = match next {
+            0 => None,
+            index => Some(interfaces[index as usize - 1].name.clone()),
+        };
+    }
+
+    fn scroll_settings(&mut self, offset: i64) {
+        let current = self.settings_state.selected().unwrap_or(0) as i64;
+        let next = (current + offset).clamp(0, SETTINGS.len() as i64 - 1);
+        self.settings_state.select(Some(next as usize));
+    }
+
+    // Steps the selected setting back or forth, panels are shown or hidden either way
+    fn change_setting(&mut self, step: i64) {
+        let selected = self.settings_state.selected().unwrap_or(0);
+        match SETTINGS.get(selected) {
+            Some(Setting::Refresh) => self.change_refresh_interval(step),
+            Some(Setting::Units) => {
+                self.units = match self.units {
+                    Units::Binary => Units::Decimal,
+                    Units::Decimal => Units::Binary,
+                }
+            }
+            Some(Setting::Theme) => {
+                let index = THEMES.iter().position(|theme| *theme == self.theme);
+                let next = (index.unwrap_or(0) as i64 + step).rem_euclid(THEMES.len() as i64);
+                self.theme = THEMES[next as usize];
+            }
+            Some(Setting::Panel(panel)) => {
+                if !self.panels.contains(panel) {
+                    // Kept in the usual order whatever order they're turned on in
+                    self.panels.push(*panel);
+                    self.panels
+                        .sort_by_key(|p| PANELS.iter().position(|q| q == p));
+                } else if self.panels.len() > 1 {
+                    self.panels.retain(|p| p != panel);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn save_settings(&mut self) {
+        let path = match &self.config_path {
+            Some(path) => path.clone(),
+            None => {
+                self.set_status("No config file to save to, HOME isn't set".to_string());
+                return;
+            }
+        };
+        let interface = config::InterfaceConfig {
+            theme: self.theme,
+            units: self.units,
+            refresh_ms: self.refresh_interval.load(Ordering::Relaxed),
+            panels: self.panels.clone(),
+            history_minutes: None,
+        };
+        match config::save_interface(&path, &interface) {
+            Ok(()) => self.set_status(format!("Saved settings to {}", path.display())),
+            Err(err) => self.set_status(format!("Saving settings failed: {:#}", err)),
+        }
+    }
+
+    fn open_column_popup(&mut self) {
+        self.column_state.select(Some(0));
+        self.popup = Some(Popup::Columns);
+    }
+
+    // Shown columns in their order, followed by the hidden ones
+    fn column_choices(&self) -> Vec<(Column, bool)> {
+        let hidden = columns::ALL_COLUMNS
+            .iter()
+            .filter(|column| !self.columns.contains(column))
+            .map(|column| (*column, false));
+        self.columns
+            .iter()
+            .map(|column| (*column, true))
+            .chain(hidden)
+            .collect()
+    }
+
+    fn scroll_columns(&mut self, offset: i64) {
+        let len = columns::ALL_COLUMNS.len() as i64;
+        let current = self.column_state.selected().unwrap_or(0) as i64;
+        let next = (current + offset).clamp(0, len - 1);
+        self.column_state.select(Some(next as usize));
+    }
+
+    fn toggle_column(&mut self) {
+        let selected = self.column_state.selected().unwrap_or(0);
+        let (column, shown) = match self.column_choices().get(selected) {
+            Some(choice) => *choice,
+            None => return,
+        };
+        if shown {
+            // The table needs at least one column
+            if self.columns.len() > 1 {
+                self.columns.retain(|c| *c != column);
+            }
+        } else {
+            self.columns.push(column);
+        }
+        let index = self.column_choices().iter().position(|(c, _)| *c == column);
+        self.column_state.select(index);
+    }
+
+    fn move_column(&mut self, offset: i64) {
+        let selected = self.column_state.selected().unwrap_or(0);
+        if selected >= self.columns.len() {
+            return;
+        }
+        let target = (selected as i64 + offset).clamp(0, self.columns.len() as i64 - 1) as usize;
+        self.columns.swap(selected, target);
+        self.column_state.select(Some(target));
+    }
+
+    fn scroll_sysctls(&mut self, offset: i64) {
+        let len = self.filtered_sysctls().len() as i64;
+        if len == 0 {
+            self.sysctl_state.select(None);
+            return;
+        }
+        let current = self.sysctl_state.selected().unwrap_or(0) as i64;
+        let next = (current + offset).clamp(0, len - 1);
+        self.sysctl_state.select(Some(next as usize));
+    }
+}
+
+// Uptime below this is treated as a recent, possibly unexpected, reboot
+const RECENT_BOOT_THRESHOLD: u64 = 10 * 60;
+
+const STEAL_WARNING_THRESHOLD: f64 = 10.0;
+
+// Either is treated as the kernel getting close to OOM killing something
+const MEMORY_PRESSURE_THRESHOLD: f64 = 10.0;
+const MEMORY_USED_THRESHOLD: f64 = 0.9;
+
+// Share of the conntrack table in use before warning, new connections are
+// dropped once it's full
+const CONNTRACK_THRESHOLD: f64 = 0.9;
+
+// Share of a filesystem in use before its gauge turns red
+const FILESYSTEM_THRESHOLD: f64 = 0.9;
+
+// Share of PIDs in use before warning, for whichever of pid_max,
+// threads-max and the cgroup's pids.max is closest. fork() fails once any
+// of them runs out.
+const PID_THRESHOLD: f64 = 0.9;
+
+// How long a status message replaces the usage title
+const STATUS_DURATION: Duration = Duration::from_secs(5);
+
+// Characters moved per left/right press in the process table
+const PROCESS_SCROLL_STEP: i64 = 4;
+
+// Sampling intervals in milliseconds, stepped through with < and >
+const REFRESH_INTERVALS: [u64; 7] = [100, 250, 500, 1000, 2000, 5000, 10000];
+
+// Gap between the two samples taken by --once
+const SNAPSHOT_INTERVAL: Duration = Duration::from_millis(500);
+
+// How often the interface's state is written with --resume
+const SESSION_INTERVAL: Duration = Duration::from_secs(5);
+
+// How long the render loop gets to wind down after SIGTERM or SIGHUP
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
+
+// How long the table stays frozen after the last navigation key
+const NAVIGATION_IDLE: Duration = Duration::from_millis(1500);
+
+// A chart's border blinks this long when an alert on it fires
+const FLASH_DURATION: Duration = Duration::from_secs(3);
+const FLASH_BLINK: Duration = Duration::from_millis(250);
+
+// Shortest interval between reads of the storage panel
+const STORAGE_REFRESH: Duration = Duration::from_secs(5);
+
+// xterm's window title stack, ignored by terminals without one
+const PUSH_TITLE: &str = "\x1b[22;0t";
+const POP_TITLE: &str = "\x1b[23;0t";
+
+// In the order of SystemData::pressure_history
+const PRESSURE_RESOURCES: [&str; 3] = ["cpu", "memory", "io"];
+
+// Each CPU line gets its own color so the legend can tell them apart
+const CPU_COLORS: [Color; 12] = [
+    Color::Green,
+    Color::Cyan,
+    Color::Yellow,
+    Color::Magenta,
+    Color::Blue,
+    Color::Red,
+    Color::LightGreen,
+    Color::LightCyan,
+    Color::LightYellow,
+    Color::LightMagenta,
+    Color::LightBlue,
+    Color::LightRed,
+];
+
+#[derive(Debug, Clone)]
+struct ProcessData {
+    pid: u32,
+    name: String,
+    cpu_usage: f32,
+    cpu_average: f32,
+    // Seconds of CPU used since the process started
+    cpu_time: Option<f64>,
+    parent: Option<u32>,
+    user: String,
+    state: String,
+    nice: Option<i64>,
+    threads: Option<u64>,
+    memory: u64,
+    swap: Option<u64>,
+    oom: Option<procfs::OomScore>,
+    cgroup: Option<String>,
+    // Docker or Podman container name
+    container: Option<String>,
+    // Bytes per second since the previous refresh
+    read_rate: f64,
+    write_rate: f64,
+    command: String,
+}
+
+impl ProcessData {
+    // Systemd unit the process runs under, empty when /proc/<pid>/cgroup is unreadable
+    fn unit(&self) -> String {
+        self.cgroup
+            .as_deref()
+            .map(procfs::unit_name)
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug)]
+struct CPUData {
+    name: String,
+    history: History,
+    color: Color,
+}
+
+impl SystemData<'_> {
+    fn new(config: &Config) -> Self {
+        SystemData {
+            system: System::new_all(),
+            total_memory: 0.0,
+            memory_history: History::default(),
+            tmpfs_history: History::default(),
+            tmpfs_used: 0.0,
+            cpus: Vec::new(),
+            packets: [("Packets In", 0), ("Packets Out", 0)],
+            network_history: [History::default(), History::default()],
+            interfaces: Vec::new(),
+            processes: Vec::new(),
+            cpu_usage: 0.0,
+            boot_time: 0,
+            uptime: 0,
+            virtualization: host::detect_virtualization(),
+            cpu_times: host::CpuTimes::default(),
+            steal_usage: 0.0,
+            used_memory: 0.0,
+            container: host::detect_container(),
+            cgroup_limits: host::read_cgroup_limits(),
+            cgroup_cpu_sample: None,
+            last_refresh: None,
+            cpu_samples: HashMap::new(),
+            cpu_average_window: Duration::from_secs(config.processes.cpu_average_seconds),
+            memory_pressure: None,
+            entropy: None,
+            random_ready: true,
+            clock: None,
+            cpu_sensors: Vec::new(),
+            frequency_history: History::default(),
+            package_temperature_history: History::default(),
+            container_names: containers::Resolver::default(),
+            process_extras: HashMap::new(),
+            freeze_processes: Arc::new(AtomicBool::new(false)),
+            alerts: alerts::Engine::new(config.alerts.clone()),
+            tcp_states: None,
+            conntrack: None,
+            tasks: None,
+            task_history: [History::default(), History::default()],
+            forks: None,
+            fork_history: History::default(),
+            tcp_segments: None,
+            retransmit_rate: None,
+            retransmit_share: None,
+            retransmit_history: History::default(),
+            socket_history: Default::default(),
+            dns: config.dns.name.clone().map(dns::DnsHealth::new),
+            failed_units: None,
+            disks: Vec::new(),
+            captures: Vec::new(),
+            anomalies: Vec::new(),
+            power_profile: None,
+            backlight: None,
+            inhibitors: None,
+            filesystems: Vec::new(),
+            full_within_days: config.storage.full_within_days,
+            disk_latency: Vec::new(),
+            disk_io_history: [History::default(), History::default()],
+            gpu: gpu::Gpu::default(),
+            lightweight: false,
+            headless: false,
+            load_history: Default::default(),
+            pressure_history: Default::default(),
+        }
+    }
+
+    // For callers that own the state outright, the UI reads the extras before
+    // taking the lock instead
+    fn sample(&mut self, extras: &mut procfs::ExtrasCache) {
+        if !self.lightweight {
+            self.process_extras = extras.read();
+        }
+        self.update_system_info();
+    }
+
+    fn update_system_info(&mut self) {
+        if self.lightweight {
+            self.system.refresh_cpu();
+            self.system.refresh_memory();
+        } else {
+            self.system.refresh_all();
+        }
+        // Not part of refresh_all, which only updates usage
+        self.system
+            .refresh_cpu_specifics(CpuRefreshKind::new().with_frequency());
+        let refreshed = Instant::now();
+        let elapsed = self
+            .last_refresh
+            .map(|last| refreshed.duration_since(last).as_secs_f64())
+            .unwrap_or(0.0);
+        self.last_refresh = Some(refreshed);
+
+        // Boot
+        self.boot_time = self.system.boot_time();
+        self.uptime = self.system.uptime();
+
+        // All charts share one timeline
+        let now = Local::now().timestamp_millis() as f64 / 1000.0;
+
+        // CPU frequency and temperature, charted against usage to spot throttling
+        let frequencies = self
+            .system
+            .cpus()
+            .iter()
+            .map(|cpu| cpu.frequency())
+            .filter(|mhz| *mhz > 0)
+            .collect::<Vec<u64>>();
+        if !frequencies.is_empty() {
+            let average = frequencies.iter().sum::<u64>() as f64 / frequencies.len() as f64;
+            self.frequency_history.push(now, average);
+        }
+        self.cpu_sensors = sensors::read_cpu_sensors();
+        if let Some(celsius) = sensors::package_temperature(&self.cpu_sensors) {
+            self.package_temperature_history.push(now, celsius);
+        }
+
+        // Memory
+        let (used_memory, total_memory) = self.memory_totals();
+        self.used_memory = used_memory as f64;
+        self.memory_history.push(now, used_memory as f64 / GIB);
+        self.total_memory = total_memory as f64;
+        self.memory_pressure = host::read_pressure("memory");
+        self.entropy = host::read_entropy();
+        self.random_ready = host::random_ready();
+        self.clock = host::read_clock();
+        if !self.headless {
+            self.backlight = power::read_backlight();
+        }
+
+        // CPU, the total first and then every core
+        let container_cpu_usage = self.container_cpu_usage();
+        let total_usage =
+            container_cpu_usage.unwrap_or(self.system.global_cpu_info().cpu_usage() as f64);
+        let usages = std::iter::once(("Total".to_string(), total_usage))
+            .chain(
+                self.system
+                    .cpus()
+                    .iter()
+                    .map(|cpu| (cpu.name().to_string(), cpu.cpu_usage() as f64)),
+            )
+            .collect::<Vec<(String, f64)>>();
+        for (name, usage) in usages {
+            let index = match self.cpus.iter().position(|cpu| cpu.name == name) {
+                Some(index) => index,
+                None => {
+                    self.cpus.push(CPUData {
+                        name,
+                        history: History::default(),
+                        color: CPU_COLORS[self.cpus.len() % CPU_COLORS.len()],
+                    });
+                    self.cpus.len() - 1
+                }
+            };
+            self.cpus[index].history.push(now, usage);
+        }
+        self.cpu_usage = total_usage;
+
+        // Steal time is only meaningful on guests, but cheap enough to always track
+        if let Some(cpu_times) = host::read_cpu_times() {
+            let total = cpu_times.total.saturating_sub(self.cpu_times.total);
+            let steal = cpu_times.steal.saturating_sub(self.cpu_times.steal);
+            if total > 0 {
+                self.steal_usage = steal as f64 / total as f64 * 100.0;
+            }
+            self.cpu_times = cpu_times;
+        }
+
+        // Everything below goes through every process or is only shown by the
+        // full layout
+        if self.lightweight {
+            if gpu::present() || gpu::has_nvidia() {
+                self.gpu.record_cards(now);
+            }
+            self.evaluate_alerts(now);
+            return;
+        }
+
+        // Network
+        let all_networks = self.system.networks();
+
+        let (recieved_packets, transmitted_packets) = all_networks
+            .into_iter()
+            .map(|(_, net)| (net.packets_received(), net.packets_transmitted()))
+            .reduce(|(a, b), (c, d)| (a + c, b + d))
+            .unwrap_or((0, 0));
+
+        self.packets = [
+            ("Packets In", recieved_packets),
+            ("Packets Out", transmitted_packets),
+        ];
+
+        // Counted since the previous refresh, interfaces that went away are dropped
+        let mut interfaces = Vec::new();
+        for (name, net) in all_networks {
+            let mut interface = match self.interfaces.iter().position(|i| i.name == *name) {
+                Some(index) => self.interfaces.swap_remove(index),
+                None => netstat::Interface::new(name.clone()),
+            };
+            if elapsed > 0.0 {
+                interface
+                    .received
+                    .push(now, net.received() as f64 / elapsed);
+                interface
+                    .transmitted
+                    .push(now, net.transmitted() as f64 / elapsed);
+            }
+            interface.errors = (
+                net.total_errors_on_received(),
+                net.total_errors_on_transmitted(),
+            );
+            interface.drops = netstat::read_drops(name).unwrap_or((0, 0));
+            interfaces.push(interface);
+        }
+        interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+        self.interfaces = interfaces;
+        if elapsed > 0.0 {
+            let rates = [
+                self.interfaces
+                    .iter()
+                    .filter_map(|i| i.received.latest())
+                    .sum(),
+                self.interfaces
+                    .iter()
+                    .filter_map(|i| i.transmitted.latest())
+                    .sum(),
+            ];
+            for (history, rate) in self.network_history.iter_mut().zip(rates) {
+                history.push(now, rate);
+            }
+        }
+
+        // Filesystems, keyed by mount point since disks come and go
+        let mut mounts = self
+            .system
+            .disks()
+            .iter()
+            .map(|disk| {
+                (
+                    disk.mount_point().to_string_lossy().into_owned(),
+                    String::from_utf8_lossy(disk.file_system()).into_owned(),
+                    disk.total_space(),
+                    disk.available_space(),
+                )
+            })
+            .collect::<Vec<(String, String, u64, u64)>>();
+        let tmpfs = storage::read_tmpfs();
+        self.tmpfs_used = tmpfs
+            .iter()
+            .map(|tmpfs| tmpfs.total.saturating_sub(tmpfs.available))
+            .sum::<u64>() as f64;
+        self.tmpfs_history.push(now, self.tmpfs_used / GIB);
+        for tmpfs in tmpfs {
+            if !mounts.iter().any(|(mount, ..)| *mount == tmpfs.mount) {
+                mounts.push((
+                    tmpfs.mount,
+                    "tmpfs".to_string(),
+                    tmpfs.total,
+                    tmpfs.available,
+                ));
+            }
+        }
+        let mut filesystems = Vec::new();
+        for (mount, kind, total, available) in mounts {
+            let mut filesystem = match self.filesystems.iter().position(|f| f.mount == mount) {
+                Some(index) => self.filesystems.swap_remove(index),
+                None => storage::Filesystem::new(mount, kind),
+            };
+            filesystem.record(now, total, available);
+            filesystems.push(filesystem);
+        }
+        self.filesystems = filesystems;
+
+        let mut disk_latency = Vec::new();
+        for (name, counters) in storage::read_io_counters() {
+            match self.disk_latency.iter().position(|disk| disk.name == name) {
+                Some(index) => {
+                    let mut disk = self.disk_latency.swap_remove(index);
+                    disk.record(now, elapsed, counters);
+                    disk_latency.push(disk);
+                }
+                None => disk_latency.push(storage::DiskLatency::new(name, counters)),
+            }
+        }
+        self.disk_latency = disk_latency;
+        let rates = [
+            self.disk_latency.iter().map(|disk| disk.read_rate).sum(),
+            self.disk_latency.iter().map(|disk| disk.write_rate).sum(),
+        ];
+        for (history, rate) in self.disk_io_history.iter_mut().zip(rates) {
+            history.push(now, rate);
+        }
+
+        // Load and pressure, the first things to look at on a struggling server
+        let load = self.system.load_average();
+        for (history, value) in
+            self.load_history
+                .iter_mut()
+                .zip([load.one, load.five, load.fifteen])
+        {
+            history.push(now, value);
+        }
+        for (history, resource) in self.pressure_history.iter_mut().zip(PRESSURE_RESOURCES) {
+            if let Some(pressure) = host::read_pressure(resource) {
+                history.push(now, pressure);
+            }
+        }
+
+        self.tcp_states = netstat::read_tcp_states();
+        self.conntrack = netstat::read_conntrack();
+
+        // Tasks, fork storms and PID exhaustion give no other warning
+        self.tasks = host::read_task_counts();
+        if let Some(tasks) = self.tasks {
+            self.task_history[0].push(now, tasks.processes as f64);
+            self.task_history[1].push(now, tasks.threads as f64);
+        }
+        let forks = host::read_forks();
+        if let (Some(previous), Some(current)) = (self.forks, forks) {
+            if elapsed > 0.0 {
+                let rate = current.saturating_sub(previous) as f64 / elapsed;
+                self.fork_history.push(now, rate);
+            }
+        }
+        self.forks = forks;
+
+        // Retransmits are what tells a flaky network apart from a slow server
+        let segments = netstat::read_tcp_segments();
+        if let (Some(previous), Some(current)) = (self.tcp_segments, segments) {
+            if elapsed > 0.0 {
+                let retransmitted = current.retransmitted.saturating_sub(previous.retransmitted);
+                let sent = current.sent.saturating_sub(previous.sent);
+                let rate = retransmitted as f64 / elapsed;
+                self.retransmit_rate = Some(rate);
+                self.retransmit_share =
+                    (sent > 0).then(|| retransmitted as f64 / sent as f64 * 100.0);
+                self.retransmit_history.push(now, rate);
+            }
+        }
+        self.tcp_segments = segments;
+        if let Some(states) = self.tcp_states {
+            for (history, state) in self.socket_history.iter_mut().zip(netstat::CHARTED_STATES) {
+                history.push(now, states[state] as f64);
+            }
+        }
+
+        // The clients get a task of their own, see run_app
+        if !self.headless && (gpu::present() || gpu::has_nvidia()) {
+            self.gpu.record_cards(now);
+        }
+
+        // Processes
+        let num_cpus = self.system.cpus().len() as f32;
+        let all_processes = self.system.processes();
+        let mut sorted_processes = all_processes
+            .values()
+            .map(|p| {
+                let disk_usage = p.disk_usage();
+                let rate = |bytes: u64| {
+                    if elapsed > 0.0 {
+                        bytes as f64 / elapsed
+                    } else {
+                        0.0
+                    }
+                };
+                let user = p.user_id().map(|uid| {
+                    self.system
+                        .get_user_by_id(uid)
+                        .map(|user| user.name().to_owned())
+                        .unwrap_or_else(|| (**uid).to_string())
+                });
+                ProcessData {
+                    pid: p.pid().as_u32(),
+                    name: p.name().to_owned(),
+                    cpu_usage: p.cpu_usage() / num_cpus,
+                    cpu_average: 0.0,
+                    cpu_time: None,
+                    parent: p.parent().map(|parent| parent.as_u32()),
+                    user: user.unwrap_or_default(),
+                    state: p.status().to_string(),
+                    nice: None,
+                    threads: None,
+                    memory: p.memory(),
+                    swap: None,
+                    oom: None,
+                    cgroup: None,
+                    container: None,
+                    read_rate: rate(disk_usage.read_bytes),
+                    write_rate: rate(disk_usage.written_bytes),
+                    command: p.cmd().join(" "),
+                }
+            })
+            .collect::<Vec<ProcessData>>();
+
+        sorted_processes.sort_by(|a, b| b.cpu_usage.partial_cmp(&a.cpu_usage).unwrap());
+
+        // sysinfo doesn't expose these
+        let mut extras = std::mem::take(&mut self.process_extras);
+        for process in sorted_processes.iter_mut() {
+            let extras = extras.remove(&process.pid).unwrap_or_default();
+            if let Some(stat) = extras.stat {
+                process.nice = Some(stat.nice);
+                process.threads = Some(stat.threads);
+                process.cpu_time = Some(stat.cpu_time);
+            }
+            process.swap = extras.swap;
+            process.oom = extras.oom;
+            process.cgroup = extras.cgroup;
+            process.container = process
+                .cgroup
+                .as_deref()
+                .and_then(|cgroup| self.container_names.name(cgroup));
+
+            let samples = self.cpu_samples.entry(process.pid).or_default();
+            samples.push_back((refreshed, process.cpu_usage));
+            while let Some((time, _)) = samples.front() {
+                if refreshed.duration_since(*time) <= self.cpu_average_window {
+                    break;
+                }
+                samples.pop_front();
+            }
+            process.cpu_average =
+                samples.iter().map(|(_, usage)| usage).sum::<f32>() / samples.len() as f32;
+        }
+        // Forget processes that have exited, pids get reused
+        let pids = sorted_processes
+            .iter()
+            .map(|p| p.pid)
+            .collect::<HashSet<u32>>();
+        self.cpu_samples.retain(|pid, _| pids.contains(pid));
+        if !self.freeze_processes.load(Ordering::Relaxed) {
+            self.processes = sorted_processes;
+        }
+
+        self.evaluate_alerts(now);
+    }
+
+    // Once everything the rules can refer to is up to date
+    fn evaluate_alerts(&mut self, now: f64) {
+        let mut alerts = std::mem::take(&mut self.alerts);
+        alerts.evaluate(self, now);
+        self.alerts = alerts;
+    }
+
+    fn restore_history(&mut self, saved: persist::SavedHistory) {
+        if !saved.cpus.is_empty() {
+            self.cpus = saved
+                .cpus
+                .into_iter()
+                .enumerate()
+                .map(|(index, (name, history))| CPUData {
+                    name,
+                    history,
+                    color: CPU_COLORS[index % CPU_COLORS.len()],
+                })
+                .collect();
+        }
+        persist::adopt([
+            (&mut self.memory_history, saved.memory),
+            (&mut self.tmpfs_history, saved.tmpfs),
+            (&mut self.frequency_history, saved.frequency),
+            (
+                &mut self.package_temperature_history,
+                saved.package_temperature,
+            ),
+            (&mut self.fork_history, saved.forks),
+            (&mut self.retransmit_history, saved.retransmits),
+            (&mut self.gpu.vram_history, saved.gpu_vram),
+            (&mut self.gpu.power_history, saved.gpu_power),
+            (&mut self.gpu.temperature_history, saved.gpu_temperature),
+            (&mut self.gpu.busy_history, saved.gpu_busy),
+        ]);
+        persist::adopt(self.network_history.iter_mut().zip(saved.network));
+        persist::adopt(self.disk_io_history.iter_mut().zip(saved.disk_io));
+        persist::adopt(self.load_history.iter_mut().zip(saved.load));
+        persist::adopt(self.pressure_history.iter_mut().zip(saved.pressure));
+        persist::adopt(self.task_history.iter_mut().zip(saved.tasks));
+        persist::adopt(self.socket_history.iter_mut().zip(saved.sockets));
+        if !saved.interfaces.is_empty() {
+            self.interfaces = saved
+                .interfaces
+                .into_iter()
+                .map(|(name, [received, transmitted])| {
+                    let mut interface = netstat::Interface::new(name);
+                    interface.received = received;
+                    interface.transmitted = transmitted;
+                    interface
+                })
+                .collect();
+        }
+        // Counted from now, disks that have gone since are dropped
+        if !saved.disk_latency.is_empty() {
+            let counters = storage::read_io_counters();
+            self.disk_latency = saved
+                .disk_latency
+                .into_iter()
+                .filter_map(|(name, [read, write])| {
+                    let (_, counters) = counters.iter().find(|(disk, _)| *disk == name)?;
+                    let mut disk = storage::DiskLatency::new(name, *counters);
+                    disk.read = read;
+                    disk.write = write;
+                    Some(disk)
+                })
+                .collect();
+        }
+        if !saved.gpu_engines.is_empty() {
+            self.gpu.engines = saved
+                .gpu_engines
+                .into_iter()
+                .map(|(card, engine, history)| ((card, engine), history))
+                .collect();
+        }
+        // Only while the same name is being resolved
+        if let (Some(dns), Some((name, [latency, failed]))) = (self.dns.as_mut(), saved.dns) {
+            if dns.name == name {
+                dns.latency = latency;
+                dns.failed = failed;
+            }
+        }
+    }
+
+    fn saved_history(&self) -> persist::SavedHistory {
+        persist::SavedHistory {
+            memory: self.memory_history.clone(),
+            cpus: self
+                .cpus
+                .iter()
+                .map(|cpu| (cpu.name.clone(), cpu.history.clone()))
+                .collect(),
+            tmpfs: self.tmpfs_history.clone(),
+            frequency: self.frequency_history.clone(),
+            package_temperature: self.package_temperature_history.clone(),
+            network: self.network_history.clone(),
+            interfaces: self
+                .interfaces
+                .iter()
+                .map(|interface| {
+                    let histories = [interface.received.clone(), interface.transmitted.clone()];
+                    (interface.name.clone(), histories)
+                })
+                .collect(),
+            disk_io: self.disk_io_history.clone(),
+            disk_latency: self
+                .disk_latency
+                .iter()
+                .map(|disk| (disk.name.clone(), [disk.read.clone(), disk.write.clone()]))
+                .collect(),
+            load: self.load_history.clone(),
+            pressure: self.pressure_history.clone(),
+            tasks: self.task_history.clone(),
+            forks: self.fork_history.clone(),
+            retransmits: self.retransmit_history.clone(),
+            sockets: self.socket_history.clone(),
+            gpu_engines: self
+                .gpu
+                .engines
+                .iter()
+                .map(|((card, engine), history)| (card.clone(), engine.clone(), history.clone()))
+                .collect(),
+            gpu_vram: self.gpu.vram_history.clone(),
+            gpu_power: self.gpu.power_history.clone(),
+            gpu_temperature: self.gpu.temperature_history.clone(),
+            gpu_busy: self.gpu.busy_history.clone(),
+            dns: self.dns.as_ref().map(|dns| {
+                let histories = [dns.latency.clone(), dns.failed.clone()];
+                (dns.name.clone(), histories)
+            }),
+        }
+    }
+
+    // Inside a container the cgroup limit is the real ceiling, not the host's RAM
+    fn memory_totals(&self) -> (u64, u64) {
+        let host_totals = (self.system.used_memory(), self.system.total_memory());
+        if self.container.is_none() {
+            return host_totals;
+        }
+        match (
+            self.cgroup_limits.memory_limit,
+            host::read_cgroup_memory_usage(),
+        ) {
+            (Some(limit), Some(used)) => (used, limit.min(host_totals.1)),
+            _ => host_totals,
+        }
+    }
+
+    // Usage as a percentage of the container's CPU quota, None outside containers
+    fn container_cpu_usage(&mut self) -> Option<f64> {
+        self.container.as_ref()?;
+        let cores = self.cgroup_limits.cpu_limit?;
+        let usage = host::read_cgroup_cpu_usage()?;
+        let now = Instant::now();
+
+        let percentage = self.cgroup_cpu_sample.map(|(last_usage, last_time)| {
+            let elapsed = now.duration_since(last_time).as_micros() as f64;
+            let used = usage.saturating_sub(last_usage) as f64;
+            (used / (elapsed * cores) * 100.0).min(100.0)
+        });
+        self.cgroup_cpu_sample = Some((usage, now));
+        percentage
+    }
+}
+
+impl alerts::Source for SystemData<'_> {
+    fn current(&self, metric: alerts::Metric) -> Option<f64> {
+        let percent = |used: f64, total: f64| (total > 0.0).then(|| used / total * 100.0);
+        let load = self.system.load_average();
+        match metric {
+            alerts::Metric::Cpu => Some(self.cpu_usage),
+            alerts::Metric::Memory => percent(self.used_memory, self.total_memory),
+            alerts::Metric::Swap => percent(
+                self.system.used_swap() as f64,
+                self.system.total_swap() as f64,
+            ),
+            alerts::Metric::Steal => Some(self.steal_usage),
+            alerts::Metric::Pressure => self.memory_pressure,
+            alerts::Metric::Retransmits => self.retransmit_rate,
+            alerts::Metric::Conntrack => {
+                let (count, max) = self.conntrack?;
+                percent(count as f64, max as f64)
+            }
+            alerts::Metric::Pids => {
+                let (_, used, max) = self.tasks?.tightest()?;
+                percent(used as f64, max as f64)
+            }
+            alerts::Metric::Load1 => Some(load.one),
+            alerts::Metric::Load5 => Some(load.five),
+            alerts::Metric::Load15 => Some(load.fifteen),
+            alerts::Metric::Cpus => Some(self.system.cpus().len() as f64),
+        }
+    }
+
+    // Averages over whatever history there is, so a 5m rule already applies
+    // a minute after starting
+    fn window(
+        &self,
+        metric: alerts::Metric,
+        aggregate: alerts::Aggregate,
+        seconds: f64,
+    ) -> Option<f64> {
+        match metric {
+            alerts::Metric::Cpu => {
+                let history = &self.cpus.first()?.history;
+                aggregate.apply(history.recent(seconds).iter().map(|(_, value)| *value))
+            }
+            // The history is kept in GiB
+            alerts::Metric::Memory => {
+                let total = self.total_memory / GIB;
+                if total <= 0.0 {
+                    return None;
+                }
+                let points = self.memory_history.recent(seconds);
+                aggregate.apply(points.iter().map(|(_, value)| value / total * 100.0))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn window_title(system_data: &SystemData) -> String {
+    let memory = if system_data.total_memory > 0.0 {
+        system_data.used_memory / system_data.total_memory * 100.0
+    } else {
+        0.0
+    };
+    format!("CPU {:.0}% MEM {:.0}%", system_data.cpu_usage, memory)
+}
+
+// Memory histories are kept in GiB whatever the units, so saved history
+// reads the same either way. Labels convert from bytes with memory_to_gb.
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+fn memory_to_gb(bytes: &f64, units: Units) -> String {
+    let (gigabyte, name) = units.gigabyte();
+    format!("{:.2} {}", bytes / gigabyte, name)
+}
+
+fn format_uptime(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let hours = (seconds % 86400) / 3600;
+    let minutes = (seconds % 3600) / 60;
+    if days > 0 {
+        format!("{}d {:02}h {:02}m", days, hours, minutes)
+    } else {
+        format!("{:02}h {:02}m", hours, minutes)
+    }
+}
+
+// Capabilities are per thread, so the drop happens before the runtime starts
+// its workers, which then inherit what's kept. The socket systemd passed is
+// taken then too, as that changes the environment.
+fn main() -> Result<()> {
+    let args = Args::parse();
+    if args.drop_privileges {
+        privilege::drop_privileges()?;
+    }
+    let activated = if args.daemon {
+        systemd::listener()
+    } else {
+        None
+    };
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(run(args, activated))
+}
+
+async fn run(args: Args, activated: Option<std::os::unix::net::UnixListener>) -> Result<()> {
+    // Only asked when there's someone at a terminal to answer
+    let interactive = io::stdin().is_terminal() && io::stdout().is_terminal();
+    if interactive && !args.once && !args.stream && !args.plain && !args.daemon {
+        let path = args.config.clone().or_else(config::default_path);
+        if let Some(path) = path.filter(|path| !path.exists()) {
+            setup::run(&path)?;
+        }
+    }
+    let mut config = config::load(args.config)?;
+    // Flags win over the file
+    if let Some(column) = args.sort {
+        config.processes.sort = Some(column);
+    }
+    if let Some(theme) = args.theme {
+        config.interface.theme = theme;
+    }
+    if let Some(minutes) = args.history_length.or(config.interface.history_minutes) {
+        history::limit_retention(minutes.saturating_mul(60));
+    }
+    let history_file = if args.persist_history {
+        args.history_file.or_else(persist::default_path)
+    } else {
+        None
+    };
+    if args.daemon {
+        let interval = Duration::from_millis(args.interval.unwrap_or(1000));
+        return daemon::run(&config, interval, history_file, activated).await;
+    }
+    if args.stream {
+        let top = args.top.unwrap_or(10);
+        let interval = Duration::from_millis(args.interval.unwrap_or(1000));
+        return stream(&config, interval, |state| {
+            Ok(report::json_line(state, top)?)
+        })
+        .await;
+    }
+    if args.plain {
+        let top = args.top.unwrap_or(3);
+        let interval = Duration::from_millis(args.interval.unwrap_or(5000));
+        return stream(&config, interval, |state| {
+            Ok(report::sentence(state, top, config.interface.units))
+        })
+        .await;
+    }
+    // Redirected output gets the snapshot rather than escape codes and a
+    // process waiting for keys that will never come
+    if args.once || !io::stdout().is_terminal() {
+        print!("{}", snapshot(&config, args.top.unwrap_or(10)).await);
+        return Ok(());
+    }
+
+    // setup terminal
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mouse = !args.no_mouse;
+    if mouse {
+        execute!(stdout, EnableMouseCapture)?;
+    }
+    // Terminals that keep a stack of titles get the previous one back on exit
+    let set_title = config.terminal.title;
+    if set_title {
+        execute!(stdout, Print(PUSH_TITLE))?;
+    }
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    // run app
+    let _ = run_app(
+        &mut terminal,
+        config,
+        history_file,
+        args.preset,
+        args.interval,
+        mouse,
+        args.resume,
+    )
+    .await;
+
+    // restore terminal
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    if mouse {
+        execute!(terminal.backend_mut(), DisableMouseCapture)?;
+    }
+    if set_title {
+        execute!(terminal.backend_mut(), Print(POP_TITLE))?;
+    }
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+// CPU usage is a difference between two refreshes, so the snapshot waits for a second one
+async fn snapshot(config: &Config, top: usize) -> String {
+    let mut state = SystemData::new(config);
+    let mut extras = procfs::ExtrasCache::default();
+    state.sample(&mut extras);
+    tokio::time::sleep(SNAPSHOT_INTERVAL).await;
+    state.sample(&mut extras);
+    report::snapshot(
+        &state,
+        &config.processes.columns,
+        config.interface.units,
+        top,
+    ) + "\n"
+}
+
+// Prints a line per sample until interrupted, or until whatever reads stdout goes away
+async fn stream(
+    config: &Config,
+    interval: Duration,
+    format: impl Fn(&SystemData) -> Result<String>,
+) -> Result<()> {
+    let mut state = SystemData::new(config);
+    let mut extras = procfs::ExtrasCache::default();
+    let mut stdout = io::stdout();
+    loop {
+        tokio::time::sleep(interval).await;
+        state.sample(&mut extras);
+        let line = format(&state)?;
+        match writeln!(stdout, "{}", line).and_then(|_| stdout.flush()) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+async fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    config: Config,
+    history_file: Option<PathBuf>,
+    preset: Option<Preset>,
+    interval: Option<u64>,
+    mouse: bool,
+    resume: bool,
+) -> Result<()> {
+    let poll_rate = preset.map_or(100, Preset::redraw_interval);
+    let refresh_interval = interval
+        .or_else(|| preset.and_then(Preset::refresh_interval))
+        .unwrap_or(config.interface.refresh_ms);
+
+    let mut state = SystemData::new(&config);
+    state.lightweight = preset.is_some_and(Preset::lightweight);
+    state.headless = preset.is_some_and(Preset::headless);
+    if let Some(saved) = history_file.as_deref().and_then(persist::load) {
+        state.restore_history(saved);
+    }
+    // The daemon's history is at least as recent as the saved one
+    let attached = daemon::socket_path()
+        .as_deref()
+        .and_then(daemon::attach)
+        .map(|saved| state.restore_history(saved))
+        .is_some();
+    let mut extras = procfs::ExtrasCache::default();
+    state.sample(&mut extras);
+
+    let mut app = App {
+        columns: config.processes.columns,
+        sort: config
+            .processes
+            .sort
+            .map(|column| (column, column.sorts_descending())),
+        highlights: config.processes.highlight,
+        freeze_processes: state.freeze_processes.clone(),
+        freeze_while_navigating: config.processes.freeze_while_navigating,
+        refresh_interval: Arc::new(AtomicU64::new(refresh_interval)),
+        color_depth: palette::detect(),
+        config_path: config.path,
+        theme: config.interface.theme,
+        units: config.interface.units,
+        panels: config.interface.panels,
+        bell: config.notify.bell,
+        flash_alerts: config.notify.flash,
+        set_title: config.terminal.title,
+        sensor_config: config.sensors,
+        preset,
+        mouse,
+        ..App::default()
+    };
+
+    if let Some(session) = resume.then(session::load).flatten() {
+        app.restore_session(session, &state.system);
+    }
+    if attached {
+        app.set_status("Attached to the daemon, q detaches and leaves it running".to_string());
+    }
+
+    let system_data = Arc::new(RwLock::new(state));
+
+    let loop_system_data = system_data.clone();
+    let loop_paused = app.paused.clone();
+    let loop_interval = app.refresh_interval.clone();
+    let loop_refresh = app.refresh_now.clone();
+    let lightweight = system_data.read().await.lightweight;
+    let sampler = tokio::spawn(async move {
+        let mut forced = false;
+        loop {
+            if forced || !loop_paused.load(Ordering::Relaxed) {
+                // /proc is read for every process, drawing shouldn't wait on it
+                let read = if lightweight {
+                    HashMap::new()
+                } else {
+                    tokio::task::block_in_place(|| extras.read())
+                };
+                let mut system_data = loop_system_data.write().await;
+                system_data.process_extras = read;
+                system_data.update_system_info();
+            }
+            let interval = Duration::from_millis(loop_interval.load(Ordering::Relaxed));
+            forced = tokio::select! {
+                _ = tokio::time::sleep(interval) => false,
+                _ = loop_refresh.notified() => true,
+            };
+        }
+    });
+
+    // Lookups can take seconds, so they get a task of their own rather than
+    // holding up sampling
+    if let Some(name) = config.dns.name.clone() {
+        let dns_system_data = system_data.clone();
+        let interval = Duration::from_secs(config.dns.interval_seconds.max(1));
+        tokio::spawn(async move {
+            loop {
+                let result = dns::lookup(&name).await;
+                let now = Local::now().timestamp_millis() as f64 / 1000.0;
+                if let Some(dns) = dns_system_data.write().await.dns.as_mut() {
+                    dns.record(now, result);
+                }
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+
+    let headless = preset.is_some_and(Preset::headless);
+    if !headless {
+        let power_system_data = system_data.clone();
+        let power_changed = app.power_changed.clone();
+        tokio::spawn(async move {
+            loop {
+                let profile = tokio::task::spawn_blocking(power::read_profile)
+                    .await
+                    .ok()
+                    .flatten();
+                power_system_data.write().await.power_profile = profile;
+                tokio::select! {
+                    _ = tokio::time::sleep(power::POLL_INTERVAL) => {}
+                    _ = power_changed.notified() => {}
+                }
+            }
+        });
+    }
+
+    if !headless && gpu::has_nvidia() {
+        let nvidia_system_data = system_data.clone();
+        tokio::spawn(async move {
+            loop {
+                let processes = gpu::nvidia_processes().await.unwrap_or_default();
+                let cards = gpu::nvidia_cards().await;
+                {
+                    let mut system_data = nvidia_system_data.write().await;
+                    system_data.gpu.nvidia = processes;
+                    system_data.gpu.nvidia_cards = cards;
+                }
+                tokio::time::sleep(gpu::NVIDIA_INTERVAL).await;
+            }
+        });
+    }
+
+    // Scans every process's file descriptors, so only with a GPU around and
+    // without holding the state
+    if !headless && (gpu::present() || gpu::has_nvidia()) {
+        let clients_system_data = system_data.clone();
+        tokio::spawn(async move {
+            loop {
+                let pids = clients_system_data
+                    .read()
+                    .await
+                    .system
+                    .processes()
+                    .keys()
+                    .map(|pid| pid.as_u32())
+                    .collect::<Vec<u32>>();
+                let clients =
+                    tokio::task::spawn_blocking(move || gpu::read_clients(pids.into_iter()))
+                        .await
+                        .unwrap_or_default();
+                let now = Local::now().timestamp_millis() as f64 / 1000.0;
+                clients_system_data.write().await.gpu.record(now, clients);
+                tokio::time::sleep(gpu::CLIENT_INTERVAL).await;
+            }
+        });
+    }
+
+    // Asking disks for their power state runs hdparm per disk, so this isn't
+    // done more often than STORAGE_REFRESH
+    let disks_system_data = system_data.clone();
+    let disks = app.disks.clone();
+    let disks_interval = app.refresh_interval.clone();
+    tokio::spawn(async move {
+        loop {
+            disks.asked().await;
+            let read = tokio::task::spawn_blocking(storage::read_disks)
+                .await
+                .unwrap_or_default();
+            disks_system_data.write().await.disks = read;
+            let interval =
+                Duration::from_millis(disks_interval.load(Ordering::Relaxed)).max(STORAGE_REFRESH);
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    // Reads the executable link of every process, so on the same slower
+    // schedule as the disks
+    let anomalies_system_data = system_data.clone();
+    let anomalies = app.anomalies.clone();
+    let anomalies_interval = app.refresh_interval.clone();
+    tokio::spawn(async move {
+        loop {
+            anomalies.asked().await;
+            let processes = anomalies_system_data
+                .read()
+                .await
+                .processes
+                .iter()
+                .map(|process| (process.pid, process.name.clone(), process.user.clone()))
+                .collect::<Vec<(u32, String, String)>>();
+            let read = tokio::task::spawn_blocking(move || {
+                let mut found = processes
+                    .iter()
+                    .filter_map(|(pid, name, user)| anomalies::check(*pid, name, user))
+                    .collect::<Vec<anomalies::Anomaly>>();
+                found.sort_by_key(|anomaly| anomaly.pid);
+                found
+            })
+            .await
+            .unwrap_or_default();
+            anomalies_system_data.write().await.anomalies = read;
+            let interval = Duration::from_millis(anomalies_interval.load(Ordering::Relaxed))
+                .max(STORAGE_REFRESH);
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    // Reads every process's file descriptors and runs pactl, so on the same
+    // slower schedule as the disks
+    let captures_system_data = system_data.clone();
+    let captures = app.captures.clone();
+    let captures_interval = app.refresh_interval.clone();
+    tokio::spawn(async move {
+        loop {
+            captures.asked().await;
+            let processes = captures_system_data
+                .read()
+                .await
+                .processes
+                .iter()
+                .map(|process| (process.pid, process.name.clone()))
+                .collect::<Vec<(u32, String)>>();
+            let read = tokio::task::spawn_blocking(move || {
+                capture::read(processes.iter().map(|(pid, name)| (*pid, name.as_str())))
+            })
+            .await
+            .unwrap_or_default();
+            captures_system_data.write().await.captures = read;
+            let interval = Duration::from_millis(captures_interval.load(Ordering::Relaxed))
+                .max(STORAGE_REFRESH);
+            tokio::time::sleep(interval).await;
+        }
+    });
+
+    if systemd::is_running() {
+        let units_system_data = system_data.clone();
+        tokio::spawn(async move {
+            loop {
+                let units = systemd::failed_units().await;
+                units_system_data.write().await.failed_units = units;
+                tokio::time::sleep(systemd::POLL_INTERVAL).await;
+            }
+        });
+        let inhibitors_system_data = system_data.clone();
+        tokio::spawn(async move {
+            loop {
+                let inhibitors = systemd::inhibitors().await;
+                inhibitors_system_data.write().await.inhibitors = inhibitors;
+                tokio::time::sleep(systemd::INHIBITOR_INTERVAL).await;
+            }
+        });
+    }
+
+    // Being closed by a window or service manager ends like quitting: the
+    // terminal is restored and the history saved
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut hangup = signal(SignalKind::hangup())?;
+    let signal_shutdown = shutdown.clone();
+    let signal_mouse = app.mouse;
+    let signals = tokio::spawn(async move {
+        tokio::select! {
+            _ = terminate.recv() => {}
+            _ = hangup.recv() => {}
+        }
+        signal_shutdown.store(true, Ordering::Relaxed);
+
+        // Drawing to a terminal that hung up can block for good, so the render
+        // loop may not get to see the flag. It aborts this task once it has,
+        // and saves the history itself.
+        tokio::time::sleep(SHUTDOWN_GRACE).await;
+        let _ = disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen);
+        if signal_mouse {
+            let _ = execute!(io::stdout(), DisableMouseCapture);
+        }
+        std::process::exit(0);
+    });
+
+    // Catching SIGTSTP replaces the default stop, which would leave the shell
+    // in raw mode on the alternate screen
+    let suspend_requested = Arc::new(AtomicBool::new(false));
+    let mut terminal_stop = signal(SignalKind::from_raw(libc::SIGTSTP))?;
+    let signal_suspend = suspend_requested.clone();
+    tokio::spawn(async move {
+        while terminal_stop.recv().await.is_some() {
+            signal_suspend.store(true, Ordering::Relaxed);
+        }
+    });
+
+    let mut session_saved = Instant::now();
+    loop {
+        if shutdown.load(Ordering::Relaxed) {
+            break;
+        }
+        app.thaw_processes();
+        {
+            let system_data = system_data.read().await;
+            if app.notice_alerts(&system_data.alerts) && app.bell {
+                execute!(io::stdout(), Print("\x07"))?;
+            }
+            if suspend_requested.swap(false, Ordering::Relaxed) {
+                suspend(terminal, &system_data.system, app.mouse)?;
+            }
+            terminal.draw(|f| {
+                ui(f, &system_data, &mut app);
+            })?;
+            if resume && session_saved.elapsed() >= SESSION_INTERVAL {
+                app.save_session(&system_data.system);
+                session_saved = Instant::now();
+            }
+            if app.set_title {
+                let title = window_title(&system_data);
+                if title != app.title {
+                    execute!(io::stdout(), SetTitle(&title))?;
+                    app.title = title;
+                }
+            }
+        }
+
+        // The lock isn't held while waiting, so sampling carries on meanwhile
+        if !event::poll(Duration::from_millis(poll_rate))? {
+            continue;
+        }
+        let event = event::read()?;
+        let system_data = system_data.read().await;
+        match event {
+            // Raw mode turns Ctrl+C into a key press, it quits from anywhere
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('c'),
+                modifiers,
+                ..
+            }) if modifiers.contains(KeyModifiers::CONTROL) => break,
+            Event::Key(KeyEvent {
+                code: KeyCode::Char('z'),
+                modifiers,
+                ..
+            }) if modifiers.contains(KeyModifiers::CONTROL) => {
+                suspend(terminal, &system_data.system, app.mouse)?
+            }
+            Event::Key(key) => match app.popup {
+                Some(Popup::Sysctl) => match key.code {
+                    KeyCode::Esc => app.popup = None,
+                    KeyCode::Up => app.scroll_sysctls(-1),
+                    KeyCode::Down => app.scroll_sysctls(1),
+                    KeyCode::Backspace => {
+                        app.sysctl_query.pop();
+                        app.scroll_sysctls(0);
+                    }
+                    KeyCode::Char(c) => {
+                        app.sysctl_query.push(c);
+                        app.sysctl_state.select(Some(0));
+                        app.scroll_sysctls(0);
+                    }
+                    _ => {}
+                },
+                Some(Popup::Settings) => match key.code {
+                    KeyCode::Esc | KeyCode::Char('O') => app.popup = None,
+                    KeyCode::Up => app.scroll_settings(-1),
+                    KeyCode::Down => app.scroll_settings(1),
+                    KeyCode::Left => app.change_setting(-1),
+                    KeyCode::Right | KeyCode::Char(' ') | KeyCode::Enter => app.change_setting(1),
+                    KeyCode::Char('s') => app.save_settings(),
+                    _ => {}
+                },
+                Some(Popup::Columns) => match key.code {
+                    KeyCode::Esc | KeyCode::Char('o') => app.popup = None,
+                    KeyCode::Up => app.scroll_columns(-1),
+                    KeyCode::Down => app.scroll_columns(1),
+                    KeyCode::Char(' ') | KeyCode::Enter => app.toggle_column(),
+                    KeyCode::Char('+') => app.move_column(-1),
+                    KeyCode::Char('-') => app.move_column(1),
+                    _ => {}
+                },
+                Some(Popup::Users) => match key.code {
+                    KeyCode::Esc | KeyCode::Char('u') => app.popup = None,
+                    KeyCode::Up => app.scroll_users(-1),
+                    KeyCode::Down => app.scroll_users(1),
+                    KeyCode::Enter => app.choose_user(),
+                    _ => {}
+                },
+                Some(Popup::Confirm) => match key.code {
+                    KeyCode::Char('y')
+                        if app.pending.as_ref().is_some_and(|p| p.escalator.is_some()) =>
+                    {
+                        // A password prompt can take a while, sampling carries on meanwhile
+                        drop(system_data);
+                        escalate(terminal, &mut app).await?
+                    }
+                    KeyCode::Char('y') => app.confirm_action(),
+                    KeyCode::Char('n') | KeyCode::Esc => {
+                        app.pending = None;
+                        app.popup = None;
+                    }
+                    _ => {}
+                },
+                Some(Popup::Storage) => {
+                    if let KeyCode::Esc | KeyCode::Char('d') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::FailedUnits) => {
+                    if let KeyCode::Esc | KeyCode::Char('F') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Sleep) => {
+                    if let KeyCode::Esc | KeyCode::Char('W') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Tasks) => {
+                    if let KeyCode::Esc | KeyCode::Char('T') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Throttling) => {
+                    if let KeyCode::Esc | KeyCode::Char('f') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Anomalies) => {
+                    if let KeyCode::Esc | KeyCode::Char('A') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Gpu) => {
+                    if let KeyCode::Esc | KeyCode::Char('G') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Capture) => {
+                    if let KeyCode::Esc | KeyCode::Char('p') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Help) => match key.code {
+                    KeyCode::Up => app.help_scroll = app.help_scroll.saturating_sub(1),
+                    KeyCode::Down => app.help_scroll = (app.help_scroll + 1).min(HELP.len() as u16),
+                    KeyCode::PageUp => app.help_scroll = app.help_scroll.saturating_sub(10),
+                    KeyCode::PageDown => {
+                        app.help_scroll = (app.help_scroll + 10).min(HELP.len() as u16)
+                    }
+                    KeyCode::Home => app.help_scroll = 0,
+                    KeyCode::Esc | KeyCode::Char('?') => app.popup = None,
+                    _ => {}
+                },
+                Some(Popup::Network) => match key.code {
+                    KeyCode::Up => app.scroll_interfaces(-1, &system_data.interfaces),
+                    KeyCode::Down => app.scroll_interfaces(1, &system_data.interfaces),
+                    KeyCode::Esc | KeyCode::Char('n') => app.popup = None,
+                    _ => {}
+                },
+                Some(Popup::Sensors) => {
+                    if let KeyCode::Esc | KeyCode::Char('h') = key.code {
+                        app.popup = None;
+                    }
+                }
+                Some(Popup::Maintenance) => match key.code {
+                    KeyCode::Char('y') => app.run_task(),
+                    KeyCode::Char('n') | KeyCode::Esc => {
+                        app.pending_task = None;
+                        app.popup = None;
+                    }
+                    _ => {}
+                },
+                Some(Popup::ProcessDetail(_)) => match key.code {
+                    KeyCode::Esc | KeyCode::Enter => app.popup = None,
+                    KeyCode::Char('G') => app.popup = Some(Popup::Gpu),
+                    _ => {}
+                },
+                None if app.command_input.is_some() => match key.code {
+                    KeyCode::Enter => {
+                        app.run_command(&system_data);
+                        // powerprofilesctl goes through D-Bus, sampling carries on meanwhile
+                        if let Some(choice) = app.power_choice.take() {
+                            drop(system_data);
+                            set_power_profile(&mut app, choice).await;
+                        }
+                    }
+                    KeyCode::Esc => app.command_input = None,
+                    KeyCode::Backspace => {
+                        if let Some(input) = app.command_input.as_mut() {
+                            input.pop();
+                        }
+                    }
+                    KeyCode::Char(c) => {
+                        if let Some(input) = app.command_input.as_mut() {
+                            input.push(c);
+                        }
+                    }
+                    _ => {}
+                },
+                None if app.editing_filter => match key.code {
+                    KeyCode::Enter => app.editing_filter = false,
+                    KeyCode::Esc => {
+                        app.editing_filter = false;
+                        app.edit_filter(String::clear);
+                    }
+                    KeyCode::Backspace => app.edit_filter(|query| {
+                        query.pop();
+                    }),
+                    KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.edit_filter(String::clear)
+                    }
+                    KeyCode::Char(c) => app.edit_filter(|query| query.push(c)),
+                    _ => {}
+                },
+                None => match key.code {
+                    KeyCode::Char('q') => break,
+                    KeyCode::Char('s') => app.open_sysctl_popup(),
+                    KeyCode::Char('h') => app.open_sensor_popup(),
+                    KeyCode::Char('n') => app.popup = Some(Popup::Network),
+                    KeyCode::Char('d') => app.popup = Some(Popup::Storage),
+                    KeyCode::Char('A') => app.popup = Some(Popup::Anomalies),
+                    KeyCode::Char('p') => app.popup = Some(Popup::Capture),
+                    KeyCode::Char('f') => app.popup = Some(Popup::Throttling),
+                    KeyCode::Char('G') => app.popup = Some(Popup::Gpu),
+                    KeyCode::Char('T') => app.popup = Some(Popup::Tasks),
+                    KeyCode::Char('F') => app.popup = Some(Popup::FailedUnits),
+                    KeyCode::Char('W') => app.popup = Some(Popup::Sleep),
+                    KeyCode::Char('?') => {
+                        app.help_scroll = 0;
+                        app.popup = Some(Popup::Help);
+                    }
+                    KeyCode::Char('x') => app.export_panel(&system_data),
+                    KeyCode::Char('o') => app.open_column_popup(),
+                    KeyCode::Char('O') => app.open_settings_popup(),
+                    KeyCode::Char('/') => app.editing_filter = true,
+                    KeyCode::Char(':') => app.command_input = Some(String::new()),
+                    KeyCode::Char(' ') => app.toggle_mark(
+                        selected_process(&system_data, &app),
+                        &row_pids(&system_data, &app),
+                    ),
+                    KeyCode::Char('k') => app.confirm_for_targets(&system_data, Action::Terminate),
+                    KeyCode::Char('K') => app.confirm_for_targets(&system_data, Action::Kill),
+                    KeyCode::Char('u') => app.open_user_popup(&system_data.processes),
+                    KeyCode::Char('U') => app.toggle_own_processes(own_user(&system_data)),
+                    KeyCode::Char(key @ '1'..='5') => app.view = VIEWS[key as usize - '1' as usize],
+                    // The network tab has no process table, its rows are the interfaces
+                    KeyCode::Up if app.view == AppView::Network => {
+                        app.scroll_interfaces(-1, &system_data.interfaces)
+                    }
+                    KeyCode::Down if app.view == AppView::Network => {
+                        app.scroll_interfaces(1, &system_data.interfaces)
+                    }
+                    KeyCode::Up => app.scroll_processes(-1, &row_pids(&system_data, &app)),
+                    KeyCode::Down => app.scroll_processes(1, &row_pids(&system_data, &app)),
+                    KeyCode::PageUp => app.scroll_processes(
+                        -(app.process_page as i64),
+                        &row_pids(&system_data, &app),
+                    ),
+                    KeyCode::PageDown => {
+                        app.scroll_processes(app.process_page as i64, &row_pids(&system_data, &app))
+                    }
+                    KeyCode::Enter => app.open_process_popup(selected_process(&system_data, &app)),
+                    KeyCode::Char('t') => app.toggle_tree(),
+                    KeyCode::Char('P') => app.sort_by(Column::Cpu),
+                    KeyCode::Char('M') => app.sort_by(Column::Mem),
+                    KeyCode::Char('N') => app.sort_by(Column::Pid),
+                    KeyCode::Char('S') => app.cycle_sort(),
+                    KeyCode::Char('I') => app.invert_sort(),
+                    KeyCode::Char('c') => app.toggle_per_core(),
+                    KeyCode::Char('g') => app.toggle_group_units(),
+                    KeyCode::Char('-') if app.tree => {
+                        app.set_collapsed(selected_process(&system_data, &app), true)
+                    }
+                    KeyCode::Char('+') if app.tree => {
+                        app.set_collapsed(selected_process(&system_data, &app), false)
+                    }
+                    KeyCode::Char('C') if app.tree => {
+                        app.collapse_all(&filtered_processes(&system_data, &app))
+                    }
+                    KeyCode::Char('E') if app.tree => app.collapsed.clear(),
+                    KeyCode::Char('a') => {
+                        app.toggle_aggregate(&filtered_processes(&system_data, &app))
+                    }
+                    KeyCode::Char('i') => app.toggle_inspect(),
+                    KeyCode::Left if app.inspect.is_some() => app.move_inspect(1),
+                    KeyCode::Right if app.inspect.is_some() => app.move_inspect(-1),
+                    KeyCode::Home if app.inspect.is_some() => app.move_inspect(i64::from(u16::MAX)),
+                    KeyCode::End if app.inspect.is_some() => app.move_inspect(-i64::from(u16::MAX)),
+                    KeyCode::Tab if app.inspect.is_some() => app.switch_inspect_chart(),
+                    KeyCode::Char('z') => app.toggle_pause(),
+                    KeyCode::Char('r') => app.refresh(),
+                    // + and - fold the tree while it's shown
+                    KeyCode::Char('<') | KeyCode::Char('+') => app.change_refresh_interval(1),
+                    KeyCode::Char('>') | KeyCode::Char('-') => app.change_refresh_interval(-1),
+                    KeyCode::Left if app.is_paused() => {
+                        app.scroll_history(1.0, &system_data.memory_history)
+                    }
+                    KeyCode::Right if app.is_paused() => {
+                        app.scroll_history(-1.0, &system_data.memory_history)
+                    }
+                    KeyCode::Home if app.is_paused() => {
+                        app.scroll_history(f64::INFINITY, &system_data.memory_history)
+                    }
+                    KeyCode::End if app.is_paused() => app.scroll = 0.0,
+                    KeyCode::Home => {
+                        app.scroll_processes(-i64::from(u32::MAX), &row_pids(&system_data, &app))
+                    }
+                    KeyCode::End => {
+                        app.scroll_processes(i64::from(u32::MAX), &row_pids(&system_data, &app))
+                    }
+                    KeyCode::Left => app.scroll_process_columns(-PROCESS_SCROLL_STEP),
+                    KeyCode::Right => app.scroll_process_columns(PROCESS_SCROLL_STEP),
+                    KeyCode::Char('w') => app.cycle_window(),
+                    KeyCode::Esc => {
+                        app.inspect = None;
+                        app.marked.clear();
+                        if !app.process_query.is_empty() {
+                            app.edit_filter(String::clear);
+                        }
+                    }
+                    _ => {}
+                },
+            },
+            Event::Mouse(MouseEvent {
+                kind: MouseEventKind::Moved | MouseEventKind::Drag(_),
+                column,
+                row,
+                ..
+            }) => app.mouse_position = Some((column, row)),
+            // The next draw lays everything out again, the old pointer
+            // position could now be over a different panel
+            Event::Resize(_, _) => {
+                terminal.autoresize()?;
+                app.mouse_position = None;
+            }
+            _ => {}
+        }
+    }
+
+    sampler.abort();
+    signals.abort();
+    if resume {
+        app.save_session(&system_data.read().await.system);
+    }
+    if let Some(path) = &history_file {
+        persist::save(path, &system_data.read().await.saved_history())?;
+    }
+    Ok(())
+}
+
+fn ui<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App) {
+    // Wrapping block for a group
+    // Just draw the block and the group on the same area and build the group
+    // with at least a margin of 1
+    let size = f.size();
+    if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+        too_small(f, size);
+        return;
+    }
+
+    // Surrounding block
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(" System Info ")
+        .title_alignment(Alignment::Left)
+        .border_type(BorderType::Rounded);
+    f.render_widget(block, size);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(1)
+        .constraints(
+            [
+                Constraint::Length(1),
+                Constraint::Length(1),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ]
+            .as_ref(),
+        )
+        .split(f.size());
+
+    let header = header_block(system_data, app);
+    f.render_widget(header, chunks[0]);
+    f.render_widget(tab_bar(app), chunks[1]);
+
+    // Only the overview has charts to hover over or inspect
+    if app.view != AppView::Overview {
+        app.cpu_plot_area = Rect::default();
+        app.memory_plot_area = Rect::default();
+    }
+    match app.view {
+        AppView::Overview => overview(f, system_data, app, chunks[2]),
+        AppView::Processes => processes_block(f, system_data, app, chunks[2]),
+        AppView::Network => network_view(f, system_data, app, chunks[2]),
+        AppView::Disks => disks_view(f, system_data, app, chunks[2]),
+        AppView::Sensors => sensors_view(f, app, chunks[2]),
+    }
+
+    let info_block = info_block(app);
+    f.render_widget(info_block, chunks[3]);
+
+    match app.popup {
+        Some(Popup::Sysctl) => sysctl_popup(f, app, size),
+        Some(Popup::ProcessDetail(pid)) => process_popup(f, system_data, app, pid, size),
+        Some(Popup::Columns) => column_popup(f, app, size),
+        Some(Popup::Settings) => settings_popup(f, app, size),
+        Some(Popup::Help) => help_popup(f, app, size),
+        Some(Popup::Users) => user_popup(f, app, size),
+        Some(Popup::Confirm) => confirm_popup(f, app, size),
+        Some(Popup::Maintenance) => maintenance_popup(f, app, size),
+        Some(Popup::Sensors) => sensor_popup(f, app, size),
+        Some(Popup::Network) => network_popup(f, system_data, app, size),
+        Some(Popup::Storage) => storage_popup(f, system_data, app, size),
+        Some(Popup::Anomalies) => anomaly_popup(f, system_data, app, size),
+        Some(Popup::Capture) => capture_popup(f, system_data, app, size),
+        Some(Popup::Gpu) => gpu_popup(f, system_data, app, size),
+        Some(Popup::Throttling) => throttling_popup(f, system_data, app, size),
+        Some(Popup::Tasks) => tasks_popup(f, system_data, app, size),
+        Some(Popup::FailedUnits) => failed_units_popup(f, system_data, size),
+        Some(Popup::Sleep) => sleep_popup(f, system_data, size),
+        None => chart_readout(f, system_data, app, size),
+    }
+
+    f.render_widget(palette::Adapt(app.color_depth, app.theme), size);
+}
+
+fn tab_bar<'a>(app: &App) -> Tabs<'a> {
+    let titles = VIEWS
+        .iter()
+        .enumerate()
+        .map(|(index, view)| Spans::from(format!("{} {}", index + 1, view.name())))
+        .collect();
+    let selected = VIEWS.iter().position(|view| *view == app.view);
+    Tabs::new(titles)
+        .select(selected.unwrap_or(0))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+}
+
+// The layout the preset or the terminal's width calls for
+fn overview<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    if f.size().width < NARROW_WIDTH {
+        narrow_body(f, system_data, app, area);
+    } else if app.preset == Some(Preset::Ml) {
+        ml_body(f, system_data, app, area);
+    } else if app.preset == Some(Preset::Gaming) {
+        gaming_body(f, system_data, app, area);
+    } else if app.preset == Some(Preset::Server) {
+        server_body(f, system_data, app, area);
+    } else {
+        wide_body(f, system_data, app, area);
+    }
+}
+
+// Usage over the interface table and the rest of the network popup
+fn network_view<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(35), Constraint::Min(0)].as_ref())
+        .split(area);
+    f.render_widget(network_chart(system_data, app), chunks[0]);
+    let block = Block::default().title(" Network ").borders(Borders::ALL);
+    let inner = block.inner(chunks[1]);
+    f.render_widget(block, chunks[1]);
+    network_details(f, system_data, app, inner);
+}
+
+// Throughput and space over the storage popup's list of disks
+fn disks_view<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(40), Constraint::Min(0)].as_ref())
+        .split(area);
+    let top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(rows[0]);
+    f.render_widget(disk_io_chart(system_data, app), top[0]);
+    disks_block(f, system_data, app, top[1]);
+    let block = Block::default().title(" Storage ").borders(Borders::ALL);
+    let inner = block.inner(rows[1]);
+    f.render_widget(block, rows[1]);
+    storage_details(f, system_data, app, inner);
+}
+
+fn sensors_view<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let block = Block::default().title(" Sensors ").borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    sensor_details(f, app, inner);
+}
+
+// Charts side by side over the process table, with network usage and disk
+// space stacked next to it. Hidden panels leave their space to the ones next
+// to them or the row below.
+fn wide_body<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    let rows = [
+        vec![Panel::Cpu, Panel::Memory, Panel::Io],
+        vec![Panel::Processes, Panel::Network, Panel::Disks],
+    ]
+    .into_iter()
+    .map(|row| {
+        row.into_iter()
+            .filter(|panel| app.panels.contains(panel))
+            .collect::<Vec<Panel>>()
+    })
+    .filter(|row| !row.is_empty())
+    .collect::<Vec<Vec<Panel>>>();
+    let row_areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, rows.len() as u32); rows.len()])
+        .split(area);
+
+    // Nothing to hover over or inspect unless the chart is shown
+    app.cpu_plot_area = Rect::default();
+    app.memory_plot_area = Rect::default();
+
+    for (row, row_area) in rows.iter().zip(row_areas) {
+        let constraints = if row.len() > 1 {
+            vec![Constraint::Percentage(70), Constraint::Percentage(30)]
+        } else {
+            vec![Constraint::Percentage(100)]
+        };
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(row_area);
+        // The first panel takes the wide column, the rest share the narrow one
+        let stacked = row.len().saturating_sub(1).max(1) as u32;
+        let mut areas = vec![columns[0]];
+        if let Some(narrow) = columns.get(1) {
+            areas.extend(
+                Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(vec![Constraint::Ratio(1, stacked); stacked as usize])
+                    .split(*narrow),
+            );
+        }
+        for (panel, area) in row.iter().zip(areas) {
+            match panel {
+                Panel::Cpu => {
+                    let cpu_block = cpu_block(f, system_data, app, area);
+                    f.render_widget(cpu_block, area);
+                    app.cpu_plot_area = chart_plot_area(area, "100%".len() as u16);
+                }
+                Panel::Memory => {
+                    let ram_block = ram_block(f, system_data, app, area);
+                    f.render_widget(ram_block, area);
+                    let memory_label_width =
+                        memory_to_gb(&system_data.total_memory, app.units).len() as u16;
+                    app.memory_plot_area = chart_plot_area(area, memory_label_width);
+                }
+                Panel::Processes => processes_block(f, system_data, app, area),
+                Panel::Network => f.render_widget(network_chart(system_data, app), area),
+                Panel::Disks => disks_block(f, system_data, app, area),
+                Panel::Io => f.render_widget(disk_io_chart(system_data, app), area),
+            }
+        }
+    }
+}
+
+// The GPU's engines and memory over its power and temperature, then the
+// processes using it
+fn ml_body<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage(40),
+                Constraint::Percentage(25),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let top_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(chunks[0]);
+    let sensor_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)].as_ref())
+        .split(chunks[1]);
+
+    let gpu = &system_data.gpu;
+    let engines = gpu_engine_chart(
+        system_data,
+        app,
+        Block::default()
+            .title(format!(
+                " GPU busy ({}){} ",
+                app.window.label(),
+                gpu_devices(gpu)
+            ))
+            .borders(Borders::ALL),
+    );
+    f.render_widget(engines, top_chunks[0]);
+    app.cpu_plot_area = Rect::default();
+
+    let ram_block = ram_block(f, system_data, app, top_chunks[1]);
+    f.render_widget(ram_block, top_chunks[1]);
+    let memory_label_width = memory_to_gb(&system_data.total_memory, app.units).len() as u16;
+    app.memory_plot_area = chart_plot_area(top_chunks[1], memory_label_width);
+
+    let span = chart_span(system_data, app);
+    let sensors = [
+        (&gpu.power_history, " GPU power", " W", Color::Yellow, 1.0),
+        (
+            &gpu.temperature_history,
+            " GPU temperature",
+            "°C",
+            Color::Red,
+            100.0,
+        ),
+    ];
+    // The same label width on both keeps their time axes lined up
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let label = |value: f64, unit: &str| {
+        Span::styled(format!("{:>6}", format!("{:.0}{}", value, unit)), bold)
+    };
+    for ((history, name, unit, color, floor), area) in sensors.into_iter().zip(sensor_chunks.iter())
+    {
+        let points = history::slice(history.points(app.window), span.0, span.1);
+        let max = points.iter().map(|(_, value)| *value).fold(floor, f64::max);
+        let title = match history.latest() {
+            Some(value) => format!("{} now {:.0}{} ", name, value, unit),
+            None => format!("{} unavailable ", name),
+        };
+        let chart = Chart::new(vec![Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(color))
+            .data(points)])
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![label(0.0, unit), label(max, unit)])
+                .bounds([0.0, max]),
+        );
+        f.render_widget(chart, *area);
+    }
+
+    gpu_process_table(f, system_data, app.units, chunks[2]);
+}
+
+// One sparkline per row, the newest sample on the right
+fn gaming_body<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    // Nothing to hover over or inspect
+    app.cpu_plot_area = Rect::default();
+    app.memory_plot_area = Rect::default();
+
+    let gpu = &system_data.gpu;
+    let empty = History::default();
+    let cpu = system_data
+        .cpus
+        .first()
+        .map(|cpu| &cpu.history)
+        .unwrap_or(&empty);
+    let memory_total = system_data.total_memory / GIB;
+    let vram_total = gpu.vram_total.unwrap_or(0) as f64 / GIB;
+    // The memory histories are in GiB, titled in the chosen units
+    let (gigabyte, gigabyte_name) = app.units.gigabyte();
+    let memory_unit = format!(" {}", gigabyte_name);
+    let in_units = GIB / gigabyte;
+    // History, name, unit, decimals, scale for the title, top of the chart and color
+    let lines = [
+        (cpu, "CPU", "%", 0, 1.0, 100.0, Color::Green),
+        (&gpu.busy_history, "GPU", "%", 0, 1.0, 100.0, Color::Yellow),
+        (
+            &system_data.memory_history,
+            "RAM",
+            memory_unit.as_str(),
+            1,
+            in_units,
+            memory_total,
+            Color::Cyan,
+        ),
+        (
+            &gpu.vram_history,
+            "VRAM",
+            memory_unit.as_str(),
+            1,
+            in_units,
+            vram_total,
+            Color::Magenta,
+        ),
+        (
+            &system_data.package_temperature_history,
+            "CPU temp",
+            "°C",
+            0,
+            1.0,
+            100.0,
+            Color::Red,
+        ),
+        (
+            &gpu.temperature_history,
+            "GPU temp",
+            "°C",
+            0,
+            1.0,
+            100.0,
+            Color::LightRed,
+        ),
+    ];
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Ratio(1, lines.len() as u32); lines.len()])
+        .split(area);
+
+    for ((history, name, unit, decimals, scale, max, color), area) in
+        lines.into_iter().zip(chunks.iter())
+    {
+        let title = match history.latest() {
+            Some(value) => format!(" {} {:.*}{} ", name, decimals, value * scale, unit),
+            None => format!(" {} unavailable ", name),
+        };
+        let block = Block::default().title(title).borders(Borders::ALL);
+        // Sparklines take integers, tenths keep the memory lines from looking flat
+        let width = block.inner(*area).width as usize;
+        let points = history.points(app.window);
+        let data = points[points.len().saturating_sub(width)..]
+            .iter()
+            .map(|(_, value)| (value * 10.0).max(0.0) as u64)
+            .collect::<Vec<u64>>();
+        let max = (max * 10.0) as u64;
+        let sparkline = Sparkline::default()
+            .block(block)
+            .style(Style::default().fg(color))
+            .data(&data)
+            .max(max.max(data.iter().copied().max().unwrap_or(0)).max(1));
+        f.render_widget(sparkline, *area);
+    }
+}
+
+// CPU, memory and load over pressure, disk latency and connections, then the
+// processes next to the failed units
+fn server_body<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage(35),
+                Constraint::Percentage(30),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let thirds = |area: Rect| {
+        Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [
+                    Constraint::Percentage(45),
+                    Constraint::Percentage(25),
+                    Constraint::Percentage(30),
+                ]
+                .as_ref(),
+            )
+            .split(area)
+    };
+    let top = thirds(rows[0]);
+    let middle = thirds(rows[1]);
+    let bottom = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(70), Constraint::Percentage(30)].as_ref())
+        .split(rows[2]);
+
+    let cpu_block = cpu_block(f, system_data, app, top[0]);
+    f.render_widget(cpu_block, top[0]);
+    app.cpu_plot_area = chart_plot_area(top[0], "100%".len() as u16);
+
+    let ram_block = ram_block(f, system_data, app, top[1]);
+    f.render_widget(ram_block, top[1]);
+    let memory_label_width = memory_to_gb(&system_data.total_memory, app.units).len() as u16;
+    app.memory_plot_area = chart_plot_area(top[1], memory_label_width);
+
+    // Load past the core count means work is queueing
+    let cores = system_data.system.cpus().len() as f64;
+    let load = ["1m", "5m", "15m"]
+        .into_iter()
+        .zip(system_data.load_history.iter())
+        .zip([Color::Red, Color::Yellow, Color::Green])
+        .map(|((name, history), color)| (name, history, color))
+        .collect();
+    let block = Block::default()
+        .title(format!(" Load, {} cores ({}) ", cores, app.window.label()))
+        .borders(Borders::ALL);
+    let chart = history_chart(system_data, app, load, cores.max(1.0), "", block);
+    f.render_widget(chart, top[2]);
+
+    let pressure = PRESSURE_RESOURCES
+        .into_iter()
+        .zip(system_data.pressure_history.iter())
+        .zip([Color::Green, Color::Cyan, Color::Magenta])
+        .map(|((name, history), color)| (name, history, color))
+        .collect();
+    let block = Block::default()
+        .title(format!(" Pressure, stalled ({}) ", app.window.label()))
+        .borders(Borders::ALL);
+    let chart = history_chart(system_data, app, pressure, 10.0, "%", block);
+    f.render_widget(chart, middle[0]);
+
+    let block = Block::default().borders(Borders::ALL);
+    disk_latency_chart(f, system_data, app, block.inner(middle[1]));
+    f.render_widget(block, middle[1]);
+
+    let block = Block::default()
+        .title(format!(" TCP sockets ({}) ", app.window.label()))
+        .borders(Borders::ALL);
+    match system_data.tcp_states {
+        Some(states) => {
+            let chart = socket_chart(system_data, app, &states, block);
+            f.render_widget(chart, middle[2]);
+        }
+        None => f.render_widget(
+            Paragraph::new("/proc/net/tcp is unavailable").block(block),
+            middle[2],
+        ),
+    }
+
+    processes_block(f, system_data, app, bottom[0]);
+
+    let block = Block::default()
+        .title(" Failed units ")
+        .borders(Borders::ALL);
+    failed_units_table(f, system_data, block.inner(bottom[1]));
+    f.render_widget(block, bottom[1]);
+}
+
+// A line per history with its latest value in the legend, scaled to the
+// highest point or `floor`, whichever is higher
+fn history_chart<'a>(
+    system_data: &SystemData,
+    app: &App,
+    lines: Vec<(&str, &'a History, Color)>,
+    floor: f64,
+    unit: &str,
+    block: Block<'a>,
+) -> Chart<'a> {
+    let span = chart_span(system_data, app);
+    let mut max = floor;
+    let mut datasets = Vec::new();
+    for (name, history, color) in lines {
+        let points = history::slice(history.points(app.window), span.0, span.1);
+        max = points.iter().map(|(_, value)| *value).fold(max, f64::max);
+        let latest = history
+            .latest()
+            .map(|value| format!("{:.1}{}", value, unit))
+            .unwrap_or_else(|| "unavailable".to_string());
+        datasets.push(
+            Dataset::default()
+                .name(format!("{} {}", name, latest))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(points),
+        );
+    }
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    Chart::new(datasets)
+        .block(block)
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled(format!("0{}", unit), bold),
+                    Span::styled(format!("{:.0}{}", max, unit), bold),
+                ])
+                .bounds([0.0, max]),
+        )
+}
+
+// Narrower than this, e.g. a phone's SSH client, the charts give way to gauges
+// stacked over the process table
+const NARROW_WIDTH: u16 = 60;
+
+fn narrow_body<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &mut App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    // Nothing to hover over or inspect
+    app.cpu_plot_area = Rect::default();
+    app.memory_plot_area = Rect::default();
+
+    let cpu = system_data.cpu_usage.clamp(0.0, 100.0);
+    let cpu_gauge = Gauge::default()
+        .block(
+            Block::default()
+                .title(" CPU ")
+                .borders(Borders::ALL)
+                .border_style(app.border_style(ChartKind::Cpu)),
+        )
+        .gauge_style(Style::default().fg(Color::Green))
+        .ratio(cpu / 100.0)
+        .label(format!("{:.2}%", cpu));
+    f.render_widget(cpu_gauge, chunks[0]);
+
+    let used = if system_data.total_memory > 0.0 {
+        (system_data.used_memory / system_data.total_memory).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let memory_gauge = Gauge::default()
+        .block(
+            Block::default()
+                .title(" Memory ")
+                .borders(Borders::ALL)
+                .border_style(app.border_style(ChartKind::Memory)),
+        )
+        .gauge_style(Style::default().fg(Color::Cyan))
+        .ratio(used)
+        .label(format!(
+            "{} / {}",
+            memory_to_gb(&system_data.used_memory, app.units),
+            memory_to_gb(&system_data.total_memory, app.units)
+        ));
+    f.render_widget(memory_gauge, chunks[1]);
+
+    processes_block(f, system_data, app, chunks[2]);
+}
+
+// Below this the panels' borders and labels no longer fit
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 13;
+
+fn too_small<B: Backend>(f: &mut Frame<B>, size: Rect) {
+    let text = vec![
+        Spans::from(Span::styled(
+            "Terminal too small",
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Spans::from(format!("{}x{}", size.width, size.height)),
+        Spans::from(format!("need {}x{}", MIN_WIDTH, MIN_HEIGHT)),
+    ];
+    // Vertically centered when there is room for it
+    let top = size.height.saturating_sub(text.len() as u16) / 2;
+    let area = Rect::new(size.x, size.y + top, size.width, size.height - top);
+    let paragraph = Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(paragraph, area);
+}
+
+// Chart x labels are HH:MM:SS
+const TIME_LABEL_WIDTH: u16 = 8;
+
+// Approximates where tui draws the plot inside a bordered chart, to the right
+// of the y axis labels and above the x axis line and its label row
+fn chart_plot_area(area: Rect, y_label_width: u16) -> Rect {
+    // The first x label hangs left of the y axis when it is wider than the y labels
+    let label_width = y_label_width.max(TIME_LABEL_WIDTH - 1);
+    Rect::new(
+        area.x + label_width + 2,
+        area.y + 1,
+        area.width.saturating_sub(label_width + 3),
+        area.height.saturating_sub(4),
+    )
+}
+
+fn format_time(time: f64) -> String {
+    Local
+        .timestamp_millis_opt((time * 1000.0) as i64)
+        .single()
+        .map(|t| t.format("%H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+// Time span shown on the charts, with the memory history doubling as the shared timeline
+fn chart_span(system_data: &SystemData, app: &App) -> (f64, f64) {
+    let history = &system_data.memory_history;
+    let end = history.newest_time().unwrap_or(0.0) - app.scroll;
+    let oldest = history
+        .points(app.window)
+        .first()
+        .map(|(time, _)| *time)
+        .unwrap_or(end);
+
+    // Track the real history length until the window is full, so the first
+    // samples span the chart instead of sitting against the left edge
+    let start = match app.window.seconds() {
+        Some(seconds) => (end - seconds).max(oldest),
+        None => history.started().unwrap_or(end),
+    };
+    (start, end.max(start + 1.0))
+}
+
+fn time_at_column(plot: Rect, column: u16, (start, end): (f64, f64)) -> Option<f64> {
+    if plot.width < 2 {
+        return None;
+    }
+    let fraction = column.saturating_sub(plot.x) as f64 / (plot.width - 1) as f64;
+    Some(start + fraction * (end - start))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ChartKind {
+    Cpu,
+    Memory,
+}
+
+// Which chart an alert on a metric belongs to
+fn alert_chart(metric: alerts::Metric) -> ChartKind {
+    match metric {
+        alerts::Metric::Memory | alerts::Metric::Swap | alerts::Metric::Pressure => {
+            ChartKind::Memory
+        }
+        _ => ChartKind::Cpu,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Inspect {
+    chart: ChartKind,
+    // Columns left of the plot's right edge, so the cursor stays put as data scrolls
+    offset: u16,
+}
+
+fn readout_lines<'a>(
+    system_data: &SystemData,
+    app: &App,
+    chart: ChartKind,
+    time: f64,
+) -> Vec<Spans<'a>> {
+    let (start, end) = chart_span(system_data, app);
+    let mut lines = vec![Spans::from(format_time(time))];
+    match chart {
+        ChartKind::Cpu => {
+            for cpu in charted_cpus(system_data, app) {
+                let points = history::slice(cpu.history.points(app.window), start, end);
+                if let Some((_, value)) = history::nearest(points, time) {
+                    lines.push(Spans::from(Span::styled(
+                        format!("{} {:.1}%", cpu.name, value),
+                        Style::default().fg(cpu.color),
+                    )));
+                }
+            }
+        }
+        ChartKind::Memory => {
+            let lines_of = [
+                (&system_data.memory_history, "", Color::Cyan),
+                (&system_data.tmpfs_history, " in tmpfs", Color::Magenta),
+                (&system_data.gpu.vram_history, " VRAM", Color::Yellow),
+            ];
+            for (history, suffix, color) in lines_of {
+                let points = history::slice(history.points(app.window), start, end);
+                if let Some((_, value)) = history::nearest(points, time) {
+                    lines.push(Spans::from(Span::styled(
+                        format!("{}{}", memory_to_gb(&(value * GIB), app.units), suffix),
+                        Style::default().fg(color),
+                    )));
+                }
+            }
+        }
+    }
+    lines
+}
+
+fn render_tooltip<B: Backend>(
+    f: &mut Frame<B>,
+    lines: Vec<Spans>,
+    column: u16,
+    row: u16,
+    size: Rect,
+) {
+    let width = lines.iter().map(|line| line.width()).max().unwrap_or(0) as u16 + 2;
+    let height = lines.len() as u16 + 2;
+    // Keep the tooltip next to the cursor but inside the terminal
+    let x = (column + 1).min(size.width.saturating_sub(width));
+    let y = (row + 1).min(size.height.saturating_sub(height));
+    let area = Rect::new(x, y, width.min(size.width), height.min(size.height));
+
+    f.render_widget(Clear, area);
+    f.render_widget(
+        Paragraph::new(lines).block(Block::default().borders(Borders::ALL)),
+        area,
+    );
+}
+
+fn chart_readout<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, size: Rect) {
+    if let Some(inspect) = app.inspect {
+        inspect_cursor(f, system_data, app, inspect, size);
+        return;
+    }
+
+    let (column, row) = match app.mouse_position {
+        Some(position) => position,
+        None => return,
+    };
+    let cursor = Rect::new(column, row, 1, 1);
+
+    let (chart, plot) = if app.cpu_plot_area.intersects(cursor) {
+        (ChartKind::Cpu, app.cpu_plot_area)
+    } else if app.memory_plot_area.intersects(cursor) {
+        (ChartKind::Memory, app.memory_plot_area)
+    } else {
+        return;
+    };
+
+    if let Some(time) = time_at_column(plot, column, chart_span(system_data, app)) {
+        let lines = readout_lines(system_data, app, chart, time);
+        render_tooltip(f, lines, column, row, size);
+    }
+}
+
+fn inspect_cursor<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &App,
+    inspect: Inspect,
+    size: Rect,
+) {
+    let plot = match inspect.chart {
+        ChartKind::Cpu => app.cpu_plot_area,
+        ChartKind::Memory => app.memory_plot_area,
+    };
+    if plot.width < 2 || plot.height == 0 {
+        return;
+    }
+
+    let column = plot.x + plot.width - 1 - inspect.offset.min(plot.width - 1);
+    let time = match time_at_column(plot, column, chart_span(system_data, app)) {
+        Some(time) => time,
+        None => return,
+    };
+
+    let cursor = Block::default()
+        .borders(Borders::LEFT)
+        .border_style(Style::default().fg(Color::Yellow));
+    f.render_widget(cursor, Rect::new(column, plot.y, 1, plot.height));
+
+    let lines = readout_lines(system_data, app, inspect.chart, time);
+    render_tooltip(f, lines, column, plot.y, size);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_y) / 2),
+                Constraint::Percentage(percent_y),
+                Constraint::Percentage((100 - percent_y) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(
+            [
+                Constraint::Percentage((100 - percent_x) / 2),
+                Constraint::Percentage(percent_x),
+                Constraint::Percentage((100 - percent_x) / 2),
+            ]
+            .as_ref(),
+        )
+        .split(vertical[1])[1]
+}
+
+fn storage_popup<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &mut App,
+    area: Rect,
+) {
+    let area = centered_rect(70, 60, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Storage ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    storage_details(f, system_data, app, inner);
+}
+
+fn storage_details<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &mut App,
+    area: Rect,
+) {
+    app.disks.ask();
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let header = Row::new(["Disk", "Model", "Size", "Type", "Temp", "State"])
+        .style(bold)
+        .bottom_margin(1);
+    let rows = system_data
+        .disks
+        .iter()
+        .map(|disk| {
+            let state = disk.power.map(|power| power.label()).unwrap_or_default();
+            let style = match disk.power {
+                Some(storage::PowerState::Active) | None => Style::default(),
+                _ => Style::default().fg(Color::DarkGray),
+            };
+            Row::new(vec![
+                Cell::from(disk.name.clone()),
+                Cell::from(disk.model.clone().unwrap_or_default()),
+                Cell::from(columns::format_bytes(disk.size as f64, app.units)),
+                Cell::from(if disk.rotational { "HDD" } else { "SSD" }),
+                Cell::from(
+                    disk.temperature
+                        .map(|celsius| format!("{:.0}°C", celsius))
+                        .unwrap_or_default(),
+                ),
+                Cell::from(state),
+            ])
+            .style(style)
+        })
+        .collect::<Vec<Row>>();
+    let disk_rows = rows.len() as u16;
+    let filesystem_rows = system_data.filesystems.len() as u16;
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(disk_rows + 3),
+                Constraint::Length(filesystem_rows + 3),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Length(10),
+        Constraint::Percentage(40),
+        Constraint::Length(8),
+        Constraint::Length(5),
+        Constraint::Length(6),
+        Constraint::Length(9),
+    ]);
+    f.render_widget(table, chunks[0]);
+
+    let header = Row::new([
+        "Mount",
+        "Type",
+        "Used",
+        "Size",
+        "Use%",
+        "Growth/day",
+        "Full in",
+    ])
+    .style(bold)
+    .bottom_margin(1);
+    let bytes = |value: f64| columns::format_bytes(value, app.units);
+    let rows = system_data
+        .filesystems
+        .iter()
+        .map(|filesystem| {
+            let percent = if filesystem.total > 0 {
+                filesystem.used() as f64 / filesystem.total as f64 * 100.0
+            } else {
+                0.0
+            };
+            let growth = match filesystem.growth_per_day() {
+                Some(growth) if growth < 0.0 => format!("-{}", bytes(-growth)),
+                Some(growth) => bytes(growth),
+                None => "measuring".to_string(),
+            };
+            let days = filesystem.days_until_full();
+            // tmpfs in the memory chart's color, it's RAM rather than disk
+            let style = if days.is_some_and(|days| days < system_data.full_within_days) {
+                Style::default().fg(Color::Red)
+            } else if filesystem.kind == "tmpfs" {
+                Style::default().fg(Color::Magenta)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(filesystem.mount.clone()),
+                Cell::from(filesystem.kind.clone()),
+                Cell::from(bytes(filesystem.used() as f64)),
+                Cell::from(bytes(filesystem.total as f64)),
+                Cell::from(format!("{:.0}%", percent)),
+                Cell::from(growth),
+                Cell::from(days.map(format_days).unwrap_or_default()),
+            ])
+            .style(style)
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Percentage(30),
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Length(8),
+        Constraint::Length(5),
+        Constraint::Length(11),
+        Constraint::Length(10),
+    ]);
+    f.render_widget(table, chunks[1]);
+
+    disk_latency_chart(f, system_data, app, chunks[2]);
+}
+
+fn network_chart<'a>(system_data: &'a SystemData, app: &App) -> Chart<'a> {
+    let [mut received, mut transmitted] = system_data.network_history.each_ref();
+    let mut title = "Network Usage".to_string();
+    // An interface that went away leaves the total charted
+    let selected = app
+        .interface
+        .as_ref()
+        .and_then(|name| system_data.interfaces.iter().find(|i| &i.name == name));
+    if let Some(interface) = selected {
+        received = &interface.received;
+        transmitted = &interface.transmitted;
+        title = format!("Network Usage, {}", interface.name);
+    }
+    throughput_chart(
+        system_data,
+        app,
+        &title,
+        [
+            ("in", received, Color::Green),
+            ("out", transmitted, Color::Yellow),
+        ],
+    )
+}
+
+fn disk_io_chart<'a>(system_data: &'a SystemData, app: &App) -> Chart<'a> {
+    let [read, write] = &system_data.disk_io_history;
+    throughput_chart(
+        system_data,
+        app,
+        "Disk I/O",
+        [("read", read, Color::Cyan), ("write", write, Color::Yellow)],
+    )
+}
+
+// Two per second byte rates, e.g. read and written, with the axis in whatever
+// unit suits the busiest moment shown
+fn throughput_chart<'a>(
+    system_data: &'a SystemData,
+    app: &App,
+    title: &str,
+    series: [(&str, &'a History, Color); 2],
+) -> Chart<'a> {
+    let span = chart_span(system_data, app);
+    let rate = |bytes: f64| format!("{}/s", columns::format_bytes(bytes, app.units));
+    let markers = [symbols::Marker::Braille, symbols::Marker::Dot];
+    let mut max: f64 = 1.0;
+    let mut datasets = Vec::new();
+    for ((name, history, color), marker) in series.into_iter().zip(markers) {
+        let points = history::slice(history.points(app.window), span.0, span.1);
+        max = points.iter().map(|(_, bytes)| *bytes).fold(max, f64::max);
+        datasets.push(
+            Dataset::default()
+                .name(format!(
+                    "{} {}",
+                    name,
+                    rate(history.latest().unwrap_or(0.0))
+                ))
+                .marker(marker)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(points),
+        );
+    }
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    Chart::new(datasets)
+        .block(
+            Block::default()
+                .title(format!(" {} ({}) ", title, app.window.label()))
+                .borders(Borders::ALL),
+        )
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![Span::styled("0", bold), Span::styled(rate(max), bold)])
+                .bounds([0.0, max]),
+        )
+}
+
+// A solid and a dotted line per disk, for reads and writes
+fn disk_latency_chart<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &App,
+    area: Rect,
+) {
+    // Two legend lines per disk, so let it take the chart's height
+    let span = chart_span(system_data, app);
+    let latest = |value: Option<f64>| {
+        value
+            .map(|ms| format!("{:.1}ms", ms))
+            .unwrap_or_else(|| "idle".to_string())
+    };
+    let mut max: f64 = 1.0;
+    let mut datasets = Vec::new();
+    for (index, disk) in system_data.disk_latency.iter().enumerate() {
+        let color = CPU_COLORS[index % CPU_COLORS.len()];
+        let lines = [
+            ("read", &disk.read, disk.last_read, symbols::Marker::Braille),
+            ("write", &disk.write, disk.last_write, symbols::Marker::Dot),
+        ];
+        for (direction, history, last, marker) in lines {
+            let points = history::slice(history.points(app.window), span.0, span.1);
+            max = points.iter().map(|(_, ms)| *ms).fold(max, f64::max);
+            datasets.push(
+                Dataset::default()
+                    .name(format!("{} {} {}", disk.name, direction, latest(last)))
+                    .marker(marker)
+                    .graph_type(GraphType::Line)
+                    .style(Style::default().fg(color))
+                    .data(points),
+            );
+        }
+    }
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(format!(
+            " I/O latency per request ({}) ",
+            app.window.label()
+        )))
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled("0ms", bold),
+                    Span::styled(format!("{:.0}ms", max), bold),
+                ])
+                .bounds([0.0, max]),
+        );
+    f.render_widget(chart, area);
+}
+
+fn socket_chart<'a>(
+    system_data: &'a SystemData,
+    app: &App,
+    states: &[u64; 11],
+    block: Block<'a>,
+) -> Chart<'a> {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let span = chart_span(system_data, app);
+    let series = system_data
+        .socket_history
+        .iter()
+        .map(|history| history::slice(history.points(app.window), span.0, span.1))
+        .collect::<Vec<_>>();
+    let max = series
+        .iter()
+        .flat_map(|points| points.iter().map(|(_, count)| *count))
+        .fold(1.0, f64::max);
+    let datasets = series
+        .into_iter()
+        .zip(netstat::CHARTED_STATES)
+        .zip(SOCKET_COLORS)
+        .map(|((points, state), color)| {
+            Dataset::default()
+                .name(format!("{} {}", netstat::TCP_STATES[state], states[state]))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(points)
+        })
+        .collect();
+    Chart::new(datasets)
+        .block(block)
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled("0", bold),
+                    Span::styled(format!("{:.0}", max), bold),
+                ])
+                .bounds([0.0, max]),
+        )
+}
+
+// Engines are charted separately so it's clear whether video playback or
+// transcoding is using the fixed function video engines or the 3D ones
+fn gpu_popup<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let area = centered_rect(70, 60, area);
+    f.render_widget(Clear, area);
+
+    let gpu = &system_data.gpu;
+    let block = Block::default()
+        .title(format!(" GPU engines{} ", gpu_devices(gpu)))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if gpu.engines.is_empty() && gpu.processes.is_empty() {
+        let text = if !gpu::present() && !gpu::has_nvidia() {
+            "No GPU found"
+        } else if privilege::sees_all_processes() {
+            "No GPU clients, or the driver doesn't report engine usage"
+        } else {
+            "None of your processes use the GPU, run as root to check all of them"
+        };
+        f.render_widget(Paragraph::new(text), inner);
+        return;
+    }
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(55), Constraint::Min(0)].as_ref())
+        .split(inner);
+    gpu_process_table(f, system_data, app.units, chunks[1]);
+
+    let block = Block::default().title(format!(" Busy ({}) ", app.window.label()));
+    f.render_widget(gpu_engine_chart(system_data, app, block), chunks[0]);
+}
+
+// ": amdgpu 0000:03:00.0", empty until a client has been seen
+fn gpu_devices(gpu: &gpu::Gpu) -> String {
+    let devices = gpu
+        .drivers
+        .iter()
+        .map(|(device, driver)| format!("{} {}", driver, device))
+        .collect::<Vec<String>>();
+    if devices.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", devices.join(", "))
+    }
+}
+
+fn gpu_engine_chart<'a>(system_data: &'a SystemData, app: &App, block: Block<'a>) -> Chart<'a> {
+    let gpu = &system_data.gpu;
+    let span = chart_span(system_data, app);
+    let mut datasets = Vec::new();
+    for (index, ((device, engine), history)) in gpu.engines.iter().enumerate() {
+        let label = if gpu.drivers.len() > 1 {
+            format!("{} {}", device, gpu::engine_label(engine))
+        } else {
+            gpu::engine_label(engine).to_string()
+        };
+        datasets.push(
+            Dataset::default()
+                .name(format!("{} {:.0}%", label, history.latest().unwrap_or(0.0)))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(CPU_COLORS[index % CPU_COLORS.len()]))
+                .data(history::slice(history.points(app.window), span.0, span.1)),
+        );
+    }
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    Chart::new(datasets)
+        .block(block)
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![Span::styled("0%", bold), Span::styled("100%", bold)])
+                .bounds([0.0, 100.0]),
+        )
+}
+
+fn gpu_process_table<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    units: Units,
+    area: Rect,
+) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let header = Row::new(
+        ["PID", "Name", "Engines", "VRAM", "Shared"]
+            .iter()
+            .map(|h| Cell::from(*h).style(bold)),
+    )
+    .height(1)
+    .bottom_margin(1);
+    let bytes = |value: u64| columns::format_bytes(value as f64, units);
+    let rows = system_data
+        .gpu
+        .processes
+        .iter()
+        .map(|process| {
+            let name = system_data
+                .processes
+                .iter()
+                .find(|p| p.pid == process.pid)
+                .map(|p| p.name.clone())
+                .unwrap_or_default();
+            Row::new(vec![
+                Cell::from(process.pid.to_string()),
+                Cell::from(name),
+                Cell::from(process.describe_engines()),
+                Cell::from(bytes(process.vram)),
+                Cell::from(bytes(process.shared)),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows)
+        .header(header)
+        .block(
+            Block::default()
+                .title(degraded_title(format!(
+                    " Processes ({}) ",
+                    system_data.gpu.processes.len()
+                )))
+                .borders(Borders::TOP),
+        )
+        .widths(&[
+            Constraint::Length(8),
+            Constraint::Percentage(20),
+            Constraint::Percentage(45),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ]);
+    f.render_widget(table, area);
+}
+
+const SOCKET_COLORS: [Color; 3] = [Color::Green, Color::Yellow, Color::Red];
+
+fn network_popup<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let area = centered_rect(80, 70, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Network ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    network_details(f, system_data, app, inner);
+}
+
+fn network_details<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    // A header, its margin and the All row above the interfaces
+    let interface_height = system_data.interfaces.len() as u16 + 3;
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(2),
+                Constraint::Length(interface_height + 1),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(area);
+    let conntrack = match system_data.conntrack {
+        Some((count, max)) => {
+            let ratio = if max > 0 {
+                (count as f64 / max as f64).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let color = if ratio >= CONNTRACK_THRESHOLD {
+                Color::Red
+            } else {
+                Color::Green
+            };
+            Gauge::default()
+                .block(Block::default().title("Conntrack table"))
+                .gauge_style(Style::default().fg(color))
+                .ratio(ratio)
+                .label(format!("{} / {}", count, max))
+        }
+        None => Gauge::default()
+            .block(Block::default().title("Conntrack table"))
+            .label("not tracking connections"),
+    };
+    f.render_widget(conntrack, rows[0]);
+    interface_table(f, system_data, app, rows[1]);
+
+    let states = match system_data.tcp_states {
+        Some(states) => states,
+        None => {
+            f.render_widget(Paragraph::new("/proc/net/tcp is unavailable"), rows[2]);
+            return;
+        }
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(22), Constraint::Min(0)].as_ref())
+        .split(rows[2]);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let header = Row::new(vec![Cell::from("TCP state"), Cell::from("Sockets")])
+        .style(bold)
+        .bottom_margin(1);
+    let rows = netstat::TCP_STATES
+        .iter()
+        .zip(states)
+        .map(|(name, count)| Row::new(vec![Cell::from(*name), Cell::from(count.to_string())]))
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows)
+        .header(header)
+        .widths(&[Constraint::Length(12), Constraint::Length(8)]);
+    f.render_widget(table, chunks[0]);
+
+    let block = Block::default().title(format!(" TCP sockets ({}) ", app.window.label()));
+    let chart = socket_chart(system_data, app, &states, block);
+    let chart_count = if system_data.dns.is_some() { 3 } else { 2 };
+    let charts = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Ratio(1, chart_count);
+            chart_count as usize
+        ])
+        .split(chunks[1]);
+    f.render_widget(chart, charts[0]);
+
+    let span = chart_span(system_data, app);
+    let points = history::slice(
+        system_data.retransmit_history.points(app.window),
+        span.0,
+        span.1,
+    );
+    let max = points.iter().map(|(_, rate)| *rate).fold(1.0, f64::max);
+    let mut title = format!(" TCP retransmits/s ({})", app.window.label());
+    if let Some(rate) = system_data.retransmit_rate {
+        title.push_str(&format!(" now {:.1}", rate));
+    }
+    if let Some(share) = system_data.retransmit_share {
+        title.push_str(&format!(", {:.2}% of sent", share));
+    }
+    title.push(' ');
+    let datasets = vec![Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Magenta))
+        .data(points)];
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled("0", bold),
+                    Span::styled(format!("{:.0}", max), bold),
+                ])
+                .bounds([0.0, max]),
+        );
+    f.render_widget(chart, charts[1]);
+
+    if let Some(dns) = &system_data.dns {
+        dns_chart(f, dns, app, span, charts[2]);
+    }
+}
+
+// Rates, errors and drops per interface under a row for all of them, the
+// highlighted one is what the network panel charts
+fn interface_table<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let rate = |history: &History| {
+        format!(
+            "{}/s",
+            columns::format_bytes(history.latest().unwrap_or(0.0), app.units)
+        )
+    };
+    let pair = |(received, transmitted): (u64, u64)| format!("{} / {}", received, transmitted);
+    let highlight = Style::default().add_modifier(Modifier::REVERSED);
+
+    let [received, transmitted] = &system_data.network_history;
+    let mut rows = vec![Row::new(vec![
+        Cell::from("All"),
+        Cell::from(rate(received)),
+        Cell::from(rate(transmitted)),
+    ])
+    .style(if app.interface.is_none() {
+        highlight
+    } else {
+        Style::default()
+    })];
+    for interface in &system_data.interfaces {
+        let mut style = Style::default();
+        if interface.errors.0 + interface.errors.1 > 0 {
+            style = style.fg(Color::Red);
+        }
+        if app.interface.as_ref() == Some(&interface.name) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        rows.push(
+            Row::new(vec![
+                Cell::from(interface.name.clone()),
+                Cell::from(rate(&interface.received)),
+                Cell::from(rate(&interface.transmitted)),
+                Cell::from(pair(interface.errors)),
+                Cell::from(pair(interface.drops)),
+            ])
+            .style(style),
+        );
+    }
+
+    let header = Row::new(vec![
+        Cell::from("Interface"),
+        Cell::from("In"),
+        Cell::from("Out"),
+        Cell::from("Errors in/out"),
+        Cell::from("Drops in/out"),
+    ])
+    .style(Style::default().add_modifier(Modifier::BOLD))
+    .bottom_margin(1);
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().title("Interfaces (Up/Down to chart one)"))
+        .widths(&[
+            Constraint::Length(16),
+            Constraint::Length(12),
+            Constraint::Length(12),
+            Constraint::Length(16),
+            Constraint::Length(16),
+        ]);
+    f.render_widget(table, area);
+}
+
+// Lookup latency, with failed lookups marked along the top
+fn dns_chart<B: Backend>(
+    f: &mut Frame<B>,
+    dns: &dns::DnsHealth,
+    app: &App,
+    span: (f64, f64),
+    area: Rect,
+) {
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let latency = history::slice(dns.latency.points(app.window), span.0, span.1);
+    let max = latency.iter().map(|(_, ms)| *ms).fold(1.0, f64::max);
+    let failed = history::slice(dns.failed.points(app.window), span.0, span.1)
+        .iter()
+        .map(|(time, _)| (*time, max))
+        .collect::<Vec<(f64, f64)>>();
+
+    let mut title = format!(" DNS {} ({})", dns.name, app.window.label());
+    match &dns.last {
+        Some(Ok(ms)) => title.push_str(&format!(" now {:.0} ms", ms)),
+        Some(Err(err)) => title.push_str(&format!(" failing: {}", err)),
+        None => title.push_str(" waiting for the first lookup"),
+    }
+    if dns.failures > 0 {
+        title.push_str(&format!(", {} of {} failed", dns.failures, dns.lookups));
+    }
+    title.push(' ');
+
+    let datasets = vec![
+        Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(Color::Cyan))
+            .data(latency),
+        Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .graph_type(GraphType::Scatter)
+            .style(Style::default().fg(Color::Red))
+            .data(&failed),
+    ];
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled("0ms", bold),
+                    Span::styled(format!("{:.0}ms", max), bold),
+                ])
+                .bounds([0.0, max]),
+        );
+    f.render_widget(chart, area);
+}
+
+// Usage, frequency and temperature stacked over the same time span. Usage
+// staying up while the frequency drops with the temperature at its limit is
+// thermal throttling.
+fn throttling_popup<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let area = centered_rect(80, 80, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" CPU throttling ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+                Constraint::Ratio(1, 3),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    let span = chart_span(system_data, app);
+    let empty = History::default();
+    let usage_history = system_data
+        .cpus
+        .first()
+        .map(|cpu| &cpu.history)
+        .unwrap_or(&empty);
+    let usage = history::slice(usage_history.points(app.window), span.0, span.1);
+    let frequency = history::slice(
+        system_data.frequency_history.points(app.window),
+        span.0,
+        span.1,
+    );
+    let temperature = history::slice(
+        system_data.package_temperature_history.points(app.window),
+        span.0,
+        span.1,
+    );
+    let max_frequency = frequency.iter().map(|(_, mhz)| *mhz).fold(1.0, f64::max);
+    let max_temperature = temperature.iter().map(|(_, c)| *c).fold(100.0, f64::max);
+
+    let latest = |history: &History, unit: &str| {
+        history
+            .latest()
+            .map(|value| format!(" now {:.0}{}", value, unit))
+            .unwrap_or_else(|| " unavailable".to_string())
+    };
+    let charts = [
+        (
+            format!(" Usage{} ", latest(usage_history, "%")),
+            usage,
+            Color::Green,
+            100.0,
+            "%",
+        ),
+        (
+            format!(
+                " Frequency{} ",
+                latest(&system_data.frequency_history, " MHz")
+            ),
+            frequency,
+            Color::Cyan,
+            max_frequency,
+            " MHz",
+        ),
+        (
+            format!(
+                " Package temperature{} ",
+                latest(&system_data.package_temperature_history, "°C")
+            ),
+            temperature,
+            Color::Red,
+            max_temperature,
+            "°C",
+        ),
+    ];
+    // The same label width on every chart keeps their time axes lined up
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let label = |value: f64, unit: &str| {
+        Span::styled(format!("{:>8}", format!("{:.0}{}", value, unit)), bold)
+    };
+    for ((title, points, color, max, unit), area) in charts.into_iter().zip(chunks.iter()) {
+        let datasets = vec![Dataset::default()
+            .marker(symbols::Marker::Braille)
+            .graph_type(GraphType::Line)
+            .style(Style::default().fg(color))
+            .data(points)];
+        let chart = Chart::new(datasets)
+            .block(Block::default().title(title))
+            .x_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .labels(time_labels(span))
+                    .bounds([span.0, span.1]),
+            )
+            .y_axis(
+                Axis::default()
+                    .style(Style::default().fg(Color::Gray))
+                    .labels(vec![label(0.0, unit), label(max, unit)])
+                    .bounds([0.0, max]),
+            );
+        f.render_widget(chart, *area);
+    }
+}
+
+fn failed_units_popup<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, area: Rect) {
+    let area = centered_rect(60, 50, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Failed units ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    failed_units_table(f, system_data, inner);
+}
+
+fn failed_units_table<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, area: Rect) {
+    let units = match &system_data.failed_units {
+        Some(units) if !units.is_empty() => units,
+        Some(_) => {
+            f.render_widget(Paragraph::new("No failed units"), area);
+            return;
+        }
+        None if systemd::is_running() => {
+            f.render_widget(Paragraph::new("Waiting for systemctl"), area);
+            return;
+        }
+        None => {
+            f.render_widget(Paragraph::new("Not running under systemd"), area);
+            return;
+        }
+    };
+
+    let header_cells = ["Unit", "State", "Description"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    let rows = units
+        .iter()
+        .map(|unit| {
+            Row::new(vec![
+                Cell::from(unit.name.clone()),
+                Cell::from(unit.sub.clone()),
+                Cell::from(unit.description.clone()),
+            ])
+            .style(Style::default().fg(Color::Red))
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Percentage(35),
+        Constraint::Length(12),
+        Constraint::Percentage(50),
+    ]);
+    f.render_widget(table, area);
+}
+
+// Inhibitors are listed with the process holding them, since what keeps a
+// machine awake is often a busy process
+fn sleep_popup<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, area: Rect) {
+    let area = centered_rect(70, 50, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Sleep ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)].as_ref())
+        .split(inner);
+
+    let backlight = match &system_data.backlight {
+        Some(backlight) => format!("Backlight: {:.0}% ({})", backlight.percent, backlight.name),
+        None => "Backlight: none".to_string(),
+    };
+    f.render_widget(Paragraph::new(backlight), chunks[0]);
+
+    let inhibitors = match &system_data.inhibitors {
+        Some(inhibitors) if !inhibitors.is_empty() => inhibitors,
+        Some(_) => {
+            f.render_widget(Paragraph::new("Nothing is inhibiting sleep"), chunks[1]);
+            return;
+        }
+        None if systemd::is_running() => {
+            f.render_widget(Paragraph::new("Waiting for logind"), chunks[1]);
+            return;
+        }
+        None => {
+            f.render_widget(Paragraph::new("Not running under systemd"), chunks[1]);
+            return;
+        }
+    };
+
+    let header_cells = ["Who", "What", "Mode", "Process", "CPU", "Why"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    let rows = inhibitors
+        .iter()
+        .map(|inhibitor| {
+            let process = system_data
+                .processes
+                .iter()
+                .find(|process| process.pid == inhibitor.pid);
+            let style = if inhibitor.blocks_sleep() {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(inhibitor.who.clone()),
+                Cell::from(inhibitor.what.clone()),
+                Cell::from(inhibitor.mode.clone()),
+                Cell::from(match process {
+                    Some(process) => format!("{} {}", inhibitor.pid, process.name),
+                    None => inhibitor.pid.to_string(),
+                }),
+                Cell::from(process.map_or(String::new(), |process| {
+                    format!("{:.1}%", process.cpu_usage)
+                })),
+                Cell::from(inhibitor.why.clone()),
+            ])
+            .style(style)
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Percentage(15),
+        Constraint::Percentage(20),
+        Constraint::Length(6),
+        Constraint::Percentage(20),
+        Constraint::Length(7),
+        Constraint::Percentage(35),
+    ]);
+    f.render_widget(table, chunks[1]);
+}
+
+fn tasks_popup<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let area = centered_rect(60, 50, area);
+    f.render_widget(Clear, area);
+
+    let mut title = vec![Span::raw(" Tasks ")];
+    for (name, used, max) in system_data
+        .tasks
+        .map(|tasks| tasks.limits())
+        .unwrap_or_default()
+    {
+        let style = if max > 0 && used as f64 / max as f64 >= PID_THRESHOLD {
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        title.push(Span::styled(format!(" {} {}/{} ", name, used, max), style));
+    }
+    let block = Block::default()
+        .title(Spans::from(title))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)].as_ref())
+        .split(inner);
+
+    let span = chart_span(system_data, app);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let latest = |history: &History| history.latest().unwrap_or(0.0);
+
+    let mut max: f64 = 1.0;
+    let mut datasets = Vec::new();
+    for (history, name, color) in [
+        (&system_data.task_history[0], "processes", Color::Cyan),
+        (&system_data.task_history[1], "threads", Color::Yellow),
+    ] {
+        let points = history::slice(history.points(app.window), span.0, span.1);
+        max = points.iter().map(|(_, count)| *count).fold(max, f64::max);
+        datasets.push(
+            Dataset::default()
+                .name(format!("{} {:.0}", name, latest(history)))
+                .marker(symbols::Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(color))
+                .data(points),
+        );
+    }
+    let counts = Chart::new(datasets)
+        .block(Block::default().title(format!(" Count ({}) ", app.window.label())))
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 1)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled(format!("{:>6}", 0), bold),
+                    Span::styled(format!("{:>6.0}", max), bold),
+                ])
+                .bounds([0.0, max]),
+        );
+    f.render_widget(counts, chunks[0]);
+
+    let forks = history::slice(system_data.fork_history.points(app.window), span.0, span.1);
+    let max = forks.iter().map(|(_, rate)| *rate).fold(1.0, f64::max);
+    let datasets = vec![Dataset::default()
+        .marker(symbols::Marker::Braille)
+        .graph_type(GraphType::Line)
+        .style(Style::default().fg(Color::Magenta))
+        .data(forks)];
+    let rate = Chart::new(datasets)
+        .block(Block::default().title(format!(
+            " Forks per second, now {:.0} ",
+            latest(&system_data.fork_history)
+        )))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled(format!("{:>6}", 0), bold),
+                    Span::styled(format!("{:>6.0}", max), bold),
+                ])
+                .bounds([0.0, max]),
+        );
+    f.render_widget(rate, chunks[1]);
+}
+
+fn sensor_popup<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let area = centered_rect(60, 60, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Sensors ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    sensor_details(f, app, inner);
+}
+
+fn sensor_details<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    app.refresh_sensors();
+    if app.sensors.is_empty() {
+        f.render_widget(
+            Paragraph::new("No temperature sensors found under /sys/class/hwmon"),
+            area,
+        );
+        return;
+    }
+
+    let header_cells = ["Sensor", "Temp", "Warn", "Crit", "Source"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let celsius = |value: Option<f64>| {
+        value
+            .map(|value| format!("{:.1}°C", value))
+            .unwrap_or_default()
+    };
+    let rows = app
+        .sensors
+        .iter()
+        .map(|sensor| {
+            let style = if sensor.critical.is_some_and(|crit| sensor.celsius >= crit) {
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+            } else if sensor.warning.is_some_and(|warn| sensor.celsius >= warn) {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(sensor.name.clone()),
+                Cell::from(celsius(Some(sensor.celsius))),
+                Cell::from(celsius(sensor.warning)),
+                Cell::from(celsius(sensor.critical)),
+                Cell::from(sensor.id.clone()),
+            ])
+            .style(style)
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Percentage(30),
+        Constraint::Percentage(15),
+        Constraint::Percentage(15),
+        Constraint::Percentage(15),
+        Constraint::Percentage(25),
+    ]);
+    f.render_widget(table, area);
+}
+
+fn anomaly_popup<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &mut App,
+    area: Rect,
+) {
+    app.anomalies.ask();
+    let area = centered_rect(70, 60, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(degraded_title(format!(
+            " Anomalies ({}) ",
+            system_data.anomalies.len()
+        )))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if system_data.anomalies.is_empty() {
+        // Other users' executables can only be checked as root
+        let text = if privilege::sees_all_processes() {
+            "Nothing flagged"
+        } else {
+            "Nothing flagged among your own processes, run as root to check all of them"
+        };
+        f.render_widget(Paragraph::new(text), inner);
+        return;
+    }
+
+    let header_cells = ["PID", "Name", "User", "Why", "Executable"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = system_data
+        .anomalies
+        .iter()
+        .map(|anomaly| {
+            let reasons = anomaly
+                .reasons
+                .iter()
+                .map(|reason| reason.label())
+                .collect::<Vec<&str>>();
+            Row::new(vec![
+                Cell::from(anomaly.pid.to_string()),
+                Cell::from(anomaly.name.clone()),
+                Cell::from(anomaly.user.clone()),
+                Cell::from(reasons.join(", ")),
+                Cell::from(anomaly.executable.clone().unwrap_or_default()),
+            ])
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Length(8),
+        Constraint::Percentage(20),
+        Constraint::Length(10),
+        Constraint::Percentage(35),
+        Constraint::Percentage(35),
+    ]);
+    f.render_widget(table, inner);
+}
+
+fn capture_popup<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &mut App,
+    area: Rect,
+) {
+    app.captures.ask();
+    let area = centered_rect(60, 40, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(degraded_title(" Microphone and camera ".to_string()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if system_data.captures.is_empty() {
+        let text = if privilege::sees_all_processes() {
+            "Nothing is recording"
+        } else {
+            "None of your processes are recording, run as root to check all of them"
+        };
+        f.render_widget(Paragraph::new(text), inner);
+        return;
+    }
+
+    let header_cells = ["PID", "Name", "Device", "Source"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+    let rows = system_data
+        .captures
+        .iter()
+        .map(|capture| {
+            Row::new(vec![
+                Cell::from(capture.pid.to_string()),
+                Cell::from(capture.name.clone()),
+                Cell::from(capture.device.label()),
+                Cell::from(capture.source.clone()),
+            ])
+            .style(Style::default().fg(Color::Yellow))
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows).header(header).widths(&[
+        Constraint::Length(8),
+        Constraint::Percentage(25),
+        Constraint::Length(11),
+        Constraint::Percentage(55),
+    ]);
+    f.render_widget(table, inner);
+}
+
+fn sysctl_popup<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let area = centered_rect(70, 70, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Sysctls (read-only) ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)].as_ref())
+        .split(inner);
+
+    let search = Paragraph::new(Spans::from(vec![
+        Span::styled("Search: ", Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw(app.sysctl_query.clone()),
+        Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+    ]));
+    f.render_widget(search, chunks[0]);
+
+    let header_cells = ["Name", "Value", "Description"]
+        .iter()
+        .map(|h| Cell::from(*h).style(Style::default().add_modifier(Modifier::BOLD)));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = app
+        .filtered_sysctls()
+        .into_iter()
+        .map(|s| {
+            Row::new(vec![
+                Cell::from(s.name),
+                Cell::from(s.value.clone()),
+                Cell::from(s.description),
+            ])
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows)
+        .header(header)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[
+            Constraint::Percentage(35),
+            Constraint::Percentage(20),
+            Constraint::Percentage(45),
+        ]);
+    f.render_stateful_widget(table, chunks[1], &mut app.sysctl_state);
+}
+
+// Names the likely OOM victim once memory is running short
+fn oom_alert(system_data: &SystemData) -> Option<String> {
+    let pressure = system_data.memory_pressure.unwrap_or(0.0);
+    // A total of 0, as before the first sample, would give NaN
+    let used = if system_data.total_memory > 0.0 {
+        system_data.used_memory / system_data.total_memory
+    } else {
+        0.0
+    };
+    if pressure < MEMORY_PRESSURE_THRESHOLD && used < MEMORY_USED_THRESHOLD {
+        return None;
+    }
+    let victim = system_data
+        .processes
+        .iter()
+        .filter_map(|p| p.oom.map(|oom| (p, oom.score)))
+        .max_by_key(|(_, score)| *score);
+    Some(match victim {
+        Some((process, score)) => format!(
+            "Memory pressure high, OOM killer would pick {} ({}, score {})",
+            process.name, process.pid, score
+        ),
+        None => "Memory pressure high".to_string(),
+    })
+}
+
+// Filesystems projected to fill up within the configured horizon
+fn filling_filesystems<'a>(
+    system_data: &'a SystemData,
+) -> impl Iterator<Item = (&'a storage::Filesystem, f64)> {
+    let horizon = system_data.full_within_days;
+    system_data
+        .filesystems
+        .iter()
+        .filter_map(move |filesystem| {
+            let days = filesystem.days_until_full()?;
+            (days < horizon).then_some((filesystem, days))
+        })
+}
+
+fn format_days(days: f64) -> String {
+    if days < 1.0 / 24.0 {
+        "under an hour".to_string()
+    } else if days < 1.0 {
+        format!("{:.0}h", days * 24.0)
+    } else {
+        format!("{:.1} days", days)
+    }
+}
+
+fn header_block<'a>(system_data: &SystemData, app: &App) -> Paragraph<'a> {
+    let boot_time = Local
+        .timestamp_opt(system_data.boot_time as i64, 0)
+        .single()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let mut spans = vec![
+        Span::raw(" Booted "),
+        Span::styled(boot_time, Style::default().add_modifier(Modifier::BOLD)),
+        Span::raw("  Up "),
+        Span::styled(
+            format_uptime(system_data.uptime),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+    ];
+
+    if let Some(virtualization) = &system_data.virtualization {
+        spans.push(Span::raw("  Guest "));
+        spans.push(Span::styled(
+            virtualization.clone(),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some(container) = &system_data.container {
+        spans.push(Span::raw("  Container "));
+        spans.push(Span::styled(
+            container.clone(),
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ));
+
+        let mut limits = Vec::new();
+        if let Some(cores) = system_data.cgroup_limits.cpu_limit {
+            limits.push(format!("{:.2} CPUs", cores));
+        }
+        if let Some(memory) = system_data.cgroup_limits.memory_limit {
+            limits.push(memory_to_gb(&(memory as f64), app.units));
+        }
+        if !limits.is_empty() {
+            spans.push(Span::raw(format!(" (limit {})", limits.join(", "))));
+        }
+    }
+
+    if let Some(profile) = &system_data.power_profile {
+        spans.push(Span::raw("  Power "));
+        spans.push(Span::styled(
+            profile.active.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some(backlight) = &system_data.backlight {
+        spans.push(Span::raw(format!("  Backlight {:.0}%", backlight.percent)));
+    }
+
+    if let Some(entropy) = system_data.entropy {
+        spans.push(Span::raw("  Entropy "));
+        spans.push(Span::styled(
+            entropy.to_string(),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+    }
+    // A skewed clock shows up as TLS and authentication failures that don't
+    // look like they have anything to do with time
+    match system_data.clock {
+        Some(clock) if clock.synchronized => {
+            spans.push(Span::raw("  Clock offset "));
+            spans.push(Span::styled(
+                format_offset(clock.offset),
+                Style::default().add_modifier(Modifier::BOLD),
+            ));
+        }
+        Some(_) => {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(
+                "Clock not synchronized",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+        None => {}
+    }
+    if !system_data.random_ready {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            "getrandom blocking",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if system_data.uptime < RECENT_BOOT_THRESHOLD {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            "Recent reboot",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some(alert) = oom_alert(system_data) {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            alert,
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if let Some((count, max)) = system_data.conntrack {
+        if max > 0 && count as f64 / max as f64 >= CONNTRACK_THRESHOLD {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(
+                format!("Conntrack table {}/{}", count, max),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+
+    if let Some((name, used, max)) = system_data.tasks.and_then(|tasks| tasks.tightest()) {
+        if max > 0 && used as f64 / max as f64 >= PID_THRESHOLD {
+            spans.push(Span::raw("  "));
+            spans.push(Span::styled(
+                format!("{} {}/{}", name, used, max),
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
+        }
+    }
+
+    let failed_units = system_data.failed_units.as_ref().map_or(0, Vec::len);
+    if failed_units > 0 {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            format!(
+                "{} failed unit{}",
+                failed_units,
+                if failed_units == 1 { "" } else { "s" }
+            ),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    let blocking_sleep = system_data
+        .inhibitors
+        .iter()
+        .flatten()
+        .filter(|inhibitor| inhibitor.blocks_sleep())
+        .count();
+    if blocking_sleep > 0 {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            format!("{} blocking sleep", blocking_sleep),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    for (filesystem, days) in filling_filesystems(system_data) {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            format!("{} full in {}", filesystem.mount, format_days(days)),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    let firing = system_data.alerts.firing();
+    if !firing.is_empty() {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            format!("Alert: {}", firing.join(", ")),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    if app.is_paused() {
+        spans.push(Span::raw("  "));
+        spans.push(Span::styled(
+            " PAUSED ",
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ));
+        let (_, end) = chart_span(system_data, app);
+        spans.push(Span::raw(format!(" viewing up to {}", format_time(end))));
+    }
+
+    Paragraph::new(Spans::from(spans))
+}
+
+fn format_offset(seconds: f64) -> String {
+    if seconds.abs() < 0.001 {
+        format!("{:.0}µs", seconds * 1e6)
+    } else if seconds.abs() < 1.0 {
+        format!("{:.1}ms", seconds * 1e3)
+    } else {
+        format!("{:.2}s", seconds)
+    }
+}
+
+fn time_labels<'a>((start, end): (f64, f64)) -> Vec<Span<'a>> {
+    vec![
+        Span::styled(
+            format_time(start),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::styled(
+            format_time(end),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+    ]
+}
+
+fn ram_block<'a, B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &'a SystemData,
+    app: &mut App,
+    area: Rect,
+) -> Chart<'a> {
+    let block = Block::default()
+        .title(format!(" Memory Usage ({}) ", app.window.label()))
+        .borders(Borders::ALL)
+        .border_style(app.border_style(ChartKind::Memory));
+
+    let span = chart_span(system_data, app);
+    let points = history::slice(
+        system_data.memory_history.points(app.window),
+        span.0,
+        span.1,
+    );
+
+    // tmpfs counts towards used memory, drawn under it to show how much
+    let tmpfs_points = history::slice(system_data.tmpfs_history.points(app.window), span.0, span.1);
+
+    // VRAM is drawn on the same scale, running out of it fails jobs just the same
+    let gpu = &system_data.gpu;
+    let vram_points = history::slice(gpu.vram_history.points(app.window), span.0, span.1);
+    let ceiling = system_data
+        .total_memory
+        .max(gpu.vram_total.unwrap_or(0) as f64);
+
+    let mut datasets = vec![
+        Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .style(Style::default().fg(Color::Cyan))
+            .data(points),
+        Dataset::default()
+            .marker(symbols::Marker::Dot)
+            .style(Style::default().fg(Color::Magenta))
+            .data(tmpfs_points),
+    ];
+    if !vram_points.is_empty() {
+        datasets.push(
+            Dataset::default()
+                .marker(symbols::Marker::Dot)
+                .style(Style::default().fg(Color::Yellow))
+                .data(vram_points),
+        );
+    }
+
+    let c: Chart<'a> = Chart::new(datasets)
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled("0", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled(
+                        memory_to_gb(&ceiling, app.units),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ),
+                ])
+                .bounds([0.0, ceiling / GIB]),
+        )
+        .block(block);
+
+    // add text inside area
+
+    let mut percentage_used = format!(
+        "{:.2}% Used",
+        system_data.used_memory / system_data.total_memory * 100.0
+    );
+    if system_data.tmpfs_used > 0.0 {
+        percentage_used.push_str(&format!(
+            "\n{} in tmpfs",
+            memory_to_gb(&system_data.tmpfs_used, app.units)
+        ));
+    }
+    if gpu.vram_history.latest().is_some() {
+        let used = memory_to_gb(&(gpu.vram_used as f64), app.units);
+        percentage_used.push_str(&match gpu.vram_total {
+            Some(total) => format!(
+                "\nVRAM {} of {}",
+                used,
+                memory_to_gb(&(total as f64), app.units)
+            ),
+            None => format!("\nVRAM {}", used),
+        });
+    }
+    let temp_rect = Rect::new(
+        area.x + 1,
+        area.y + 1,
+        area.width.saturating_sub(2),
+        area.height.saturating_sub(2),
+    );
+    let widget = Paragraph::new(percentage_used).alignment(Alignment::Center);
+    f.render_widget(widget, temp_rect);
+
+    c
+}
+
+// The total is always first, followed by the cores
+fn charted_cpus<'a>(system_data: &'a SystemData, app: &App) -> impl Iterator<Item = &'a CPUData> {
+    let (skip, take) = if app.per_core {
+        (1, usize::MAX)
+    } else {
+        (0, 1)
+    };
+    system_data.cpus.iter().skip(skip).take(take)
+}
+
+fn cpu_block<'a, B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &'a SystemData,
+    app: &mut App,
+    area: Rect,
+) -> Chart<'a> {
+    let title = if app.per_core {
+        format!(" CPU Usage per core ({}) ", app.window.label())
+    } else {
+        format!(" CPU Usage ({}) ", app.window.label())
+    };
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_style(app.border_style(ChartKind::Cpu));
+
+    let span = chart_span(system_data, app);
+    let datasets = charted_cpus(system_data, app)
+        .map(|item| {
+            let current = item.history.latest().unwrap_or(0.0);
+            Dataset::default()
+                .name(format!("{} {:.1}%", item.name, current))
+                .marker(symbols::Marker::Dot)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(item.color))
+                .data(history::slice(
+                    item.history.points(app.window),
+                    span.0,
+                    span.1,
+                ))
+        })
+        .collect();
+
+    let c: Chart<'a> = Chart::new(datasets)
+        .hidden_legend_constraints((Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)))
+        .x_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(time_labels(span))
+                .bounds([span.0, span.1]),
+        )
+        .y_axis(
+            Axis::default()
+                .style(Style::default().fg(Color::Gray))
+                .labels(vec![
+                    Span::styled("0%", Style::default().add_modifier(Modifier::BOLD)),
+                    Span::styled("100%", Style::default().add_modifier(Modifier::BOLD)),
+                ])
+                .bounds([0.0, 100.0]),
+        )
+        .block(block);
+
+    // add text inside area
+
+    let mut percentage_used = vec![Span::raw(format!("{:.2}%", system_data.cpu_usage))];
+
+    // Steal is emphasised on guests since it means the host is oversubscribed
+    if system_data.virtualization.is_some() {
+        let steal_color = if system_data.steal_usage >= STEAL_WARNING_THRESHOLD {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+        percentage_used.push(Span::raw("  "));
+        percentage_used.push(Span::styled(
+            format!("steal {:.2}%", system_data.steal_usage),
+            Style::default()
+                .fg(steal_color)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+
+    let temp_rect = Rect::new(
+        area.x + 1,
+        area.y + 1,
+        area.width.saturating_sub(2),
+        area.height.saturating_sub(2),
+    );
+    let mut lines = vec![Spans::from(percentage_used)];
+
+    // Per core temperatures, so a core running hotter than the rest stands out
+    let cores = system_data
+        .cpu_sensors
+        .iter()
+        .filter(|sensor| sensors::is_core(sensor))
+        .cloned()
+        .collect();
+    let cores = sensors::apply_config(cores, &app.sensor_config);
+    let hottest = cores
+        .iter()
+        .map(|core| core.celsius)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let mut temperatures = Vec::new();
+    for core in cores.iter() {
+        let mut style = if core.critical.is_some_and(|crit| core.celsius >= crit) {
+            Style::default().fg(Color::Red)
+        } else if core.warning.is_some_and(|warn| core.celsius >= warn) {
+            Style::default().fg(Color::Yellow)
+        } else {
+            Style::default()
+        };
+        if cores.len() > 1 && core.celsius >= hottest {
+            style = style.add_modifier(Modifier::BOLD);
+        }
+        if !temperatures.is_empty() {
+            temperatures.push(Span::raw("  "));
+        }
+        temperatures.push(Span::styled(
+            format!("{} {:.0}°C", core.name, core.celsius),
+            style,
+        ));
+    }
+    if !temperatures.is_empty() {
+        lines.push(Spans::from(temperatures));
+    }
+
+    let widget = Paragraph::new(lines)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(widget, temp_rect);
+
+    c
+}
+
+// A gauge of used space per mounted filesystem, the storage popup has the rest
+fn disks_block<B: Backend>(f: &mut Frame<B>, system_data: &SystemData, app: &App, area: Rect) {
+    let block = Block::default().title(" Disks ").borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let filesystems = &system_data.filesystems;
+    let rows = inner.height as usize;
+    if rows == 0 {
+        return;
+    }
+    // The last row says how many didn't fit instead
+    let shown = if filesystems.len() > rows {
+        rows - 1
+    } else {
+        filesystems.len()
+    };
+    let areas = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![Constraint::Length(1); rows])
+        .split(inner);
+
+    let bytes = |value: u64| columns::format_bytes(value as f64, app.units);
+    for (filesystem, area) in filesystems.iter().zip(areas.iter()).take(shown) {
+        let ratio = if filesystem.total > 0 {
+            filesystem.used() as f64 / filesystem.total as f64
+        } else {
+            0.0
+        };
+        let filling = filesystem
+            .days_until_full()
+            .is_some_and(|days| days < system_data.full_within_days);
+        // Colored like the storage popup, tmpfs is RAM rather than disk
+        let color = if filling || ratio >= FILESYSTEM_THRESHOLD {
+            Color::Red
+        } else if filesystem.kind == "tmpfs" {
+            Color::Magenta
+        } else {
+            Color::Green
+        };
+        let gauge = Gauge::default()
+            .gauge_style(Style::default().fg(color))
+            .ratio(ratio.clamp(0.0, 1.0))
+            .label(format!(
+                "{} {} / {}",
+                filesystem.mount,
+                bytes(filesystem.used()),
+                bytes(filesystem.total)
+            ));
+        f.render_widget(gauge, *area);
+    }
+    if shown < filesystems.len() {
+        let more = Paragraph::new(format!("{} more, d for all", filesystems.len() - shown))
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(more, areas[shown]);
+    }
+}
 
-    let bar = BarChart::default()
-        .block(block)
-        .bar_width(calc_bar_width)
-        .bar_gap(2)
-        .bar_style(Style::default().fg(Color::Yellow))
-        .label_style(Style::default().fg(Color::White))
-        .data(&system_data.packets)
-        .max(max);
-    bar
+struct ProcessRow<'a> {
+    process: &'a ProcessData,
+    // Tree connectors drawn before the name, empty in the flat list
+    prefix: String,
+    // Usage including descendants, when aggregating
+    totals: Option<tree::Totals>,
 }
 
-fn processes_block<'a>(system_data: &'a SystemData) -> Table<'a> {
-    let block = Block::default().title(" Processes ").borders(Borders::ALL);
+fn filtered_processes<'a>(system_data: &'a SystemData, app: &App) -> Vec<&'a ProcessData> {
+    system_data
+        .processes
+        .iter()
+        .filter(|p| match &app.user_filter {
+            Some(user) => &p.user == user,
+            None => true,
+        })
+        .filter(|p| match &app.process_filter {
+            Some(filter) => filter.matches(p),
+            None => true,
+        })
+        .collect()
+}
 
-    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+// The user running the viewer, taken from its own process entry
+fn own_user(system_data: &SystemData) -> Option<String> {
+    let pid = sysinfo::get_current_pid().ok()?.as_u32();
+    system_data
+        .processes
+        .iter()
+        .find(|p| p.pid == pid)
+        .map(|p| p.user.clone())
+}
+
+fn process_rows<'a>(system_data: &'a SystemData, app: &App) -> Vec<ProcessRow<'a>> {
+    let mut processes = filtered_processes(system_data, app);
+    // The averaged column keeps short spikes from reshuffling the table, the
+    // time column brings up slow burners that have run for a long time.
+    // Whichever of the two comes first in the table decides.
+    let sort_column = app
+        .columns
+        .iter()
+        .find(|column| matches!(column, Column::CpuAverage | Column::Time))
+        .copied();
+    let by_average = sort_column == Some(Column::CpuAverage);
+
+    let totals = if app.aggregate {
+        tree::subtree_totals(&processes)
+    } else {
+        HashMap::new()
+    };
+    let cpu = |process: &ProcessData| {
+        let total = totals.get(&process.pid);
+        match (by_average, total) {
+            (true, Some(total)) => total.cpu_average,
+            (false, Some(total)) => total.cpu_usage,
+            (true, None) => process.cpu_average,
+            (false, None) => process.cpu_usage,
+        }
+    };
+    let cpu_time = |process: &ProcessData| match totals.get(&process.pid) {
+        Some(total) => total.cpu_time,
+        None => process.cpu_time.unwrap_or(0.0),
+    };
+    if let Some((column, descending)) = app.sort {
+        // Rows summing their children sort by the sums
+        processes.sort_by(|a, b| {
+            let order = match (totals.get(&a.pid), totals.get(&b.pid), column) {
+                (Some(a), Some(b), Column::Cpu) => a.cpu_usage.total_cmp(&b.cpu_usage),
+                (Some(a), Some(b), Column::CpuAverage) => a.cpu_average.total_cmp(&b.cpu_average),
+                (Some(a), Some(b), Column::Time) => a.cpu_time.total_cmp(&b.cpu_time),
+                (Some(a), Some(b), Column::Mem) => a.memory.cmp(&b.memory),
+                _ => column.compare(a, b),
+            };
+            if descending {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    } else if sort_column == Some(Column::Time) {
+        processes.sort_by(|a, b| cpu_time(b).total_cmp(&cpu_time(a)));
+    } else if by_average || app.aggregate {
+        processes.sort_by(|a, b| cpu(b).partial_cmp(&cpu(a)).unwrap());
+    }
+    if app.group_units {
+        let mut unit_cpu = HashMap::<String, f32>::new();
+        for process in processes.iter() {
+            *unit_cpu.entry(process.unit()).or_default() += cpu(process);
+        }
+        // Stable, so each unit keeps its processes in CPU order
+        processes.sort_by(|a, b| {
+            let (a, b) = (a.unit(), b.unit());
+            unit_cpu[&b]
+                .partial_cmp(&unit_cpu[&a])
+                .unwrap()
+                .then_with(|| a.cmp(&b))
+        });
+    }
+
+    if !app.tree {
+        return processes
+            .into_iter()
+            .map(|process| ProcessRow {
+                process,
+                prefix: String::new(),
+                totals: totals.get(&process.pid).copied(),
+            })
+            .collect();
+    }
+    tree::flatten(&processes, &app.collapsed)
+        .into_iter()
+        .map(|row| ProcessRow {
+            process: processes[row.index],
+            prefix: row.prefix,
+            totals: totals.get(&processes[row.index].pid).copied(),
+        })
+        .collect()
+}
+
+// The unit column is always shown while grouping by it
+fn shown_columns(app: &App) -> Vec<Column> {
+    let mut columns = app.columns.clone();
+    if app.group_units && !columns.contains(&Column::Unit) {
+        columns.insert(0, Column::Unit);
+    }
+    columns
+}
 
-    let header_cells = ["PID", "Process Name", "Usage"]
+fn row_pids(system_data: &SystemData, app: &App) -> Vec<u32> {
+    process_rows(system_data, app)
         .iter()
-        .map(|h| Cell::from(*h).style(Style::default()));
+        .map(|row| row.process.pid)
+        .collect()
+}
+
+fn selected_process<'a>(system_data: &'a SystemData, app: &App) -> Option<&'a ProcessData> {
+    let rows = process_rows(system_data, app);
+    let row = match app.selected_pid {
+        Some(pid) => rows.into_iter().find(|row| row.process.pid == pid),
+        None => rows.into_iter().nth(app.process_state.selected()?),
+    };
+    row.map(|row| row.process)
+}
+
+// Marks panels that only see part of the system without root, usually just
+// this user's processes
+fn degraded_badge<'a>() -> Span<'a> {
+    Span::styled("degraded ", Style::default().fg(Color::Yellow))
+}
+
+fn degraded_title<'a>(title: String) -> Spans<'a> {
+    let mut spans = vec![Span::raw(title)];
+    if !privilege::sees_all_processes() {
+        spans.push(degraded_badge());
+    }
+    Spans::from(spans)
+}
+
+fn processes_block<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &mut App,
+    area: Rect,
+) {
+    // The tree hangs off the name, or the command when the name is hidden
+    let tree_column = [Column::Name, Column::Command]
+        .into_iter()
+        .find(|c| app.columns.contains(c));
+    let columns = shown_columns(app);
+    let process_rows = process_rows(system_data, app);
+    let pids = process_rows
+        .iter()
+        .map(|row| row.process.pid)
+        .collect::<Vec<u32>>();
+    app.follow_selected_process(&pids);
+    let mut values = process_rows
+        .iter()
+        .map(|row| {
+            let aggregated;
+            let process = match &row.totals {
+                Some(totals) => {
+                    aggregated = ProcessData {
+                        cpu_usage: totals.cpu_usage,
+                        cpu_average: totals.cpu_average,
+                        cpu_time: Some(totals.cpu_time),
+                        memory: totals.memory,
+                        ..row.process.clone()
+                    };
+                    &aggregated
+                }
+                None => row.process,
+            };
+            columns
+                .iter()
+                .map(|c| {
+                    if Some(*c) == tree_column {
+                        format!("{}{}", row.prefix, c.value(process, app.units))
+                    } else {
+                        c.value(process, app.units)
+                    }
+                })
+                .collect()
+        })
+        .collect::<Vec<Vec<String>>>();
+
+    // Each unit is only named on its first row
+    if let Some(unit_index) = columns.iter().position(|c| *c == Column::Unit) {
+        if app.group_units {
+            let mut previous = None;
+            for row in values.iter_mut() {
+                let unit = std::mem::take(&mut row[unit_index]);
+                if previous.as_ref() != Some(&unit) {
+                    row[unit_index] = unit.clone();
+                }
+                previous = Some(unit);
+            }
+        }
+    }
+
+    // Inside the borders and after the highlight symbol
+    let available = area.width.saturating_sub(2 + ">> ".len() as u16);
+    let widths = columns::column_widths(&columns, &values, available);
+
+    // Stop scrolling once the longest truncated cell is fully shown
+    let overflow = values
+        .iter()
+        .flat_map(|row| row.iter().zip(widths.iter()).zip(columns.iter()))
+        .filter(|(_, column)| column.is_flexible())
+        .map(|((value, width), _)| (value.chars().count() as u16).saturating_sub(*width))
+        .max()
+        .unwrap_or(0);
+    app.process_scroll = app.process_scroll.min(overflow);
+
+    let mut title = " Processes ".to_string();
+    if app.tree {
+        title.push_str("(tree) ");
+    }
+    if app.aggregate {
+        title.push_str("(with children) ");
+    }
+    if app.group_units {
+        title.push_str("(by unit) ");
+    }
+    if let Some(user) = &app.user_filter {
+        title.push_str(&format!("(user {}) ", user));
+    }
+    if !app.marked.is_empty() {
+        title.push_str(&format!("({} marked) ", app.marked.len()));
+    }
+    if app.process_scroll > 0 {
+        title.push_str(&format!("(scrolled {}) ", app.process_scroll));
+    }
+    if app.is_frozen() {
+        title.push_str("(frozen) ");
+    }
+
+    let mut title = vec![Span::raw(title)];
+    // Other users' I/O is only readable as root
+    if !privilege::sees_all_processes() {
+        title.push(degraded_badge());
+    }
+    if app.editing_filter || !app.process_query.is_empty() {
+        title.push(Span::styled(
+            format!("/{}", app.process_query),
+            Style::default().add_modifier(Modifier::BOLD),
+        ));
+        if app.editing_filter {
+            title.push(Span::styled(
+                "_",
+                Style::default().add_modifier(Modifier::SLOW_BLINK),
+            ));
+        }
+        title.push(Span::raw(" "));
+    }
+    if let Some(err) = &app.filter_error {
+        // Regex errors point at the pattern over several lines, the last holds the message
+        let message = err.lines().last().unwrap_or_default();
+        title.push(Span::styled(
+            format!("{} ", message),
+            Style::default().fg(Color::Red),
+        ));
+    }
+    let block = Block::default()
+        .title(Spans::from(title))
+        .borders(Borders::ALL);
+
+    let selected_style = Style::default().add_modifier(Modifier::REVERSED);
+
+    let header_cells = columns.iter().map(|c| match app.sort {
+        Some((column, descending)) if column == *c => {
+            let arrow = if descending { "▼" } else { "▲" };
+            Cell::from(format!("{}{}", c.title(), arrow))
+                .style(Style::default().add_modifier(Modifier::BOLD))
+        }
+        _ => Cell::from(c.title()).style(Style::default()),
+    });
     let header = Row::new(header_cells).height(1).bottom_margin(1);
 
-    let rows = system_data.processes.iter().map(|item| {
-        let height = item
+    // Inside the borders and under the header, each row taking two lines
+    app.process_page = (area.height.saturating_sub(4) / 2).max(1) as usize;
+
+    let scroll = app.process_scroll as usize;
+    let styles = process_rows.iter().map(|row| {
+        let style = app
+            .highlights
             .iter()
-            .map(|content| content.chars().filter(|c| *c == '\n').count())
-            .max()
-            .unwrap_or(0)
-            + 1;
-        let cells = item.iter().map(|c| Cell::from(c.clone()));
-        Row::new(cells).height(height as u16).bottom_margin(1)
+            .find(|highlight| highlight.pattern.is_match(&row.process.name))
+            .map(|highlight| highlight.style())
+            .unwrap_or_default();
+        if app.marked.contains(&row.process.pid) {
+            style.bg(Color::DarkGray).add_modifier(Modifier::BOLD)
+        } else {
+            style
+        }
     });
 
+    let rows = values.into_iter().zip(styles).map(|(cells, style)| {
+        let cells = cells
+            .into_iter()
+            .zip(widths.iter())
+            .zip(columns.iter())
+            .map(|((value, width), column)| {
+                if !column.is_flexible() {
+                    return Cell::from(value);
+                }
+                // Each cell only scrolls as far as its own end
+                let skip = scroll.min(value.chars().count().saturating_sub(*width as usize));
+                Cell::from(value.chars().skip(skip).collect::<String>())
+            })
+            .collect::<Vec<Cell>>();
+        Row::new(cells).style(style).height(1).bottom_margin(1)
+    });
+
+    let constraints = widths
+        .iter()
+        .map(|width| Constraint::Length(*width))
+        .collect::<Vec<Constraint>>();
+
     let t = Table::new(rows)
         .header(header)
         .block(block)
         .highlight_style(selected_style)
         .highlight_symbol(">> ")
+        .widths(&constraints);
+    f.render_stateful_widget(t, area, &mut app.process_state);
+}
+
+fn confirm_popup<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let pending = match &app.pending {
+        Some(pending) => pending,
+        None => return,
+    };
+    let area = centered_rect(50, 60, area);
+    f.render_widget(Clear, area);
+
+    let mut title = format!(
+        " {} {} processes? ",
+        pending.action.describe(),
+        pending.processes.len()
+    );
+    let mut help = "confirm: y | cancel: n/esc".to_string();
+    if let Some(escalator) = pending.escalator {
+        title = format!(" Retry with {}:{}", escalator, title);
+        help = format!(
+            "confirm: y ({} may ask for a password) | cancel: n/esc",
+            escalator
+        );
+    }
+    let block = Block::default()
+        .title(title)
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(inner);
+
+    let rows = pending
+        .processes
+        .iter()
+        .map(|(pid, name)| Row::new(vec![Cell::from(pid.to_string()), Cell::from(name.clone())]))
+        .collect::<Vec<Row>>();
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(chunks[0].width.saturating_sub(9)),
+    ];
+    let table = Table::new(rows).widths(&widths);
+    f.render_widget(table, chunks[0]);
+
+    let help = Paragraph::new(Span::styled(
+        help,
+        Style::default().add_modifier(Modifier::BOLD),
+    ));
+    f.render_widget(help, chunks[1]);
+}
+
+fn maintenance_popup<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let task = match app.pending_task {
+        Some(task) => task,
+        None => return,
+    };
+    let area = centered_rect(60, 50, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" {}? ", task.describe()))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(Color::Red));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(inner);
+
+    let audit = match maintenance::audit_path() {
+        Some(path) => format!("This is recorded in {}.", path.display()),
+        None => "There is no state directory to record this in.".to_string(),
+    };
+    let text = vec![
+        Spans::from(task.warning()),
+        Spans::from(""),
+        Spans::from(audit),
+    ];
+    f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), chunks[0]);
+
+    let help = Paragraph::new(Span::styled(
+        "confirm: y | cancel: n/esc",
+        Style::default().add_modifier(Modifier::BOLD),
+    ));
+    f.render_widget(help, chunks[1]);
+}
+
+fn user_popup<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let area = centered_rect(40, 60, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Show processes of ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+
+    let rows = std::iter::once("All users".to_string())
+        .chain(app.users.iter().cloned())
+        .map(|user| Row::new(vec![Cell::from(user)]))
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows)
+        .block(block)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[Constraint::Percentage(100)]);
+    f.render_stateful_widget(table, area, &mut app.user_state);
+}
+
+fn settings_popup<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let area = centered_rect(40, 50, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Settings ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)].as_ref())
+        .split(inner);
+
+    let help = Paragraph::new("change: left/right | save to config: s | close: esc");
+    f.render_widget(help, chunks[0]);
+
+    let rows = SETTINGS
+        .iter()
+        .map(|setting| {
+            let (name, value) = match setting {
+                Setting::Refresh => (
+                    "Refresh".to_string(),
+                    format_interval(app.refresh_interval.load(Ordering::Relaxed)),
+                ),
+                Setting::Units => ("Units".to_string(), app.units.name().to_string()),
+                Setting::Theme => ("Theme".to_string(), app.theme.name().to_string()),
+                Setting::Panel(panel) => {
+                    let shown = if app.panels.contains(panel) {
+                        "shown"
+                    } else {
+                        "hidden"
+                    };
+                    (format!("Panel {}", panel.name()), shown.to_string())
+                }
+            };
+            Row::new(vec![Cell::from(name), Cell::from(value)])
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[Constraint::Length(16), Constraint::Min(0)]);
+    f.render_stateful_widget(table, chunks[1], &mut app.settings_state);
+}
+
+fn column_popup<B: Backend>(f: &mut Frame<B>, app: &mut App, area: Rect) {
+    let area = centered_rect(40, 60, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Columns ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(2), Constraint::Min(0)].as_ref())
+        .split(inner);
+
+    let help = Paragraph::new("toggle: space | move: +/- | close: esc");
+    f.render_widget(help, chunks[0]);
+
+    let rows = app
+        .column_choices()
+        .into_iter()
+        .map(|(column, shown)| {
+            let mark = if shown { "[x]" } else { "[ ]" };
+            Row::new(vec![Cell::from(mark), Cell::from(column.title())])
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows)
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .widths(&[Constraint::Length(4), Constraint::Min(0)]);
+    f.render_stateful_widget(table, chunks[1], &mut app.column_state);
+}
+
+fn process_popup<B: Backend>(
+    f: &mut Frame<B>,
+    system_data: &SystemData,
+    app: &mut App,
+    pid: u32,
+    area: Rect,
+) {
+    let interval = Duration::from_millis(app.refresh_interval.load(Ordering::Relaxed));
+    app.process_details.refresh(pid, interval);
+    let area = centered_rect(70, 70, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(format!(" Process {} ", pid))
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(
+            [
+                Constraint::Length(14),
+                Constraint::Percentage(50),
+                Constraint::Min(0),
+            ]
+            .as_ref(),
+        )
+        .split(inner);
+
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let bytes = |value: u64| columns::format_bytes(value as f64, app.units);
+    // Totals, followed by the rate once it has been read twice. Major faults
+    // wait on disk, so any at all is worth noticing.
+    let counters = |names: [&str; 2], offset: usize| -> Vec<Span> {
+        let details = &app.process_details;
+        let activity = match details.activity {
+            Some(activity) => activity,
+            None => return vec![Span::raw("unavailable")],
+        };
+        let totals = activity.totals();
+        let mut spans = Vec::new();
+        for (i, name) in names.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(", "));
+            }
+            spans.push(Span::raw(format!("{} {}", name, totals[offset + i])));
+            if let Some(rates) = details.activity_rates {
+                let rate = rates[offset + i];
+                let style = if offset + i == 1 && rate > 0.0 {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(format!(" ({:.0}/s)", rate), style));
+            }
+        }
+        spans
+    };
+    let memory = match &app.process_details.memory {
+        Some(memory) => {
+            let mut text = format!(
+                "resident {} ({} shared), virtual {}",
+                bytes(memory.resident),
+                bytes(memory.shared),
+                bytes(memory.virtual_size)
+            );
+            if let Some(proportional) = memory.proportional {
+                text.push_str(&format!(", PSS {}", bytes(proportional)));
+            }
+            text
+        }
+        None => "unavailable".to_string(),
+    };
+    let details = &app.process_details;
+    let path = |path: &Option<String>| {
+        Span::raw(path.clone().unwrap_or_else(|| "unavailable".to_string()))
+    };
+    // A binary that changed on disk keeps running the old code, usually the
+    // sign of an upgrade that needs a restart
+    let executable = |executable: &Option<procfs::Executable>| match executable {
+        Some(executable) => {
+            let change = match executable.change {
+                Some(procfs::BinaryChange::Deleted) => " (deleted, restart needed)",
+                Some(procfs::BinaryChange::Replaced) => " (replaced, restart needed)",
+                None => "",
+            };
+            vec![
+                Span::raw(executable.path.clone()),
+                Span::styled(change, Style::default().fg(Color::Yellow)),
+            ]
+        }
+        None => vec![Span::raw("unavailable")],
+    };
+    // Unconfined processes with every capability are the ones an audit is after
+    let warning = Style::default().fg(Color::Yellow);
+    let security_label = match &details.security_label {
+        Some(label) if label.starts_with("unconfined") => Span::styled(label.clone(), warning),
+        Some(label) => Span::raw(label.clone()),
+        None => Span::raw("none, no SELinux or AppArmor"),
+    };
+    let capabilities = match details.capabilities.map(procfs::describe_capabilities) {
+        Some(capabilities) if capabilities == "all" => Span::styled(capabilities, warning),
+        Some(capabilities) => Span::raw(capabilities),
+        None => Span::raw("unavailable"),
+    };
+    let gpu = match system_data.gpu.processes.iter().find(|p| p.pid == pid) {
+        Some(process) => format!(
+            "{}, VRAM {}, shared {} (G for every GPU process)",
+            process.describe_engines(),
+            bytes(process.vram),
+            bytes(process.shared)
+        ),
+        None => "not in use".to_string(),
+    };
+    let summary = match system_data.processes.iter().find(|p| p.pid == pid) {
+        Some(process) => vec![
+            Spans::from(vec![
+                Span::styled("Name: ", bold),
+                Span::raw(process.name.clone()),
+            ]),
+            Spans::from(vec![
+                Span::styled("CPU: ", bold),
+                Span::raw(format!("{:.2}%", process.cpu_usage)),
+            ]),
+            Spans::from(vec![Span::styled("Memory: ", bold), Span::raw(memory)]),
+            Spans::from(vec![Span::styled("GPU: ", bold), Span::raw(gpu)]),
+            Spans::from(
+                [
+                    vec![Span::styled("Page faults: ", bold)],
+                    counters(["minor", "major"], 0),
+                ]
+                .concat(),
+            ),
+            Spans::from(
+                [
+                    vec![Span::styled("Context switches: ", bold)],
+                    counters(["voluntary", "involuntary"], 2),
+                ]
+                .concat(),
+            ),
+            Spans::from(vec![
+                Span::styled("I/O priority: ", bold),
+                Span::raw(
+                    app.process_details
+                        .io_priority
+                        .clone()
+                        .unwrap_or_else(|| "unavailable".to_string()),
+                ),
+            ]),
+            Spans::from(vec![
+                Span::styled("Cgroup: ", bold),
+                Span::raw(process.cgroup.clone().unwrap_or_default()),
+            ]),
+            Spans::from(
+                [
+                    vec![Span::styled("Executable: ", bold)],
+                    executable(&details.executable),
+                ]
+                .concat(),
+            ),
+            Spans::from(vec![
+                Span::styled("Working directory: ", bold),
+                path(&details.cwd),
+            ]),
+            Spans::from(vec![Span::styled("Root: ", bold), path(&details.root)]),
+            Spans::from(vec![Span::styled("Security label: ", bold), security_label]),
+            Spans::from(vec![Span::styled("Capabilities: ", bold), capabilities]),
+        ],
+        None => vec![Spans::from(Span::styled(
+            "Process has exited",
+            Style::default().fg(Color::Yellow),
+        ))],
+    };
+    f.render_widget(Paragraph::new(summary), chunks[0]);
+
+    let threads = &app.process_details.threads;
+    let header = Row::new(
+        ["TID", "Name", "CPU"]
+            .iter()
+            .map(|h| Cell::from(*h).style(bold)),
+    )
+    .height(1)
+    .bottom_margin(1);
+    let rows = threads
+        .iter()
+        .map(|thread| {
+            Row::new(vec![
+                Cell::from(thread.tid.to_string()),
+                Cell::from(thread.name.clone()),
+                Cell::from(
+                    thread
+                        .cpu_usage
+                        .map(|usage| format!("{:.2}%", usage))
+                        .unwrap_or_default(),
+                ),
+            ])
+        })
+        .collect::<Vec<Row>>();
+    let table = Table::new(rows)
+        .header(header)
+        .block(
+            Block::default()
+                .title(format!(" Threads ({}) ", threads.len()))
+                .borders(Borders::TOP),
+        )
         .widths(&[
-            Constraint::Percentage(20),
+            Constraint::Length(8),
             Constraint::Percentage(60),
+            Constraint::Length(8),
+        ]);
+    f.render_widget(table, chunks[1]);
+
+    let limits = match &app.process_details.limits {
+        Some(limits) => limits,
+        None => {
+            f.render_widget(Paragraph::new("Resource limits unavailable"), chunks[2]);
+            return;
+        }
+    };
+
+    // Limits that most often explain a misbehaving process
+    let notable = [
+        "Max open files",
+        "Max address space",
+        "Max resident set",
+        "Max core file size",
+        "Max processes",
+    ];
+
+    let header_cells = ["Limit", "Soft", "Hard", "Units"]
+        .iter()
+        .map(|h| Cell::from(*h).style(bold));
+    let header = Row::new(header_cells).height(1).bottom_margin(1);
+
+    let rows = limits
+        .iter()
+        .map(|limit| {
+            let style = if notable.contains(&limit.name.as_str()) {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            Row::new(vec![
+                Cell::from(limit.name.clone()),
+                Cell::from(limit.soft.clone()),
+                Cell::from(limit.hard.clone()),
+                Cell::from(limit.units.clone()),
+            ])
+            .style(style)
+        })
+        .collect::<Vec<Row>>();
+
+    let table = Table::new(rows)
+        .header(header)
+        .block(Block::default().title(" Limits ").borders(Borders::TOP))
+        .widths(&[
+            Constraint::Percentage(40),
+            Constraint::Percentage(20),
+            Constraint::Percentage(20),
             Constraint::Percentage(20),
         ]);
-    t
+    f.render_widget(table, chunks[2]);
+}
+
+fn format_interval(ms: u64) -> String {
+    if ms < 1000 {
+        format!("{}ms", ms)
+    } else {
+        format!("{}s", ms / 1000)
+    }
 }
 
-fn info_block() -> Paragraph<'static> {
-    let block = Block::default().title(" Usage ").borders(Borders::ALL);
-    Paragraph::new("quit: q")
+fn info_block(app: &App) -> Paragraph<'static> {
+    if let Some(input) = &app.command_input {
+        let block = Block::default().title(" Command ").borders(Borders::ALL);
+        return Paragraph::new(Spans::from(vec![
+            Span::raw(format!(":{}", input)),
+            Span::styled("_", Style::default().add_modifier(Modifier::SLOW_BLINK)),
+        ]))
+        .block(block);
+    }
+
+    let title = match &app.status {
+        Some((message, shown)) if shown.elapsed() < STATUS_DURATION => Span::styled(
+            format!(" {} ", message),
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        ),
+        _ => Span::raw(format!(
+            " Usage (refreshing every {}) ",
+            format_interval(app.refresh_interval.load(Ordering::Relaxed))
+        )),
+    };
+    let block = Block::default().title(title).borders(Borders::ALL);
+    Paragraph::new(
+        "quit: q | all keys: ? | tabs: 1-5 | select: up/down | details: enter | filter: / | kill: k | command: : | close: esc",
+    )
         .alignment(Alignment::Left)
         .block(block)
 }
 
-// This is really hacky and probably not the best way to do this
-fn create_tuple_vec_for_graph(data: &Vec<f64>) -> Vec<(f64, f64)> {
-    let mut result = Vec::new();
-    for (i, d) in data.iter().enumerate() {
-        result.push(((i + 1) as f64, *d));
+// Every key, the bottom line only has room for the common ones
+const HELP: [(&str, &str); 43] = [
+    ("q, ctrl+c", "quit"),
+    ("ctrl+z", "suspend"),
+    ("?", "this list"),
+    ("esc", "close a popup"),
+    (
+        "1-5",
+        "overview, processes, network, disks and sensors tabs",
+    ),
+    ("s", "sysctls"),
+    ("h", "sensors"),
+    ("n", "network"),
+    ("d", "storage"),
+    ("f", "throttling"),
+    ("G", "gpu"),
+    ("T", "tasks"),
+    ("F", "failed units"),
+    ("W", "sleep"),
+    ("A", "anomalies"),
+    ("p", "microphone and camera"),
+    ("up/down", "select a process"),
+    ("pgup/pgdn", "select a page up or down"),
+    ("home/end", "select the first or last process"),
+    ("left/right", "scroll the process columns"),
+    ("enter", "process details"),
+    ("c", "per core cpu"),
+    ("P, M, N", "sort by cpu, memory or pid"),
+    ("S, I", "sort by any column, invert the sort"),
+    (
+        "t",
+        "process tree, - and + fold, C and E fold or unfold all",
+    ),
+    ("a", "sum children into their parents"),
+    ("g", "group by systemd unit"),
+    ("o", "columns"),
+    ("u, U", "only one user's processes, only mine"),
+    ("/", "filter by name or command line, ~ for a regex"),
+    ("space", "mark a process"),
+    (
+        "k, K",
+        "kill the marked or selected processes, with SIGKILL",
+    ),
+    (
+        "i",
+        "inspect the charts, left/right to move and tab to switch",
+    ),
+    ("z", "pause, left/right then scroll back through the charts"),
+    ("r", "refresh now"),
+    ("<, > or +, -", "refresh slower or faster"),
+    ("w", "chart window"),
+    ("x", "export the inspected chart, connections or processes"),
+    ("O", "settings"),
+    (":", "command line"),
+    (
+        "",
+        "killall <pattern>, renice <n>, ionice <rt|be|idle> [n], pin <cpus>",
+    ),
+    ("", "power <profile>, profile [save] <name>"),
+    ("", "as root: drop-caches [1-3], compact"),
+];
+
+fn help_popup<B: Backend>(f: &mut Frame<B>, app: &App, area: Rect) {
+    let area = centered_rect(70, 80, area);
+    f.render_widget(Clear, area);
+
+    let block = Block::default()
+        .title(" Keys (up/down to scroll) ")
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+    let lines = HELP
+        .iter()
+        .map(|(key, action)| {
+            Spans::from(vec![
+                Span::styled(format!("{:<12}", key), bold),
+                Span::raw(*action),
+            ])
+        })
+        .collect::<Vec<Spans>>();
+    let help = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((app.help_scroll, 0));
+    f.render_widget(help, area);
+}
+
+fn release_terminal<B: Backend>(terminal: &mut Terminal<B>, mouse: bool) -> Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen)?;
+    if mouse {
+        execute!(io::stdout(), DisableMouseCapture)?;
+    }
+    terminal.show_cursor()?;
+    Ok(())
+}
+
+fn take_terminal<B: Backend>(terminal: &mut Terminal<B>, mouse: bool) -> Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen)?;
+    if mouse {
+        execute!(io::stdout(), EnableMouseCapture)?;
+    }
+    // The screen was drawn over while it was away
+    terminal.clear()?;
+    Ok(())
+}
+
+// Hands the terminal back to the shell and stops like any other job, then
+// takes it over again once continued with fg
+fn suspend<B: Backend>(terminal: &mut Terminal<B>, system: &System, mouse: bool) -> Result<()> {
+    release_terminal(terminal, mouse)?;
+    if let Ok(pid) = sysinfo::get_current_pid() {
+        send_signal(system, pid.as_u32(), Signal::Stop);
+    }
+    take_terminal(terminal, mouse)
+}
+
+// Retries the pending action through sudo or pkexec, which get the terminal
+// for as long as they run so they can ask for a password
+async fn escalate<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
+    app.popup = None;
+    let pending = match app.pending.take() {
+        Some(pending) => pending,
+        None => return Ok(()),
+    };
+    let pids = pending
+        .processes
+        .iter()
+        .map(|(pid, _)| *pid)
+        .collect::<Vec<u32>>();
+    let (escalator, command) = match (pending.escalator, pending.action.command(&pids)) {
+        (Some(escalator), Some(command)) => (escalator, command),
+        _ => return Ok(()),
+    };
+
+    release_terminal(terminal, app.mouse)?;
+    let result = control::escalate(escalator, &command).await;
+    take_terminal(terminal, app.mouse)?;
+
+    let ran = format!("`{} {}`", escalator, command.join(" "));
+    let status = match result {
+        Ok(()) => format!(
+            "{} {} processes with {}",
+            pending.action.describe_done(),
+            pids.len(),
+            ran
+        ),
+        Err(err) => format!("{} failed: {}", ran, err),
+    };
+    app.set_status(status);
+    Ok(())
+}
+
+async fn set_power_profile(app: &mut App, choice: String) {
+    let set = choice.clone();
+    let result = tokio::task::spawn_blocking(move || power::set_profile(&set))
+        .await
+        .unwrap_or_else(|err| Err(err.to_string()));
+    match result {
+        Ok(()) => {
+            app.power_changed.notify_one();
+            app.set_status(format!("Power profile set to {}", choice));
+        }
+        Err(err) => app.set_status(format!("Power profile: {}", err)),
     }
-    result
+}
+
+// Seconds since the epoch, which together with the PID tells processes apart
+fn start_time(system: &System, pid: u32) -> Option<u64> {
+    system
+        .process(Pid::from_u32(pid))
+        .map(|process| process.start_time())
+}
+
+// Returns whether the signal was delivered
+fn send_signal(system: &System, pid: u32, signal: Signal) -> bool {
+    system
+        .process(Pid::from_u32(pid))
+        .and_then(|process| process.kill_with(signal))
+        .unwrap_or(false)
 }
@@ -0,0 +1,87 @@
+use chrono::Local;
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+// System wide actions for memory firefighting. They only work as root, need
+// their own confirmation and leave a line in the audit log either way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Task {
+    // 1 frees the page cache, 2 dentries and inodes, 3 both
+    DropCaches(u8),
+    CompactMemory,
+}
+
+impl Task {
+    pub fn describe(self) -> String {
+        match self {
+            Task::DropCaches(1) => "Drop the page cache".to_string(),
+            Task::DropCaches(2) => "Drop the dentry and inode caches".to_string(),
+            Task::DropCaches(_) => "Drop the page, dentry and inode caches".to_string(),
+            Task::CompactMemory => "Compact memory".to_string(),
+        }
+    }
+
+    // Shown in the confirmation, neither is free on a busy machine
+    pub fn warning(self) -> &'static str {
+        match self {
+            Task::DropCaches(_) => {
+                "Dirty pages are synced first. Everything dropped has to be read back from disk, so I/O will spike for a while."
+            }
+            Task::CompactMemory => {
+                "Moves pages around to free up contiguous memory. This can stall the whole machine for seconds on large hosts."
+            }
+        }
+    }
+
+    fn sysctl(self) -> (&'static str, String) {
+        match self {
+            Task::DropCaches(level) => ("/proc/sys/vm/drop_caches", level.to_string()),
+            Task::CompactMemory => ("/proc/sys/vm/compact_memory", "1".to_string()),
+        }
+    }
+
+    pub fn run(self) -> io::Result<()> {
+        if let Task::DropCaches(_) = self {
+            // Only clean pages can be dropped
+            unsafe { libc::sync() };
+        }
+        let (path, value) = self.sysctl();
+        let result = fs::write(path, value);
+        // A failed audit write doesn't undo the action, so it isn't an error
+        let _ = audit(self, &result);
+        result
+    }
+}
+
+pub fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+pub fn audit_path() -> Option<PathBuf> {
+    Some(crate::persist::state_dir()?.join("audit.log"))
+}
+
+fn audit(task: Task, result: &io::Result<()>) -> io::Result<()> {
+    let path = audit_path().ok_or(io::ErrorKind::NotFound)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let (sysctl, value) = task.sysctl();
+    let outcome = match result {
+        Ok(()) => "ok".to_string(),
+        Err(err) => format!("failed: {}", err),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(
+        file,
+        "{} uid={} {}={} {}",
+        Local::now().format("%Y-%m-%dT%H:%M:%S%z"),
+        unsafe { libc::geteuid() },
+        sysctl,
+        value,
+        outcome
+    )
+}
@@ -0,0 +1,116 @@
+use crate::history::History;
+use std::fs;
+
+// In the kernel's order, /proc/net/tcp reports the state as this index plus one
+pub const TCP_STATES: [&str; 11] = [
+    "ESTABLISHED",
+    "SYN_SENT",
+    "SYN_RECV",
+    "FIN_WAIT1",
+    "FIN_WAIT2",
+    "TIME_WAIT",
+    "CLOSE",
+    "CLOSE_WAIT",
+    "LAST_ACK",
+    "LISTEN",
+    "CLOSING",
+];
+
+// Charted over time: a CLOSE_WAIT line that keeps growing is a server not
+// closing its sockets, TIME_WAIT one opening too many short connections
+pub const CHARTED_STATES: [usize; 3] = [0, 5, 7];
+
+// Tracked connections and the table size. Once it's full new connections are
+// dropped, with only a kernel log line to show for it
+pub fn read_conntrack() -> Option<(u64, u64)> {
+    let read = |name: &str| -> Option<u64> {
+        let path = format!("/proc/sys/net/netfilter/{}", name);
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    Some((read("nf_conntrack_count")?, read("nf_conntrack_max")?))
+}
+
+// Totals since boot from /proc/net/snmp
+#[derive(Debug, Clone, Copy)]
+pub struct TcpSegments {
+    pub sent: u64,
+    pub retransmitted: u64,
+}
+
+// The Tcp section is a header line of names followed by a line of values
+pub fn read_tcp_segments() -> Option<TcpSegments> {
+    let content = fs::read_to_string("/proc/net/snmp").ok()?;
+    let mut lines = content.lines().filter(|line| line.starts_with("Tcp:"));
+    let (names, values) = (lines.next()?, lines.next()?);
+    let field = |wanted: &str| {
+        names
+            .split_whitespace()
+            .zip(values.split_whitespace())
+            .find(|(name, _)| *name == wanted)
+            .and_then(|(_, value)| value.parse::<u64>().ok())
+    };
+    Some(TcpSegments {
+        sent: field("OutSegs")?,
+        retransmitted: field("RetransSegs")?,
+    })
+}
+
+// Sockets per state over IPv4 and IPv6, None without /proc/net/tcp
+pub fn read_tcp_states() -> Option<[u64; 11]> {
+    let mut counts = [0; 11];
+    let mut found = false;
+    for path in ["/proc/net/tcp", "/proc/net/tcp6"] {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        found = true;
+        for line in content.lines().skip(1) {
+            let state = line
+                .split_whitespace()
+                .nth(3)
+                .and_then(|state| usize::from_str_radix(state, 16).ok());
+            if let Some(count) = state
+                .and_then(|s| s.checked_sub(1))
+                .and_then(|i| counts.get_mut(i))
+            {
+                *count += 1;
+            }
+        }
+    }
+    found.then_some(counts)
+}
+
+// One network interface, with rates in bytes per second since the previous
+// refresh and the kernel's error and drop counts since it came up
+#[derive(Debug)]
+pub struct Interface {
+    pub name: String,
+    pub received: History,
+    pub transmitted: History,
+    // Received, then transmitted
+    pub errors: (u64, u64),
+    pub drops: (u64, u64),
+}
+
+impl Interface {
+    pub fn new(name: String) -> Self {
+        Interface {
+            name,
+            received: History::default(),
+            transmitted: History::default(),
+            errors: (0, 0),
+            drops: (0, 0),
+        }
+    }
+}
+
+// sysinfo has the errors but not the drops, e.g. a full receive ring or a
+// packet for a protocol nothing handles
+pub fn read_drops(interface: &str) -> Option<(u64, u64)> {
+    let read = |name: &str| -> Option<u64> {
+        let path = format!("/sys/class/net/{}/statistics/{}", interface, name);
+        fs::read_to_string(path).ok()?.trim().parse().ok()
+    };
+    Some((read("rx_dropped")?, read("tx_dropped")?))
+}
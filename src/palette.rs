@@ -0,0 +1,188 @@
+// Downgrades colors for terminals that can't show them. Everything is drawn
+// with whatever colors the panels ask for, then the Adapt widget rewrites the
+// frame to the theme and to the closest colors the terminal supports.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use tui::{buffer::Buffer, layout::Rect, style::Color, widgets::Widget};
+
+// Panels pick colors for a dark background, the light theme swaps the ones
+// that wash out on a light one
+#[derive(Debug, Clone, Copy, PartialEq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+    // Like NO_COLOR
+    Mono,
+}
+
+pub const THEMES: [Theme; 3] = [Theme::Dark, Theme::Light, Theme::Mono];
+
+impl Theme {
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+            Theme::Mono => "mono",
+        }
+    }
+
+    fn apply(self, color: Color) -> Color {
+        if self != Theme::Light {
+            return color;
+        }
+        match color {
+            Color::White => Color::Black,
+            Color::Gray => Color::DarkGray,
+            Color::Yellow | Color::LightYellow => Color::Rgb(150, 110, 0),
+            Color::LightRed => Color::Red,
+            Color::LightGreen => Color::Green,
+            Color::LightBlue => Color::Blue,
+            Color::LightMagenta => Color::Magenta,
+            Color::LightCyan => Color::Cyan,
+            _ => color,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ColorDepth {
+    #[default]
+    TrueColor,
+    Indexed256,
+    Basic16,
+    // NO_COLOR or a dumb terminal, only modifiers like bold are kept
+    Monochrome,
+}
+
+// There is no reliable query for this, so go by the same variables other
+// terminal programs check
+pub fn detect() -> ColorDepth {
+    if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return ColorDepth::Monochrome;
+    }
+    let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+    if colorterm == "truecolor" || colorterm == "24bit" {
+        return ColorDepth::TrueColor;
+    }
+    let term = std::env::var("TERM").unwrap_or_default();
+    if term.is_empty() || term == "dumb" {
+        ColorDepth::Monochrome
+    } else if term.contains("256color") || term.contains("direct") {
+        ColorDepth::Indexed256
+    } else {
+        ColorDepth::Basic16
+    }
+}
+
+// xterm's defaults for the 16 named colors
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// Steps of the 6x6x6 cube in the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize].1,
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[(cube / 36) as usize],
+                CUBE_LEVELS[(cube / 6 % 6) as usize],
+                CUBE_LEVELS[(cube % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn nearest_basic(rgb: (u8, u8, u8)) -> Color {
+    BASIC_COLORS
+        .iter()
+        .min_by_key(|(_, basic)| distance(rgb, *basic))
+        .map(|(color, _)| *color)
+        .unwrap_or(Color::Reset)
+}
+
+// Whichever of the closest cube color and the closest grey is nearer
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| (CUBE_LEVELS[*i] as i32 - value as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * level(rgb.0) + 6 * level(rgb.1) + level(rgb.2);
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(rgb, indexed_rgb(grey)) < distance(rgb, indexed_rgb(cube)) {
+        grey
+    } else {
+        cube
+    }
+}
+
+impl ColorDepth {
+    pub fn adapt(self, color: Color) -> Color {
+        match (self, color) {
+            (_, Color::Reset) | (ColorDepth::TrueColor, _) => color,
+            (ColorDepth::Monochrome, _) => Color::Reset,
+            (ColorDepth::Indexed256, Color::Rgb(r, g, b)) => {
+                Color::Indexed(nearest_indexed((r, g, b)))
+            }
+            (ColorDepth::Basic16, Color::Rgb(r, g, b)) => nearest_basic((r, g, b)),
+            (ColorDepth::Basic16, Color::Indexed(index)) => nearest_basic(indexed_rgb(index)),
+            _ => color,
+        }
+    }
+}
+
+// Drawn last over the whole frame
+pub struct Adapt(pub ColorDepth, pub Theme);
+
+impl Widget for Adapt {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Adapt(depth, theme) = self;
+        let depth = if theme == Theme::Mono {
+            ColorDepth::Monochrome
+        } else {
+            depth
+        };
+        if depth == ColorDepth::TrueColor && theme == Theme::Dark {
+            return;
+        }
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = buf.get_mut(x, y);
+                cell.fg = depth.adapt(theme.apply(cell.fg));
+                cell.bg = depth.adapt(theme.apply(cell.bg));
+            }
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use crate::history::History;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// Chart histories written on exit and read back on start, so a quick restart
+// keeps the recent context. Files from before a chart was added load with it
+// empty.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SavedHistory {
+    pub memory: History,
+    pub cpus: Vec<(String, History)>,
+    pub tmpfs: History,
+    pub frequency: History,
+    pub package_temperature: History,
+    pub network: [History; 2],
+    // Received and transmitted by interface name
+    pub interfaces: Vec<(String, [History; 2])>,
+    pub disk_io: [History; 2],
+    // Read and write latency by disk name
+    pub disk_latency: Vec<(String, [History; 2])>,
+    pub load: [History; 3],
+    pub pressure: [History; 3],
+    pub tasks: [History; 2],
+    pub forks: History,
+    pub retransmits: History,
+    pub sockets: [History; 3],
+    // By card and engine
+    pub gpu_engines: Vec<(String, String, History)>,
+    pub gpu_vram: History,
+    pub gpu_power: History,
+    pub gpu_temperature: History,
+    pub gpu_busy: History,
+    // The name resolved, then its latency and failures
+    pub dns: Option<(String, [History; 2])>,
+}
+
+// A chart the saver didn't sample comes back empty, which leaves the one
+// already there alone. The daemon only samples some, and is attached to
+// after the file is loaded.
+pub fn adopt<'a>(histories: impl IntoIterator<Item = (&'a mut History, History)>) {
+    for (history, saved) in histories {
+        if saved.started().is_some() {
+            *history = saved;
+        }
+    }
+}
+
+// Follows the XDG base directory spec, falling back to ~/.local/state
+pub fn state_dir() -> Option<PathBuf> {
+    let state_dir = match std::env::var_os("XDG_STATE_HOME").filter(|v| !v.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(state_dir.join("rust_resource_viewer"))
+}
+
+pub fn default_path() -> Option<PathBuf> {
+    Some(state_dir()?.join("history.json"))
+}
+
+// A missing or unreadable file just means starting with empty charts
+pub fn load(path: &Path) -> Option<SavedHistory> {
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Renamed over the old file like the session, so quitting part way through a
+// write keeps the last history. The name is the user's with --history-file.
+pub fn save(path: &Path, history: &SavedHistory) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut partial = path.as_os_str().to_owned();
+    partial.push(".partial");
+    fs::write(&partial, serde_json::to_string(history)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
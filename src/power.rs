@@ -0,0 +1,128 @@
+// Power profiles, through power-profiles-daemon when it's installed since it
+// lets users switch without root, otherwise the kernel's ACPI platform profile
+
+use std::{fs, process::Command, time::Duration};
+
+const PLATFORM_PROFILE: &str = "/sys/firmware/acpi/platform_profile";
+const PLATFORM_CHOICES: &str = "/sys/firmware/acpi/platform_profile_choices";
+
+// powerprofilesctl is a Python script, slow enough to not run every sample
+pub const POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+// The daemon and the kernel name the low power profile differently
+const ALIASES: [&str; 3] = ["power-saver", "low-power", "powersave"];
+
+#[derive(Debug, Clone)]
+pub struct PowerProfile {
+    pub active: String,
+    pub choices: Vec<String>,
+}
+
+impl PowerProfile {
+    // The choice a name refers to, taking the other names for power saving
+    pub fn choice(&self, name: &str) -> Option<&str> {
+        self.choices
+            .iter()
+            .find(|choice| *choice == name)
+            .or_else(|| {
+                ALIASES.contains(&name).then(|| {
+                    self.choices
+                        .iter()
+                        .find(|choice| ALIASES.contains(&choice.as_str()))
+                })?
+            })
+            .map(String::as_str)
+    }
+}
+
+fn powerprofilesctl(args: &[&str]) -> Option<String> {
+    let output = Command::new("powerprofilesctl").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// `list` prints each profile as "  name:" followed by its details, with the
+// active one marked "* name:"
+fn read_daemon() -> Option<PowerProfile> {
+    let list = powerprofilesctl(&["list"])?;
+    let mut active = None;
+    let mut choices = Vec::new();
+    for line in list.lines() {
+        let (marker, rest) = line.split_at(line.len().min(2));
+        let name = match rest.strip_suffix(':') {
+            Some(name) if !name.starts_with(' ') && !name.is_empty() => name.to_string(),
+            _ => continue,
+        };
+        if marker == "* " {
+            active = Some(name.clone());
+        }
+        choices.push(name);
+    }
+    Some(PowerProfile {
+        active: active?,
+        choices,
+    })
+}
+
+fn read_platform() -> Option<PowerProfile> {
+    let active = fs::read_to_string(PLATFORM_PROFILE)
+        .ok()?
+        .trim()
+        .to_string();
+    let choices = fs::read_to_string(PLATFORM_CHOICES)
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_string)
+        .collect();
+    Some(PowerProfile { active, choices })
+}
+
+pub fn read_profile() -> Option<PowerProfile> {
+    read_daemon().or_else(read_platform)
+}
+
+pub fn set_profile(name: &str) -> Result<(), String> {
+    if read_daemon().is_some() {
+        return powerprofilesctl(&["set", name])
+            .map(|_| ())
+            .ok_or_else(|| "powerprofilesctl failed".to_string());
+    }
+    fs::write(PLATFORM_PROFILE, name).map_err(|err| err.to_string())
+}
+
+#[derive(Debug, Clone)]
+pub struct Backlight {
+    pub name: String,
+    pub percent: f64,
+}
+
+// Laptops can have several interfaces to the same panel, the kernel docs
+// recommend firmware ones over platform ones over raw register access
+pub fn read_backlight() -> Option<Backlight> {
+    let rank = |kind: &str| match kind {
+        "firmware" => 0,
+        "platform" => 1,
+        _ => 2,
+    };
+    fs::read_dir("/sys/class/backlight")
+        .ok()?
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            let read = |file: &str| fs::read_to_string(path.join(file)).ok();
+            let brightness = read("brightness")?.trim().parse::<f64>().ok()?;
+            let max = read("max_brightness")?.trim().parse::<f64>().ok()?;
+            let kind = read("type").unwrap_or_default();
+            (max > 0.0).then(|| {
+                let backlight = Backlight {
+                    name: entry.file_name().to_string_lossy().into_owned(),
+                    percent: brightness / max * 100.0,
+                };
+                (rank(kind.trim()), backlight)
+            })
+        })
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, backlight)| backlight)
+}
@@ -0,0 +1,47 @@
+// Layouts for one kind of use each, picked with --preset instead of the
+// general purpose one
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    // GPU engines, VRAM, GPU power and temperature and the processes on the
+    // GPU, for keeping an eye on training runs
+    Ml,
+    // Sparklines of CPU, GPU, memory and temperatures, sampled slowly and
+    // without going through the processes, to leave a game or benchmark be
+    Gaming,
+    // Load, pressure, disk latency, connections and failed units next to the
+    // processes, with nothing a server doesn't have sampled
+    Server,
+}
+
+impl Preset {
+    // Milliseconds between samples, for presets that change it
+    pub fn refresh_interval(self) -> Option<u64> {
+        match self {
+            Preset::Gaming => Some(2000),
+            Preset::Ml | Preset::Server => None,
+        }
+    }
+
+    // Milliseconds between redraws while no key is pressed
+    pub fn redraw_interval(self) -> u64 {
+        match self {
+            Preset::Gaming => 1000,
+            Preset::Ml | Preset::Server => 100,
+        }
+    }
+
+    // Only what the sparklines need is sampled
+    pub fn lightweight(self) -> bool {
+        self == Preset::Gaming
+    }
+
+    // No GPU, backlight or power profile to look for
+    pub fn headless(self) -> bool {
+        self == Preset::Server
+    }
+}
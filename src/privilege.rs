@@ -0,0 +1,121 @@
+// With --drop-privileges, root gives way to the user who ran sudo or pkexec,
+// keeping only the two capabilities that reading other users' processes
+// takes: listing their file descriptors and reading their I/O, memory maps
+// and executables. Killing, renicing and writing sysctls go with root. The
+// kernel checks those on every use rather than when a file is opened, so
+// there's nothing worth holding open across the switch.
+
+use crate::procfs;
+use anyhow::{bail, Context, Result};
+use std::{
+    ffi::{CStr, CString},
+    io,
+    sync::OnceLock,
+};
+
+const CAP_DAC_READ_SEARCH: u32 = 2;
+const CAP_SYS_PTRACE: u32 = 19;
+const KEPT: u32 = 1 << CAP_DAC_READ_SEARCH | 1 << CAP_SYS_PTRACE;
+
+// _LINUX_CAPABILITY_VERSION_3, which takes two of CapData for 64 bits
+const CAPABILITY_VERSION: u32 = 0x2008_0522;
+
+#[repr(C)]
+struct CapHeader {
+    version: u32,
+    pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Default, Clone, Copy)]
+struct CapData {
+    effective: u32,
+    permitted: u32,
+    inheritable: u32,
+}
+
+fn env_id(name: &str) -> Option<u32> {
+    std::env::var(name).ok()?.parse().ok()
+}
+
+// sudo says who ran it, pkexec only gives the uid so the group comes from
+// the user's passwd entry
+fn invoking_user() -> Option<(u32, u32)> {
+    if let (Some(uid), Some(gid)) = (env_id("SUDO_UID"), env_id("SUDO_GID")) {
+        return Some((uid, gid));
+    }
+    let uid = env_id("PKEXEC_UID")?;
+    let passwd = unsafe { libc::getpwuid(uid) };
+    if passwd.is_null() {
+        return None;
+    }
+    Some((uid, unsafe { (*passwd).pw_gid }))
+}
+
+fn user_name(uid: u32) -> Option<String> {
+    let passwd = unsafe { libc::getpwuid(uid) };
+    if passwd.is_null() {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr((*passwd).pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+pub fn drop_privileges() -> Result<()> {
+    if unsafe { libc::geteuid() } != 0 {
+        bail!("--drop-privileges needs to be started as root");
+    }
+    let (uid, gid) = invoking_user().context(
+        "--drop-privileges needs to be run through sudo or pkexec, to know who to switch to",
+    )?;
+    let name = user_name(uid).with_context(|| format!("no user with uid {}", uid))?;
+    let c_name = CString::new(name.clone())?;
+
+    // Groups go first, root's supplementary groups would outlive the uid
+    // otherwise. Keeping capabilities through setuid leaves them permitted
+    // but not effective, capset turns the kept ones back on.
+    let header = CapHeader {
+        version: CAPABILITY_VERSION,
+        pid: 0,
+    };
+    let data = [
+        CapData {
+            effective: KEPT,
+            permitted: KEPT,
+            inheritable: 0,
+        },
+        CapData::default(),
+    ];
+    unsafe {
+        check(libc::initgroups(c_name.as_ptr(), gid)).context("setting groups")?;
+        check(libc::setgid(gid)).context("setting the group")?;
+        check(libc::prctl(libc::PR_SET_KEEPCAPS, 1)).context("keeping capabilities")?;
+        check(libc::setuid(uid)).context("setting the user")?;
+        check(libc::syscall(libc::SYS_capset, &header, data.as_ptr()) as libc::c_int)
+            .context("setting capabilities")?;
+    }
+    // setuid as root changes the saved uid too, so there's no way back
+    if unsafe { libc::setuid(0) } == 0 {
+        bail!("still able to become root after switching to {}", name);
+    }
+    Ok(())
+}
+
+// Whether other users' processes can be read, from the capabilities rather
+// than the uid so setcap and systemd's AmbientCapabilities count too. Fixed
+// once the drop is done, so it's only read once.
+pub fn sees_all_processes() -> bool {
+    static SEES_ALL: OnceLock<bool> = OnceLock::new();
+    *SEES_ALL.get_or_init(|| {
+        let kept = u64::from(KEPT);
+        procfs::read_capabilities(std::process::id()).is_some_and(|caps| caps & kept == kept)
+    })
+}
@@ -0,0 +1,582 @@
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::fs::MetadataExt,
+    time::{Duration, Instant},
+};
+
+#[derive(Debug)]
+pub struct Limit {
+    pub name: String,
+    pub soft: String,
+    pub hard: String,
+    pub units: String,
+}
+
+// Extra per process information that sysinfo doesn't expose, loaded on demand
+// for the detail popup
+#[derive(Debug, Default)]
+pub struct ProcessDetails {
+    pub limits: Option<Vec<Limit>>,
+    pub memory: Option<MemoryBreakdown>,
+    pub io_priority: Option<String>,
+    pub activity: Option<Activity>,
+    // Per second since the previous read, once there is one
+    pub activity_rates: Option<[f64; 4]>,
+    pub threads: Vec<Thread>,
+    pub executable: Option<Executable>,
+    pub cwd: Option<String>,
+    // Differs from / for chrooted and containerised processes
+    pub root: Option<String>,
+    // SELinux context or AppArmor profile, None without either
+    pub security_label: Option<String>,
+    pub capabilities: Option<u64>,
+    read: Option<Instant>,
+}
+
+impl ProcessDetails {
+    pub fn load(pid: u32) -> Self {
+        ProcessDetails {
+            limits: read_limits(pid),
+            memory: read_memory(pid),
+            io_priority: crate::control::io_priority(pid),
+            activity: read_activity(pid),
+            activity_rates: None,
+            threads: read_threads(pid),
+            executable: read_executable(pid),
+            cwd: read_link(pid, "cwd"),
+            root: read_link(pid, "root"),
+            security_label: read_security_label(pid),
+            capabilities: read_capabilities(pid),
+            read: Some(Instant::now()),
+        }
+    }
+
+    // The counters only say much as rates, so they're read again while the
+    // popup stays open
+    pub fn refresh(&mut self, pid: u32, interval: Duration) {
+        let read = match self.read {
+            Some(read) if read.elapsed() < interval => return,
+            read => read,
+        };
+        let seconds = read.map(|read| read.elapsed().as_secs_f64());
+        let activity = read_activity(pid);
+        self.activity_rates = match (self.activity, activity, seconds) {
+            (Some(earlier), Some(later), Some(seconds)) => Some(later.rates(&earlier, seconds)),
+            _ => None,
+        };
+        self.activity = activity;
+
+        let mut threads = read_threads(pid);
+        for thread in threads.iter_mut() {
+            let earlier = self
+                .threads
+                .iter()
+                .find(|earlier| earlier.tid == thread.tid);
+            thread.cpu_usage = match (earlier, seconds) {
+                (Some(earlier), Some(seconds)) if seconds > 0.0 => {
+                    let ticks = thread.ticks.saturating_sub(earlier.ticks) as f64;
+                    Some(ticks / clock_ticks() / seconds * 100.0)
+                }
+                _ => None,
+            };
+        }
+        // Busiest first, it's usually one hot thread that's being looked for
+        threads.sort_by(|a, b| {
+            b.cpu_usage
+                .unwrap_or(0.0)
+                .total_cmp(&a.cpu_usage.unwrap_or(0.0))
+                .then(a.tid.cmp(&b.tid))
+        });
+        self.threads = threads;
+        self.read = Some(Instant::now());
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Thread {
+    pub tid: u32,
+    // Set by the program with prctl or pthread_setname_np, the process name otherwise
+    pub name: String,
+    // User and system time in clock ticks
+    ticks: u64,
+    // Percent of one core since the previous read
+    pub cpu_usage: Option<f64>,
+}
+
+fn clock_ticks() -> f64 {
+    unsafe { libc::sysconf(libc::_SC_CLK_TCK) as f64 }
+}
+
+// The stat file of each thread in /proc/<pid>/task/, read like the process's own
+pub fn read_threads(pid: u32) -> Vec<Thread> {
+    let entries = match fs::read_dir(format!("/proc/{}/task", pid)) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let tid = entry.file_name().to_string_lossy().parse::<u32>().ok()?;
+            let content = fs::read_to_string(entry.path().join("stat")).ok()?;
+            let name = content.get(content.find('(')? + 1..content.rfind(')')?)?;
+            let fields = content
+                .get(content.rfind(')')? + 1..)?
+                .split_whitespace()
+                .collect::<Vec<&str>>();
+            // Fields 14 and 15, utime and stime
+            let utime = fields.get(11)?.parse::<u64>().ok()?;
+            let stime = fields.get(12)?.parse::<u64>().ok()?;
+            Some(Thread {
+                tid,
+                name: name.to_string(),
+                ticks: utime + stime,
+                cpu_usage: None,
+            })
+        })
+        .collect()
+}
+
+// Totals since the process started
+#[derive(Debug, Clone, Copy)]
+pub struct Activity {
+    // Faults served from the page cache, and ones that had to wait for disk
+    pub minor_faults: u64,
+    pub major_faults: u64,
+    // Switches while waiting for something, and preemptions by the scheduler
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+}
+
+impl Activity {
+    // In the order of the fields
+    pub fn totals(&self) -> [u64; 4] {
+        [
+            self.minor_faults,
+            self.major_faults,
+            self.voluntary_switches,
+            self.involuntary_switches,
+        ]
+    }
+
+    // In the same order as totals
+    pub fn rates(&self, earlier: &Activity, seconds: f64) -> [f64; 4] {
+        let (later, earlier) = (self.totals(), earlier.totals());
+        std::array::from_fn(|i| {
+            if seconds > 0.0 {
+                later[i].saturating_sub(earlier[i]) as f64 / seconds
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+// All in bytes
+#[derive(Debug, Clone, Copy)]
+pub struct MemoryBreakdown {
+    pub resident: u64,
+    // File backed and shmem pages, e.g. shared libraries and copy-on-write pages of forked servers
+    pub shared: u64,
+    pub virtual_size: u64,
+    // Proportional set size, each shared page divided between the processes mapping it.
+    // smaps_rollup is only readable for our own processes unless running as root
+    pub proportional: Option<u64>,
+}
+
+// /proc/<pid>/limits is a fixed width table, so the column offsets are taken
+// from the header line rather than splitting on whitespace
+pub fn read_limits(pid: u32) -> Option<Vec<Limit>> {
+    let content = fs::read_to_string(format!("/proc/{}/limits", pid)).ok()?;
+    let mut lines = content.lines();
+    let header = lines.next()?;
+    let soft_at = header.find("Soft Limit")?;
+    let hard_at = header.find("Hard Limit")?;
+    let units_at = header.find("Units")?;
+
+    let column = |line: &str, start: usize, end: usize| -> String {
+        line.get(start..end.min(line.len()))
+            .unwrap_or("")
+            .trim()
+            .to_string()
+    };
+
+    let limits = lines
+        .map(|line| Limit {
+            name: column(line, 0, soft_at),
+            soft: column(line, soft_at, hard_at),
+            hard: column(line, hard_at, units_at),
+            units: column(line, units_at, line.len()),
+        })
+        .collect();
+    Some(limits)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Stat {
+    pub nice: i64,
+    pub threads: u64,
+    // User and system time since the process started, in seconds
+    pub cpu_time: f64,
+    // In clock ticks since boot, tells a reused pid from the process before
+    pub start_time: u64,
+}
+
+// The command name in /proc/<pid>/stat can contain spaces and parentheses, so
+// fields are counted from the last closing parenthesis
+pub fn read_stat(pid: u32) -> Option<Stat> {
+    let content = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let fields = content
+        .get(content.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect::<Vec<&str>>();
+    // Field 19 is nice and 20 is num_threads, counted from the pid as field 1,
+    // 14 and 15 are utime and stime, 22 is starttime
+    let ticks = |index: usize| fields.get(index)?.parse::<u64>().ok();
+    Some(Stat {
+        nice: fields.get(16)?.parse().ok()?,
+        threads: fields.get(17)?.parse().ok()?,
+        cpu_time: (ticks(11)? + ticks(12)?) as f64 / clock_ticks(),
+        start_time: ticks(19)?,
+    })
+}
+
+// Lines of /proc/<pid>/status look like "VmSwap:     1234 kB"
+fn read_status_kb(pid: u32, key: &str) -> Option<u64> {
+    let content = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    content.lines().find_map(|line| {
+        let value = line.strip_prefix(key)?.strip_prefix(':')?;
+        value.split_whitespace().next()?.parse::<u64>().ok()
+    })
+}
+
+// Swapped out memory in bytes, which RSS doesn't include
+pub fn read_swap(pid: u32) -> Option<u64> {
+    read_status_kb(pid, "VmSwap").map(|kb| kb * 1024)
+}
+
+pub fn read_memory(pid: u32) -> Option<MemoryBreakdown> {
+    let kb = |key| read_status_kb(pid, key).map(|kb| kb * 1024);
+    let proportional = fs::read_to_string(format!("/proc/{}/smaps_rollup", pid))
+        .ok()
+        .and_then(|content| {
+            content.lines().find_map(|line| {
+                let value = line.strip_prefix("Pss:")?;
+                value.split_whitespace().next()?.parse::<u64>().ok()
+            })
+        })
+        .map(|kb| kb * 1024);
+    Some(MemoryBreakdown {
+        resident: kb("VmRSS")?,
+        shared: kb("RssFile").unwrap_or(0) + kb("RssShmem").unwrap_or(0),
+        virtual_size: kb("VmSize")?,
+        proportional,
+    })
+}
+
+// status counts context switches for one thread, so they're added up over
+// the threads in task/
+fn read_switches(pid: u32) -> Option<(u64, u64)> {
+    let entries = fs::read_dir(format!("/proc/{}/task", pid)).ok()?;
+    let mut switches = (0, 0);
+    for entry in entries.filter_map(|entry| entry.ok()) {
+        let content = match fs::read_to_string(entry.path().join("status")) {
+            Ok(content) => content,
+            // Exited since the directory was listed
+            Err(_) => continue,
+        };
+        for line in content.lines() {
+            let count = |key: &str| {
+                line.strip_prefix(key)?
+                    .strip_prefix(':')?
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            };
+            if let Some(count) = count("voluntary_ctxt_switches") {
+                switches.0 += count;
+            } else if let Some(count) = count("nonvoluntary_ctxt_switches") {
+                switches.1 += count;
+            }
+        }
+    }
+    Some(switches)
+}
+
+// Faults are counted for the whole process in /proc/<pid>/stat
+pub fn read_activity(pid: u32) -> Option<Activity> {
+    let content = fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    let fields = content
+        .get(content.rfind(')')? + 1..)?
+        .split_whitespace()
+        .collect::<Vec<&str>>();
+    let (voluntary_switches, involuntary_switches) = read_switches(pid)?;
+    // Fields 10 and 12, minflt and majflt
+    Some(Activity {
+        minor_faults: fields.get(7)?.parse().ok()?,
+        major_faults: fields.get(9)?.parse().ok()?,
+        voluntary_switches,
+        involuntary_switches,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinaryChange {
+    // Removed, e.g. by a package upgrade that unlinks before writing
+    Deleted,
+    // Another file now sits at the same path
+    Replaced,
+}
+
+#[derive(Debug, Clone)]
+pub struct Executable {
+    pub path: String,
+    // None while the process still runs what's on disk
+    pub change: Option<BinaryChange>,
+}
+
+// Only readable for our own processes unless running as root, and not at all
+// for kernel threads
+fn read_link(pid: u32, name: &str) -> Option<String> {
+    let path = fs::read_link(format!("/proc/{}/{}", pid, name)).ok()?;
+    Some(path.to_string_lossy().into_owned())
+}
+
+// The kernel appends " (deleted)" to the link once the file is unlinked.
+// Following the link opens the file that is running, so comparing its inode
+// with the one at the path shows when it was swapped out from under it.
+pub fn read_executable(pid: u32) -> Option<Executable> {
+    let link = read_link(pid, "exe")?;
+    if let Some(path) = link.strip_suffix(" (deleted)") {
+        return Some(Executable {
+            path: path.to_string(),
+            change: Some(BinaryChange::Deleted),
+        });
+    }
+    let running = fs::metadata(format!("/proc/{}/exe", pid)).ok();
+    let on_disk = fs::metadata(&link).ok();
+    let change = match (running, on_disk) {
+        (Some(running), Some(on_disk))
+            if (running.dev(), running.ino()) != (on_disk.dev(), on_disk.ino()) =>
+        {
+            Some(BinaryChange::Replaced)
+        }
+        _ => None,
+    };
+    Some(Executable { path: link, change })
+}
+
+// attr/current belongs to whichever LSM is the major one, newer kernels also
+// keep AppArmor's under its own name when it isn't
+pub fn read_security_label(pid: u32) -> Option<String> {
+    ["attr/current", "attr/apparmor/current"]
+        .iter()
+        .find_map(|file| {
+            let label = fs::read_to_string(format!("/proc/{}/{}", pid, file)).ok()?;
+            let label = label.trim_end_matches(['\0', '\n']).trim();
+            (!label.is_empty()).then(|| label.to_string())
+        })
+}
+
+// In bit order, as in linux/capability.h
+const CAPABILITIES: [&str; 41] = [
+    "chown",
+    "dac_override",
+    "dac_read_search",
+    "fowner",
+    "fsetid",
+    "kill",
+    "setgid",
+    "setuid",
+    "setpcap",
+    "linux_immutable",
+    "net_bind_service",
+    "net_broadcast",
+    "net_admin",
+    "net_raw",
+    "ipc_lock",
+    "ipc_owner",
+    "sys_module",
+    "sys_rawio",
+    "sys_chroot",
+    "sys_ptrace",
+    "sys_pacct",
+    "sys_admin",
+    "sys_boot",
+    "sys_nice",
+    "sys_resource",
+    "sys_time",
+    "sys_tty_config",
+    "mknod",
+    "lease",
+    "audit_write",
+    "audit_control",
+    "setfcap",
+    "mac_override",
+    "mac_admin",
+    "syslog",
+    "wake_alarm",
+    "block_suspend",
+    "audit_read",
+    "perfmon",
+    "bpf",
+    "checkpoint_restore",
+];
+
+// The effective set, what the process can use right now
+pub fn read_capabilities(pid: u32) -> Option<u64> {
+    let content = fs::read_to_string(format!("/proc/{}/status", pid)).ok()?;
+    content.lines().find_map(|line| {
+        let value = line.strip_prefix("CapEff:")?;
+        u64::from_str_radix(value.trim(), 16).ok()
+    })
+}
+
+// Root usually holds every capability, so the ones it lacks say more than a
+// list of forty that it has
+pub fn describe_capabilities(mask: u64) -> String {
+    let last = fs::read_to_string("/proc/sys/kernel/cap_last_cap")
+        .ok()
+        .and_then(|last| last.trim().parse::<usize>().ok())
+        .unwrap_or(CAPABILITIES.len() - 1)
+        .min(63);
+    let name = |bit: usize| {
+        CAPABILITIES
+            .get(bit)
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("cap_{}", bit))
+    };
+    let (held, missing): (Vec<usize>, Vec<usize>) =
+        (0..=last).partition(|bit| mask & (1 << bit) != 0);
+    if held.is_empty() {
+        "none".to_string()
+    } else if missing.is_empty() {
+        "all".to_string()
+    } else if missing.len() < held.len() {
+        let names = missing.into_iter().map(name).collect::<Vec<String>>();
+        format!("all but {}", names.join(", "))
+    } else {
+        let names = held.into_iter().map(name).collect::<Vec<String>>();
+        names.join(", ")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct OomScore {
+    // 0..1000, the highest is killed first when memory runs out
+    pub score: i64,
+    pub adjustment: i64,
+}
+
+pub fn read_oom_score(pid: u32) -> Option<OomScore> {
+    let read = |file: &str| {
+        fs::read_to_string(format!("/proc/{}/{}", pid, file))
+            .ok()?
+            .trim()
+            .parse::<i64>()
+            .ok()
+    };
+    Some(OomScore {
+        score: read("oom_score")?,
+        adjustment: read("oom_score_adj")?,
+    })
+}
+
+// The unified hierarchy line is "0::/path". Hybrid hosts leave it at the root
+// and keep the real path on the systemd named hierarchy.
+pub fn read_cgroup(pid: u32) -> Option<String> {
+    let content = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    let path = |controllers: &str| {
+        content.lines().find_map(|line| {
+            let (_, rest) = line.split_once(':')?;
+            let (names, path) = rest.split_once(':')?;
+            (names == controllers).then(|| path.to_string())
+        })
+    };
+    let unified = path("");
+    unified
+        .clone()
+        .filter(|path| path != "/")
+        .or_else(|| path("name=systemd"))
+        .or(unified)
+}
+
+// What the process table reads from /proc for every process on every sample
+#[derive(Debug, Clone, Default)]
+pub struct Extras {
+    pub stat: Option<Stat>,
+    pub swap: Option<u64>,
+    pub oom: Option<OomScore>,
+    pub cgroup: Option<String>,
+}
+
+// The OOM score and cgroup rarely change, so they're only read again every
+// few seconds or when the pid turns out to belong to a new process
+const SLOW_REFRESH: Duration = Duration::from_secs(5);
+
+struct Slow {
+    start_time: u64,
+    read: Instant,
+    oom: Option<OomScore>,
+    cgroup: Option<String>,
+}
+
+#[derive(Default)]
+pub struct ExtrasCache {
+    slow: HashMap<u32, Slow>,
+}
+
+impl ExtrasCache {
+    // Walks /proc itself rather than taking sysinfo's list, so it can run
+    // without the sampled state to hand
+    pub fn read(&mut self) -> HashMap<u32, Extras> {
+        let now = Instant::now();
+        let pids = fs::read_dir("/proc")
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok());
+        let mut extras = HashMap::new();
+        for pid in pids {
+            let stat = read_stat(pid);
+            let start_time = stat.map_or(0, |stat| stat.start_time);
+            let fresh = self.slow.get(&pid).is_some_and(|slow| {
+                slow.start_time == start_time && now.duration_since(slow.read) < SLOW_REFRESH
+            });
+            if !fresh {
+                let slow = Slow {
+                    start_time,
+                    read: now,
+                    oom: read_oom_score(pid),
+                    cgroup: read_cgroup(pid),
+                };
+                self.slow.insert(pid, slow);
+            }
+            let slow = &self.slow[&pid];
+            let entry = Extras {
+                stat,
+                swap: read_swap(pid),
+                oom: slow.oom,
+                cgroup: slow.cgroup.clone(),
+            };
+            extras.insert(pid, entry);
+        }
+        // Pids get reused, so exited processes are forgotten
+        self.slow.retain(|pid, _| extras.contains_key(pid));
+        extras
+    }
+}
+
+// The innermost systemd unit in a cgroup path, e.g. nginx.service for
+// /system.slice/nginx.service, or the path itself outside of systemd
+pub fn unit_name(cgroup: &str) -> String {
+    cgroup
+        .rsplit('/')
+        .find(|part| {
+            [".service", ".scope", ".slice"]
+                .iter()
+                .any(|suffix| part.ends_with(suffix))
+        })
+        .unwrap_or(cgroup)
+        .to_string()
+}
@@ -0,0 +1,56 @@
+// Named setups of the interface, saved with `:profile save <name>` and
+// switched to with `:profile <name>`. They're kept as JSON in the state
+// directory, next to the persisted history.
+
+use crate::{columns::Column, history::TimeWindow, persist, preset::Preset};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Profile {
+    // Layout
+    pub preset: Option<Preset>,
+    pub per_core: bool,
+    pub window: TimeWindow,
+    pub columns: Vec<Column>,
+    pub tree: bool,
+    pub aggregate: bool,
+    pub group_units: bool,
+    // Column and whether it's descending
+    pub sort: Option<(Column, bool)>,
+    // Filters
+    pub process_query: String,
+    pub user_filter: Option<String>,
+    // Milliseconds between samples
+    pub refresh_interval: u64,
+    pub freeze_while_navigating: bool,
+}
+
+// Names end up as file names, so they're kept to letters, digits, - and _
+fn path(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("profile names can only use letters, digits, - and _");
+    }
+    let dir = persist::state_dir().context("no state directory, HOME isn't set")?;
+    Ok(dir.join("profiles").join(format!("{}.json", name)))
+}
+
+pub fn save(name: &str, profile: &Profile) -> Result<()> {
+    let path = path(name)?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(profile)?)?;
+    Ok(())
+}
+
+pub fn load(name: &str) -> Result<Profile> {
+    let content =
+        fs::read_to_string(path(name)?).with_context(|| format!("no profile named `{}`", name))?;
+    serde_json::from_str(&content).with_context(|| format!("profile `{}` is unreadable", name))
+}
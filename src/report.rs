@@ -0,0 +1,190 @@
+// Output for the modes without the interface: a plain text snapshot for
+// --once, laid out like the header of `top -b -n1`, JSON lines for --stream
+// and sentences for --plain
+
+use crate::{
+    columns::{Column, Units},
+    format_uptime, memory_to_gb, oom_alert, ProcessData, SystemData,
+};
+use chrono::Local;
+use serde::Serialize;
+
+pub fn snapshot(system_data: &SystemData, columns: &[Column], units: Units, top: usize) -> String {
+    let mut lines = vec![format!(
+        "{}  up {}",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        format_uptime(system_data.uptime)
+    )];
+
+    let mut environment = Vec::new();
+    if let Some(virtualization) = &system_data.virtualization {
+        environment.push(format!("Guest: {}", virtualization));
+    }
+    if let Some(container) = &system_data.container {
+        environment.push(format!("Container: {}", container));
+    }
+    if !environment.is_empty() {
+        lines.push(environment.join("  "));
+    }
+    let firing = system_data.alerts.firing();
+    if !firing.is_empty() {
+        lines.push(format!("Alert: {}", firing.join(", ")));
+    }
+
+    let memory_percent = if system_data.total_memory > 0.0 {
+        system_data.used_memory / system_data.total_memory * 100.0
+    } else {
+        0.0
+    };
+    lines.push(format!(
+        "CPU: {:.2}%  Memory: {} / {} ({:.2}%)",
+        system_data.cpu_usage,
+        memory_to_gb(&system_data.used_memory, units),
+        memory_to_gb(&system_data.total_memory, units),
+        memory_percent
+    ));
+    lines.push(String::new());
+
+    let rows = std::iter::once(columns.iter().map(|c| c.title().to_string()).collect())
+        .chain(
+            system_data
+                .processes
+                .iter()
+                .take(top)
+                .map(|process| columns.iter().map(|c| c.value(process, units)).collect()),
+        )
+        .collect::<Vec<Vec<String>>>();
+    let widths = (0..columns.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<usize>>();
+    for row in rows {
+        let cells = row
+            .iter()
+            .zip(widths.iter())
+            .map(|(value, width)| format!("{:<width$}", value, width = width))
+            .collect::<Vec<String>>();
+        lines.push(cells.join(" ").trim_end().to_string());
+    }
+
+    lines.join("\n")
+}
+
+#[derive(Serialize)]
+struct Sample<'a> {
+    // Unix seconds, the same timeline as the charts
+    time: f64,
+    uptime: u64,
+    cpu_usage: f64,
+    steal_usage: f64,
+    memory_used: u64,
+    memory_total: u64,
+    memory_pressure: Option<f64>,
+    packets_received: u64,
+    packets_transmitted: u64,
+    // Bytes per second
+    received_rate: Option<f64>,
+    transmitted_rate: Option<f64>,
+    // Names of the configured alerts currently firing
+    alerts: Vec<&'a str>,
+    processes: Vec<ProcessSample<'a>>,
+}
+
+#[derive(Serialize)]
+struct ProcessSample<'a> {
+    pid: u32,
+    parent: Option<u32>,
+    name: &'a str,
+    user: &'a str,
+    state: &'a str,
+    cpu_usage: f32,
+    memory: u64,
+    swap: Option<u64>,
+    read_rate: f64,
+    write_rate: f64,
+    command: &'a str,
+}
+
+impl<'a> From<&'a ProcessData> for ProcessSample<'a> {
+    fn from(process: &'a ProcessData) -> Self {
+        ProcessSample {
+            pid: process.pid,
+            parent: process.parent,
+            name: &process.name,
+            user: &process.user,
+            state: &process.state,
+            cpu_usage: process.cpu_usage,
+            memory: process.memory,
+            swap: process.swap,
+            read_rate: process.read_rate,
+            write_rate: process.write_rate,
+            command: &process.command,
+        }
+    }
+}
+
+// One line of --stream output, without the trailing newline
+pub fn json_line(system_data: &SystemData, top: usize) -> serde_json::Result<String> {
+    let sample = Sample {
+        time: Local::now().timestamp_millis() as f64 / 1000.0,
+        uptime: system_data.uptime,
+        cpu_usage: system_data.cpu_usage,
+        steal_usage: system_data.steal_usage,
+        memory_used: system_data.used_memory as u64,
+        memory_total: system_data.total_memory as u64,
+        memory_pressure: system_data.memory_pressure,
+        packets_received: system_data.packets[0].1,
+        packets_transmitted: system_data.packets[1].1,
+        received_rate: system_data.network_history[0].latest(),
+        transmitted_rate: system_data.network_history[1].latest(),
+        alerts: system_data.alerts.firing(),
+        processes: system_data
+            .processes
+            .iter()
+            .take(top)
+            .map(ProcessSample::from)
+            .collect(),
+    };
+    serde_json::to_string(&sample)
+}
+
+// One line per sample for --plain. Written to be read aloud: no tables or
+// symbols beyond percent signs, and the least important part last.
+pub fn sentence(system_data: &SystemData, top: usize, units: Units) -> String {
+    let memory_percent = if system_data.total_memory > 0.0 {
+        system_data.used_memory / system_data.total_memory * 100.0
+    } else {
+        0.0
+    };
+    let mut sentences = vec![
+        Local::now().format("%H:%M:%S.").to_string(),
+        format!("CPU {:.0}%.", system_data.cpu_usage),
+        format!(
+            "Memory {:.0}%, {} of {}.",
+            memory_percent,
+            memory_to_gb(&system_data.used_memory, units),
+            memory_to_gb(&system_data.total_memory, units)
+        ),
+    ];
+    if let Some(alert) = oom_alert(system_data) {
+        sentences.push(format!("{}.", alert));
+    }
+    let firing = system_data.alerts.firing();
+    if !firing.is_empty() {
+        sentences.push(format!("Alert: {}.", firing.join(", ")));
+    }
+    let busiest = system_data
+        .processes
+        .iter()
+        .take(top)
+        .map(|process| format!("{} {:.0}%", process.name, process.cpu_usage))
+        .collect::<Vec<String>>();
+    if !busiest.is_empty() {
+        sentences.push(format!("Busiest: {}.", busiest.join(", ")));
+    }
+    sentences.join(" ")
+}
@@ -0,0 +1,136 @@
+use crate::config::SensorConfig;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug, Clone)]
+pub struct Sensor {
+    // Chip and input, e.g. "nct6775/temp3", which is what the config refers to
+    pub id: String,
+    // The config's name, else the kernel's label, else the id
+    pub name: String,
+    pub celsius: f64,
+    pub warning: Option<f64>,
+    pub critical: Option<f64>,
+}
+
+// hwmon reports millidegrees
+pub fn read_celsius(path: &Path) -> Option<f64> {
+    let value = fs::read_to_string(path).ok()?;
+    value.trim().parse::<f64>().ok().map(|m| m / 1000.0)
+}
+
+fn read_chip(dir: &Path) -> Vec<Sensor> {
+    let chip = match fs::read_to_string(dir.join("name")) {
+        Ok(name) => name.trim().to_string(),
+        Err(_) => return Vec::new(),
+    };
+    let mut inputs = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let file = entry.file_name().to_string_lossy().into_owned();
+                    let input = file.strip_suffix("_input")?.to_string();
+                    input.starts_with("temp").then_some(input)
+                })
+                .collect::<Vec<String>>()
+        })
+        .unwrap_or_default();
+    // temp10 after temp9
+    inputs.sort_by_key(|input| input[4..].parse::<u32>().unwrap_or(u32::MAX));
+
+    inputs
+        .into_iter()
+        .filter_map(|input| {
+            let id = format!("{}/{}", chip, input);
+            let label = fs::read_to_string(dir.join(format!("{}_label", input)))
+                .map(|label| label.trim().to_string())
+                .unwrap_or_else(|_| id.clone());
+            Some(Sensor {
+                name: label,
+                celsius: read_celsius(&dir.join(format!("{}_input", input)))?,
+                warning: read_celsius(&dir.join(format!("{}_max", input))),
+                critical: read_celsius(&dir.join(format!("{}_crit", input))),
+                id,
+            })
+        })
+        .collect()
+}
+
+fn hwmon_dirs() -> Vec<PathBuf> {
+    let mut dirs = fs::read_dir("/sys/class/hwmon")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+pub fn read_sensors() -> Vec<Sensor> {
+    hwmon_dirs().iter().flat_map(|dir| read_chip(dir)).collect()
+}
+
+// Chips that measure the CPU itself: Intel, AMD and most ARM boards
+const CPU_CHIPS: [&str; 4] = ["coretemp", "k10temp", "zenpower", "cpu_thermal"];
+
+// Read on every sample, so other chips aren't touched: some of them, like
+// drivetemp, talk to the device to answer
+pub fn read_cpu_sensors() -> Vec<Sensor> {
+    hwmon_dirs()
+        .iter()
+        .filter(|dir| {
+            fs::read_to_string(dir.join("name")).is_ok_and(|name| CPU_CHIPS.contains(&name.trim()))
+        })
+        .flat_map(|dir| read_chip(dir))
+        .collect()
+}
+
+// Intel's coretemp labels one input per physical core, "Core 0", "Core 1"...
+// AMD's k10temp only reports per die, so there's nothing per core there.
+pub fn is_core(sensor: &Sensor) -> bool {
+    sensor.id.starts_with("coretemp/") && sensor.name.starts_with("Core ")
+}
+
+// The whole package, what throttling goes by. Tctl on AMD carries an offset
+// on some models, Tdie doesn't, so that's preferred where both exist.
+pub fn package_temperature(sensors: &[Sensor]) -> Option<f64> {
+    ["Package id 0", "Tdie", "Tctl"]
+        .iter()
+        .find_map(|label| sensors.iter().find(|sensor| sensor.name == *label))
+        .or_else(|| {
+            sensors
+                .iter()
+                .find(|sensor| sensor.id.starts_with("cpu_thermal/"))
+        })
+        .map(|sensor| sensor.celsius)
+}
+
+// Renames, hides and sets thresholds from the config. A rule matches either
+// the id or the kernel's label, the first matching rule wins.
+pub fn apply_config(sensors: Vec<Sensor>, config: &[SensorConfig]) -> Vec<Sensor> {
+    sensors
+        .into_iter()
+        .filter_map(|mut sensor| {
+            let rule = config
+                .iter()
+                .find(|rule| rule.sensor == sensor.id || rule.sensor == sensor.name);
+            if let Some(rule) = rule {
+                if rule.hide {
+                    return None;
+                }
+                if let Some(name) = &rule.name {
+                    sensor.name = name.clone();
+                }
+                if rule.warn.is_some() {
+                    sensor.warning = rule.warn;
+                }
+            }
+            Some(sensor)
+        })
+        .collect()
+}
@@ -0,0 +1,43 @@
+// The state of the interface, written every few seconds with --resume so the
+// next start after a quit, crash or reboot picks up where this one was
+
+use crate::{persist, profile::Profile, AppView};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub profile: Profile,
+    // PIDs get reused, so each comes with its process's start time and is
+    // only marked again while that's still the same process
+    pub marked: Vec<(u32, u64)>,
+    pub selected: Option<(u32, u64)>,
+    // Missing from sessions saved before there were tabs
+    #[serde(default)]
+    pub view: AppView,
+}
+
+fn path() -> Result<PathBuf> {
+    let dir = persist::state_dir().context("no state directory, HOME isn't set")?;
+    Ok(dir.join("session.json"))
+}
+
+// A missing or unreadable file just means starting fresh
+pub fn load() -> Option<Session> {
+    let content = fs::read_to_string(path().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+// Written next to the old one and renamed over it, a crash part way through
+// leaves the previous session rather than half of this one
+pub fn save(session: &Session) -> Result<()> {
+    let path = path()?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let partial = path.with_extension("json.partial");
+    fs::write(&partial, serde_json::to_string(session)?)?;
+    fs::rename(&partial, &path)?;
+    Ok(())
+}
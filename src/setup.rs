@@ -0,0 +1,114 @@
+// A few questions on the first start in a terminal, while there's no config
+// file yet. The answers are written as the config file, which also keeps the
+// questions from coming back.
+
+use crate::{
+    config::{Panel, PANELS},
+    palette::{Theme, THEMES},
+};
+use anyhow::Result;
+use std::{
+    fs,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+const REFRESH_CHOICES: [(u64, &str); 4] = [
+    (100, "10 times a second"),
+    (500, "twice a second"),
+    (1000, "every second"),
+    (2000, "every 2 seconds"),
+];
+
+pub fn run(path: &Path) -> Result<()> {
+    let mut lines = io::stdin().lock().lines();
+    // Closed input answers everything with the default
+    let mut ask = |question: &str| -> Result<String> {
+        print!("{} ", question);
+        io::stdout().flush()?;
+        let answer = lines.next().transpose()?.unwrap_or_default();
+        Ok(answer.trim().to_lowercase())
+    };
+
+    println!("There's no config file at {} yet.", path.display());
+    if ask("Answer three questions to set one up? [Y/n]")?.starts_with('n') {
+        return write(path, Theme::Dark, REFRESH_CHOICES[0].0, &PANELS);
+    }
+
+    let theme = pick(&mut ask, "Colors", &THEMES.map(Theme::name))?;
+    let refresh = pick(
+        &mut ask,
+        "Refresh",
+        &REFRESH_CHOICES.map(|(_, label)| label),
+    )?;
+
+    let listed = PANELS
+        .iter()
+        .enumerate()
+        .map(|(index, panel)| format!("{}) {}", index + 1, panel.name()))
+        .collect::<Vec<String>>();
+    println!("Panels: {}", listed.join("  "));
+    let hidden = ask("Numbers of any to hide, separated by spaces [none]:")?
+        .split_whitespace()
+        .filter_map(|number| number.parse::<usize>().ok())
+        .collect::<Vec<usize>>();
+    let mut panels = PANELS
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !hidden.contains(&(index + 1)))
+        .map(|(_, panel)| *panel)
+        .collect::<Vec<Panel>>();
+    if panels.is_empty() {
+        println!("Not hiding everything, keeping all of them");
+        panels = PANELS.to_vec();
+    }
+
+    write(path, THEMES[theme], REFRESH_CHOICES[refresh].0, &panels)?;
+    println!("Saved, it can be edited there later.");
+    Ok(())
+}
+
+// Index of the chosen option, the first one unless a valid number is given
+fn pick(
+    ask: &mut impl FnMut(&str) -> Result<String>,
+    name: &str,
+    options: &[&str],
+) -> Result<usize> {
+    let listed = options
+        .iter()
+        .enumerate()
+        .map(|(index, option)| format!("{}) {}", index + 1, option))
+        .collect::<Vec<String>>();
+    println!("{}: {}", name, listed.join("  "));
+    let answer = ask("Number [1]:")?;
+    Ok(answer
+        .parse::<usize>()
+        .ok()
+        .filter(|number| (1..=options.len()).contains(number))
+        .map_or(0, |number| number - 1))
+}
+
+fn write(path: &Path, theme: Theme, refresh_ms: u64, panels: &[Panel]) -> Result<()> {
+    let panels = panels
+        .iter()
+        .map(|panel| format!("\"{}\"", panel.name()))
+        .collect::<Vec<String>>();
+    let content = format!(
+        "# Written on the first start, the readme lists what else can be set here\n\
+         [interface]\n\
+         # dark, light or mono\n\
+         theme = \"{}\"\n\
+         # Milliseconds between samples\n\
+         refresh_ms = {}\n\
+         # Any of: cpu, memory, io, processes, network, disks\n\
+         panels = [{}]\n",
+        theme.name(),
+        refresh_ms,
+        panels.join(", ")
+    );
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, content)?;
+    Ok(())
+}
@@ -0,0 +1,352 @@
+use crate::{history::History, sensors::read_celsius};
+use std::{
+    collections::VecDeque,
+    ffi::CString,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PowerState {
+    Active,
+    Standby,
+    Sleeping,
+}
+
+impl PowerState {
+    pub fn label(self) -> &'static str {
+        match self {
+            PowerState::Active => "active",
+            PowerState::Standby => "standby",
+            PowerState::Sleeping => "sleeping",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Disk {
+    pub name: String,
+    pub model: Option<String>,
+    // Bytes
+    pub size: u64,
+    pub rotational: bool,
+    pub temperature: Option<f64>,
+    // Only asked of spinning disks
+    pub power: Option<PowerState>,
+}
+
+fn read_trimmed(path: impl AsRef<Path>) -> Option<String> {
+    let value = fs::read_to_string(path).ok()?;
+    Some(value.trim().to_string()).filter(|value| !value.is_empty())
+}
+
+// SATA disks get a hwmon device from the drivetemp driver under
+// device/hwmon/, NVMe controllers have theirs directly in device/
+fn read_temperature(device: &Path) -> Option<f64> {
+    let mut dirs = Vec::new();
+    for dir in [device.to_path_buf(), device.join("hwmon")] {
+        if let Ok(entries) = fs::read_dir(&dir) {
+            dirs.extend(
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        path.file_name()
+                            .is_some_and(|name| name.to_string_lossy().starts_with("hwmon"))
+                    }),
+            );
+        }
+    }
+    dirs.iter()
+        .find_map(|dir| read_celsius(&dir.join("temp1_input")))
+}
+
+// hdparm's CHECK POWER MODE answers without spinning the disk up, unlike
+// reading SMART data would. Needs root and hdparm, None otherwise.
+fn read_power_state(name: &str) -> Option<PowerState> {
+    let output = Command::new("hdparm")
+        .args(["-C", &format!("/dev/{}", name)])
+        .stderr(Stdio::null())
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let state = text
+        .lines()
+        .find_map(|line| line.split_once("drive state is:"))?
+        .1;
+    match state.trim() {
+        "standby" => Some(PowerState::Standby),
+        "sleeping" => Some(PowerState::Sleeping),
+        "unknown" => None,
+        _ => Some(PowerState::Active),
+    }
+}
+
+// Loop devices and RAM backed disks
+fn is_virtual(name: &str) -> bool {
+    ["loop", "ram", "zram"]
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+}
+
+// Whole disks, without loop devices and RAM backed ones
+pub fn read_disks() -> Vec<Disk> {
+    let mut paths = fs::read_dir("/sys/block")
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect::<Vec<PathBuf>>()
+        })
+        .unwrap_or_default();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let name = path.file_name()?.to_string_lossy().into_owned();
+            if is_virtual(&name) {
+                return None;
+            }
+            let sectors = read_trimmed(path.join("size"))?.parse::<u64>().ok()?;
+            if sectors == 0 {
+                return None;
+            }
+            let rotational = read_trimmed(path.join("queue/rotational")).as_deref() == Some("1");
+            let device = path.join("device");
+            Some(Disk {
+                model: read_trimmed(device.join("model")),
+                // Always in 512 byte sectors, whatever the disk's own sector size
+                size: sectors * 512,
+                rotational,
+                temperature: read_temperature(&device),
+                power: if rotational {
+                    read_power_state(&name)
+                } else {
+                    None
+                },
+                name,
+            })
+        })
+        .collect()
+}
+
+// tmpfs is backed by memory (and swap), so what's stored there counts
+// against RAM rather than any disk. sysinfo leaves it out of its disks.
+#[derive(Debug, Clone)]
+pub struct Tmpfs {
+    pub mount: String,
+    // Bytes
+    pub total: u64,
+    pub available: u64,
+}
+
+fn statvfs(mount: &str) -> Option<(u64, u64)> {
+    let path = CString::new(mount).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+pub fn read_tmpfs() -> Vec<Tmpfs> {
+    let content = fs::read_to_string("/proc/mounts").unwrap_or_default();
+    let mut mounts: Vec<Tmpfs> = Vec::new();
+    for line in content.lines() {
+        let fields = line.split_whitespace().collect::<Vec<&str>>();
+        if fields.get(2) != Some(&"tmpfs") {
+            continue;
+        }
+        // Spaces in mount points are escaped as octal
+        let mount = fields[1].replace("\\040", " ");
+        // Mounted over more than once when namespaces share it
+        if mounts.iter().any(|tmpfs| tmpfs.mount == mount) {
+            continue;
+        }
+        if let Some((total, available)) = statvfs(&mount) {
+            mounts.push(Tmpfs {
+                mount,
+                total,
+                available,
+            });
+        }
+    }
+    mounts
+}
+
+// Growth is taken over up to this many seconds of samples
+const GROWTH_WINDOW: f64 = 60.0 * 60.0;
+// Less than this much history says little about the trend
+const GROWTH_MIN_SPAN: f64 = 10.0 * 60.0;
+// Sampling space more often than this adds nothing
+const GROWTH_SAMPLE_SECONDS: f64 = 10.0;
+
+#[derive(Debug, Clone)]
+pub struct Filesystem {
+    pub mount: String,
+    pub kind: String,
+    // Bytes
+    pub total: u64,
+    pub available: u64,
+    // Unix seconds and used bytes
+    samples: VecDeque<(f64, u64)>,
+}
+
+impl Filesystem {
+    pub fn new(mount: String, kind: String) -> Self {
+        Filesystem {
+            mount,
+            kind,
+            total: 0,
+            available: 0,
+            samples: VecDeque::new(),
+        }
+    }
+
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    pub fn record(&mut self, time: f64, total: u64, available: u64) {
+        self.total = total;
+        self.available = available;
+        if self
+            .samples
+            .back()
+            .is_some_and(|(last, _)| time - last < GROWTH_SAMPLE_SECONDS)
+        {
+            return;
+        }
+        self.samples.push_back((time, self.used()));
+        while self
+            .samples
+            .front()
+            .is_some_and(|(first, _)| time - first > GROWTH_WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    // Bytes per day, from the oldest and newest sample in the window
+    pub fn growth_per_day(&self) -> Option<f64> {
+        let (first, last) = (self.samples.front()?, self.samples.back()?);
+        let span = last.0 - first.0;
+        if span < GROWTH_MIN_SPAN {
+            return None;
+        }
+        Some((last.1 as f64 - first.1 as f64) / span * 86400.0)
+    }
+
+    // None while the filesystem isn't growing, or there's too little history
+    pub fn days_until_full(&self) -> Option<f64> {
+        let growth = self.growth_per_day().filter(|growth| *growth > 0.0)?;
+        Some(self.available as f64 / growth)
+    }
+}
+
+// Totals since boot for one disk from /proc/diskstats
+#[derive(Debug, Clone, Copy)]
+pub struct IoCounters {
+    pub reads: u64,
+    pub read_sectors: u64,
+    pub read_ms: u64,
+    pub writes: u64,
+    pub write_sectors: u64,
+    pub write_ms: u64,
+}
+
+// diskstats counts in 512 byte sectors whatever the disk's own sector size
+const SECTOR_BYTES: f64 = 512.0;
+
+// Whole disks only, partitions share their disk's queue
+pub fn read_io_counters() -> Vec<(String, IoCounters)> {
+    let content = fs::read_to_string("/proc/diskstats").unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            let name = *fields.get(2)?;
+            if is_virtual(name) || !Path::new("/sys/block").join(name).exists() {
+                return None;
+            }
+            let field = |index: usize| fields.get(index)?.parse::<u64>().ok();
+            Some((
+                name.to_string(),
+                IoCounters {
+                    reads: field(3)?,
+                    read_sectors: field(5)?,
+                    read_ms: field(6)?,
+                    writes: field(7)?,
+                    write_sectors: field(9)?,
+                    write_ms: field(10)?,
+                },
+            ))
+        })
+        .collect()
+}
+
+// Average time per completed request, which rises long before throughput
+// shows a disk is saturated
+#[derive(Debug)]
+pub struct DiskLatency {
+    pub name: String,
+    counters: IoCounters,
+    // Milliseconds, only for refreshes that completed requests
+    pub read: History,
+    pub write: History,
+    pub last_read: Option<f64>,
+    pub last_write: Option<f64>,
+    // Bytes per second since the previous refresh
+    pub read_rate: f64,
+    pub write_rate: f64,
+}
+
+impl DiskLatency {
+    pub fn new(name: String, counters: IoCounters) -> Self {
+        DiskLatency {
+            name,
+            counters,
+            read: History::default(),
+            write: History::default(),
+            last_read: None,
+            last_write: None,
+            read_rate: 0.0,
+            write_rate: 0.0,
+        }
+    }
+
+    pub fn record(&mut self, time: f64, elapsed: f64, counters: IoCounters) {
+        let average = |requests: u64, before: u64, ms: u64, ms_before: u64| {
+            let requests = requests.saturating_sub(before);
+            (requests > 0).then(|| ms.saturating_sub(ms_before) as f64 / requests as f64)
+        };
+        let previous = self.counters;
+        self.last_read = average(
+            counters.reads,
+            previous.reads,
+            counters.read_ms,
+            previous.read_ms,
+        );
+        self.last_write = average(
+            counters.writes,
+            previous.writes,
+            counters.write_ms,
+            previous.write_ms,
+        );
+        if let Some(read) = self.last_read {
+            self.read.push(time, read);
+        }
+        if let Some(write) = self.last_write {
+            self.write.push(time, write);
+        }
+        if elapsed > 0.0 {
+            let rate =
+                |now: u64, before: u64| now.saturating_sub(before) as f64 * SECTOR_BYTES / elapsed;
+            self.read_rate = rate(counters.read_sectors, previous.read_sectors);
+            self.write_rate = rate(counters.write_sectors, previous.write_sectors);
+        }
+        self.counters = counters;
+    }
+}
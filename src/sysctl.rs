@@ -0,0 +1,72 @@
+use std::fs;
+
+// Capacity related tunables, shown next to the metrics they affect
+const INTERESTING_SYSCTLS: [(&str, &str); 24] = [
+    ("vm.swappiness", "Tendency to swap out anonymous memory"),
+    ("vm.overcommit_memory", "Memory overcommit policy"),
+    ("vm.overcommit_ratio", "Overcommit limit as % of RAM"),
+    ("vm.dirty_ratio", "% of RAM dirty before writers block"),
+    (
+        "vm.dirty_background_ratio",
+        "% of RAM dirty before flushing",
+    ),
+    ("vm.min_free_kbytes", "Memory kept free for the kernel"),
+    ("vm.max_map_count", "Max memory mappings per process"),
+    ("vm.vfs_cache_pressure", "Tendency to reclaim inode caches"),
+    ("fs.file-max", "System wide open file limit"),
+    ("fs.file-nr", "Allocated, free and max file handles"),
+    ("fs.nr_open", "Max open files per process"),
+    ("fs.inotify.max_user_watches", "Inotify watches per user"),
+    (
+        "fs.inotify.max_user_instances",
+        "Inotify instances per user",
+    ),
+    ("kernel.pid_max", "Highest PID before wrapping"),
+    ("kernel.threads-max", "System wide thread limit"),
+    ("net.core.somaxconn", "Max listen backlog"),
+    (
+        "net.core.netdev_max_backlog",
+        "Max queued packets per interface",
+    ),
+    ("net.core.rmem_max", "Max socket receive buffer"),
+    ("net.core.wmem_max", "Max socket send buffer"),
+    ("net.ipv4.tcp_max_syn_backlog", "Max half open connections"),
+    ("net.ipv4.ip_local_port_range", "Ephemeral port range"),
+    (
+        "net.ipv4.tcp_fin_timeout",
+        "Seconds sockets stay in FIN_WAIT_2",
+    ),
+    ("net.ipv4.tcp_tw_reuse", "Reuse TIME_WAIT sockets"),
+    ("net.netfilter.nf_conntrack_max", "Conntrack table size"),
+];
+
+#[derive(Debug)]
+pub struct Sysctl {
+    pub name: &'static str,
+    pub value: String,
+    pub description: &'static str,
+}
+
+pub fn read_sysctl(name: &str) -> Option<String> {
+    let path = format!("/proc/sys/{}", name.replace('.', "/"));
+    let value = fs::read_to_string(path).ok()?;
+    // Multi value entries are tab separated, e.g. ip_local_port_range
+    Some(value.split_whitespace().collect::<Vec<&str>>().join(" "))
+}
+
+pub fn read_interesting_sysctls() -> Vec<Sysctl> {
+    INTERESTING_SYSCTLS
+        .iter()
+        .map(|(name, description)| Sysctl {
+            name,
+            value: read_sysctl(name).unwrap_or_else(|| "n/a".to_string()),
+            description,
+        })
+        .collect()
+}
+
+pub fn matches_query(sysctl: &Sysctl, query: &str) -> bool {
+    let query = query.to_lowercase();
+    sysctl.name.to_lowercase().contains(&query)
+        || sysctl.description.to_lowercase().contains(&query)
+}
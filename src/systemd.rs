@@ -0,0 +1,168 @@
+// Failed units and sleep inhibitors, polled with systemctl and busctl so
+// there's no D-Bus dependency. Also the two bits of the service protocol the
+// daemon speaks when systemd runs it: socket activation and readiness.
+
+use serde::Deserialize;
+use std::{
+    os::{
+        fd::{FromRawFd, RawFd},
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram, UnixListener},
+    },
+    path::Path,
+    process::Stdio,
+    time::Duration,
+};
+use tokio::process::Command;
+
+// Passed sockets start right after stdin, stdout and stderr
+const LISTEN_FDS_START: RawFd = 3;
+
+// Units rarely fail and systemctl isn't free to run
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+// Inhibitors come and go with video playback, downloads and the like
+pub const INHIBITOR_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct FailedUnit {
+    pub name: String,
+    // e.g. "failed" or "auto-restart"
+    pub sub: String,
+    pub description: String,
+}
+
+// The same check sd_booted() makes
+pub fn is_running() -> bool {
+    Path::new("/run/systemd/system").exists()
+}
+
+// The socket systemd opened for us when started by a .socket unit, as
+// sd_listen_fds() finds it. Only the first one is used. The variables are
+// unset like sd_listen_fds(1) does, so children like hdparm and systemctl
+// don't think the socket is theirs, which is only sound before any other
+// threads are started.
+pub fn listener() -> Option<UnixListener> {
+    let pid = std::env::var("LISTEN_PID").ok();
+    let count = std::env::var("LISTEN_FDS").ok();
+    for name in ["LISTEN_PID", "LISTEN_FDS", "LISTEN_FDNAMES"] {
+        std::env::remove_var(name);
+    }
+    let pid = pid?.parse::<u32>().ok()?;
+    let count = count?.parse::<u32>().ok()?;
+    if pid != std::process::id() || count == 0 {
+        return None;
+    }
+    // Owned by nothing else in this process, systemd handed it over
+    Some(unsafe { UnixListener::from_raw_fd(LISTEN_FDS_START) })
+}
+
+// Tells the service manager about a state change like READY=1, as
+// sd_notify() does. Does nothing unless it runs as a Type=notify service.
+pub fn notify(state: &str) {
+    let socket = match std::env::var_os("NOTIFY_SOCKET") {
+        Some(socket) => socket,
+        None => return,
+    };
+    let socket = socket.to_string_lossy();
+    let address = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes()),
+        None => SocketAddr::from_pathname(socket.as_ref()),
+    };
+    if let (Ok(address), Ok(datagram)) = (address, UnixDatagram::unbound()) {
+        let _ = datagram.send_to_addr(state.as_bytes(), &address);
+    }
+}
+
+// None when systemctl can't be run or fails, e.g. without a system bus
+pub async fn failed_units() -> Option<Vec<FailedUnit>> {
+    let output = Command::new("systemctl")
+        .args([
+            "list-units",
+            "--state=failed",
+            "--plain",
+            "--no-legend",
+            "--no-pager",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    // UNIT LOAD ACTIVE SUB DESCRIPTION, the description can have spaces
+    let units = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?.to_string();
+            let sub = fields.nth(2)?.to_string();
+            Some(FailedUnit {
+                name,
+                sub,
+                description: fields.collect::<Vec<&str>>().join(" "),
+            })
+        })
+        .collect();
+    Some(units)
+}
+
+#[derive(Debug, Clone)]
+pub struct Inhibitor {
+    // Colon separated, e.g. "sleep:idle" or "handle-lid-switch"
+    pub what: String,
+    pub who: String,
+    pub why: String,
+    // "block" or "delay"
+    pub mode: String,
+    pub pid: u32,
+}
+
+impl Inhibitor {
+    pub fn blocks_sleep(&self) -> bool {
+        self.mode == "block" && self.what.split(':').any(|what| what == "sleep")
+    }
+}
+
+// what, who, why, mode, uid, pid
+type RawInhibitor = (String, String, String, String, u32, u32);
+
+// busctl --json=short wraps the reply's arguments in "data", ListInhibitors
+// has one: an array of inhibitors
+#[derive(Deserialize)]
+struct InhibitorReply {
+    data: (Vec<RawInhibitor>,),
+}
+
+// None when logind can't be asked, e.g. without a system bus
+pub async fn inhibitors() -> Option<Vec<Inhibitor>> {
+    let output = Command::new("busctl")
+        .args([
+            "call",
+            "--json=short",
+            "org.freedesktop.login1",
+            "/org/freedesktop/login1",
+            "org.freedesktop.login1.Manager",
+            "ListInhibitors",
+        ])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .await
+        .ok()
+        .filter(|output| output.status.success())?;
+    let reply: InhibitorReply = serde_json::from_slice(&output.stdout).ok()?;
+    let inhibitors = reply
+        .data
+        .0
+        .into_iter()
+        .map(|(what, who, why, mode, _, pid)| Inhibitor {
+            what,
+            who,
+            why,
+            mode,
+            pid,
+        })
+        .collect();
+    Some(inhibitors)
+}
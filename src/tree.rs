@@ -0,0 +1,111 @@
+use crate::ProcessData;
+use std::collections::{HashMap, HashSet};
+
+// A process table row in tree order, with the connector drawn before its name
+#[derive(Debug)]
+pub struct TreeRow {
+    pub index: usize,
+    pub prefix: String,
+}
+
+// Children keep the order they have in `processes`, so siblings stay sorted
+fn children(processes: &[&ProcessData]) -> (Vec<usize>, HashMap<u32, Vec<usize>>) {
+    let pids = processes.iter().map(|p| p.pid).collect::<HashSet<u32>>();
+    let mut roots = Vec::new();
+    let mut children: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (index, process) in processes.iter().enumerate() {
+        match process.parent.filter(|parent| pids.contains(parent)) {
+            Some(parent) => children.entry(parent).or_default().push(index),
+            None => roots.push(index),
+        }
+    }
+    (roots, children)
+}
+
+// Processes with children at the top of the tree, used to collapse everything
+pub fn top_level_parents(processes: &[&ProcessData]) -> Vec<u32> {
+    let (roots, children) = children(processes);
+    roots
+        .into_iter()
+        .map(|index| processes[index].pid)
+        .filter(|pid| children.contains_key(pid))
+        .collect()
+}
+
+// Depth first walk that skips the descendants of collapsed processes
+pub fn flatten(processes: &[&ProcessData], collapsed: &HashSet<u32>) -> Vec<TreeRow> {
+    let (roots, children) = children(processes);
+    let mut rows = Vec::new();
+    // (index, indent of the ancestors, connector for this row)
+    let mut stack = roots
+        .into_iter()
+        .rev()
+        .map(|index| (index, String::new(), String::new()))
+        .collect::<Vec<(usize, String, String)>>();
+
+    while let Some((index, indent, connector)) = stack.pop() {
+        let pid = processes[index].pid;
+        let kids = children.get(&pid).map(Vec::as_slice).unwrap_or(&[]);
+        let marker = match (kids.is_empty(), collapsed.contains(&pid)) {
+            (true, _) => "",
+            (false, true) => "+ ",
+            (false, false) => "- ",
+        };
+        rows.push(TreeRow {
+            index,
+            prefix: format!("{}{}{}", indent, connector, marker),
+        });
+
+        if kids.is_empty() || collapsed.contains(&pid) {
+            continue;
+        }
+        let child_indent = match connector.as_str() {
+            "" => indent.clone(),
+            "└─ " => format!("{}   ", indent),
+            _ => format!("{}│  ", indent),
+        };
+        for (n, child) in kids.iter().enumerate().rev() {
+            let connector = if n == kids.len() - 1 {
+                "└─ "
+            } else {
+                "├─ "
+            };
+            stack.push((*child, child_indent.clone(), connector.to_string()));
+        }
+    }
+    rows
+}
+
+// A process plus all of its descendants
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Totals {
+    pub cpu_usage: f32,
+    pub cpu_average: f32,
+    pub cpu_time: f64,
+    pub memory: u64,
+}
+
+pub fn subtree_totals(processes: &[&ProcessData]) -> HashMap<u32, Totals> {
+    let (_, children) = children(processes);
+    let mut totals: HashMap<u32, Totals> = HashMap::new();
+    // Children come before their parents when walking the tree backwards
+    for row in flatten(processes, &HashSet::new()).iter().rev() {
+        let process = processes[row.index];
+        let mut total = Totals {
+            cpu_usage: process.cpu_usage,
+            cpu_average: process.cpu_average,
+            cpu_time: process.cpu_time.unwrap_or(0.0),
+            memory: process.memory,
+        };
+        for child in children.get(&process.pid).map(Vec::as_slice).unwrap_or(&[]) {
+            if let Some(child_total) = totals.get(&processes[*child].pid) {
+                total.cpu_usage += child_total.cpu_usage;
+                total.cpu_average += child_total.cpu_average;
+                total.cpu_time += child_total.cpu_time;
+                total.memory += child_total.memory;
+            }
+        }
+        totals.insert(process.pid, total);
+    }
+    totals
+}